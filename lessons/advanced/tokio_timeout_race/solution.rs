@@ -0,0 +1,6 @@
+use std::future::Future;
+use std::time::Duration;
+
+pub async fn with_deadline<F: Future>(limit: Duration, work: F) -> Option<F::Output> {
+    tokio::time::timeout(limit, work).await.ok()
+}
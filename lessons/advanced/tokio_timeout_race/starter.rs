@@ -0,0 +1,13 @@
+use std::future::Future;
+use std::time::Duration;
+
+/// Await `work`, but give up after `limit`: return `Some(output)` if it
+/// finishes in time and `None` otherwise.
+///
+/// Hint: Use `tokio::time`. The tests run with tokio's clock paused, so a
+/// ten-second sleep takes no real time and the results are exact.
+pub async fn with_deadline<F: Future>(limit: Duration, work: F) -> Option<F::Output> {
+    // Starter placeholder: ignores the deadline.
+    let _ = limit;
+    Some(work.await)
+}
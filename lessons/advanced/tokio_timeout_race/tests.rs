@@ -0,0 +1,29 @@
+use prelude::*;
+
+#[tokio::test(start_paused = true)]
+async fn returns_work_that_finishes_in_time() {
+    let start = Instant::now();
+    let got = crate::with_deadline(Duration::from_secs(2), async {
+        sleep(Duration::from_secs(1)).await;
+        7
+    }).await;
+    assert_eq!(got, Some(7));
+    assert_eq!(start.elapsed(), Duration::from_secs(1));
+}
+
+#[tokio::test(start_paused = true)]
+async fn gives_up_on_slow_work_at_the_deadline() {
+    let start = Instant::now();
+    let got = crate::with_deadline(Duration::from_secs(2), async {
+        sleep(Duration::from_secs(10)).await;
+        7
+    }).await;
+    assert_eq!(got, None);
+    assert_eq!(start.elapsed(), Duration::from_secs(2));
+}
+
+#[tokio::test(start_paused = true)]
+async fn gives_up_on_work_that_never_finishes() {
+    let got = within(Duration::from_secs(60), crate::with_deadline(Duration::from_secs(3), std::future::pending::<u8>())).await;
+    assert_eq!(got, None);
+}
@@ -0,0 +1,10 @@
+use std::slice;
+
+pub fn split_at_mut(values: &mut [i32], mid: usize) -> (&mut [i32], &mut [i32]) {
+    let len = values.len();
+    assert!(mid <= len, "mid {} out of bounds for length {}", mid, len);
+    let ptr = values.as_mut_ptr();
+    // SAFETY: both ranges lie within `values` and don't overlap, and the
+    // returned slices borrow `values` for as long as they live.
+    unsafe { (slice::from_raw_parts_mut(ptr, mid), slice::from_raw_parts_mut(ptr.add(mid), len - mid)) }
+}
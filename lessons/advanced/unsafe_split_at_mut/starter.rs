@@ -0,0 +1,12 @@
+/// Split `values` into two non-overlapping mutable slices at `mid`, like
+/// the standard library's `split_at_mut`. Panic if `mid > values.len()`.
+///
+/// Hint: Safe Rust won't hand out two `&mut` into one slice. Use
+/// `as_mut_ptr` and `std::slice::from_raw_parts_mut` in an `unsafe` block,
+/// and don't call the standard `split_at_mut`. Checks also run under Miri
+/// (when installed), which reports undefined behavior the tests can't see.
+pub fn split_at_mut(values: &mut [i32], mid: usize) -> (&mut [i32], &mut [i32]) {
+    // Starter placeholder for compilation.
+    let _ = (values, mid);
+    (&mut [], &mut [])
+}
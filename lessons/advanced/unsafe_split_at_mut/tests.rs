@@ -0,0 +1,35 @@
+#[test]
+fn splits_in_the_middle() {
+    let mut v = [1, 2, 3, 4, 5];
+    let (a, b) = crate::split_at_mut(&mut v, 2);
+    assert_eq!(a, &[1, 2]);
+    assert_eq!(b, &[3, 4, 5]);
+}
+
+#[test]
+fn halves_are_independent() {
+    let mut v = [1, 2, 3, 4];
+    {
+        let (a, b) = crate::split_at_mut(&mut v, 1);
+        a[0] = 10;
+        b[2] = 40;
+    }
+    assert_eq!(v, [10, 2, 3, 40]);
+}
+
+#[test]
+fn splits_at_the_ends() {
+    let mut v = [7, 8];
+    let (a, b) = crate::split_at_mut(&mut v, 0);
+    assert!(a.is_empty());
+    assert_eq!(b, &[7, 8]);
+    let (a, b) = crate::split_at_mut(&mut v, 2);
+    assert_eq!((a.len(), b.len()), (2, 0));
+}
+
+#[test]
+#[should_panic]
+fn panics_past_the_end() {
+    let mut v = [1, 2, 3];
+    let _ = crate::split_at_mut(&mut v, 4);
+}
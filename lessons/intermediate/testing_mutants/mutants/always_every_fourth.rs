@@ -0,0 +1,4 @@
+// Treats every fourth year as a leap year, centuries included
+pub fn is_leap_year(year: u32) -> bool {
+    year % 4 == 0
+}
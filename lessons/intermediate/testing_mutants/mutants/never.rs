@@ -0,0 +1,4 @@
+// Never reports a leap year
+pub fn is_leap_year(_year: u32) -> bool {
+    false
+}
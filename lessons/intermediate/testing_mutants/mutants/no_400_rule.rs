@@ -0,0 +1,4 @@
+// Forgets that years divisible by 400 are leap years after all
+pub fn is_leap_year(year: u32) -> bool {
+    year % 4 == 0 && year % 100 != 0
+}
@@ -0,0 +1,5 @@
+// Checks the year before
+pub fn is_leap_year(year: u32) -> bool {
+    let year = year.wrapping_sub(1);
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
@@ -0,0 +1,4 @@
+/// Gregorian leap years: divisible by 4, except centuries, except every 400 years.
+pub fn is_leap_year(year: u32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
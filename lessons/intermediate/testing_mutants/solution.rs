@@ -0,0 +1,19 @@
+#[test]
+fn ordinary_year_is_not_leap() {
+    assert!(!crate::is_leap_year(2023));
+}
+
+#[test]
+fn every_fourth_year_is_leap() {
+    assert!(crate::is_leap_year(2024));
+}
+
+#[test]
+fn centuries_are_not_leap() {
+    assert!(!crate::is_leap_year(1900));
+}
+
+#[test]
+fn every_400th_year_is_leap() {
+    assert!(crate::is_leap_year(2000));
+}
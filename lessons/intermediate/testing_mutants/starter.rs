@@ -0,0 +1,10 @@
+// This time the code is done and the tests are yours. `crate::is_leap_year`
+// follows the Gregorian rules: a year divisible by 4 is a leap year, except
+// centuries, except every 400th year.
+//
+// Your tests must pass on the correct version and fail on each buggy one.
+
+#[test]
+fn ordinary_year_is_not_leap() {
+    assert!(!crate::is_leap_year(2023));
+}
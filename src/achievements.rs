@@ -0,0 +1,186 @@
+//! Achievements defined in `<lessons_root>/achievements.toml` and unlocked by
+//! a small rules engine that runs after every check and quiz.
+
+use crate::{
+    exercise::{self, Exercise},
+    persistence::{self, Progress},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{collections::HashSet, fs, path::Path};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AchievementFile {
+    #[serde(default, rename = "achievement")]
+    pub achievements: Vec<Achievement>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub icon: Option<String>,
+    pub condition: Condition,
+}
+
+/// Every field that is set must hold. Lesson counts (`completed`,
+/// `no_hint_solves`, `fast_solves`, `first_try`) only count lessons of
+/// `difficulty` when it is set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    #[serde(default)]
+    pub difficulty: Option<String>,
+    /// Completed lessons.
+    #[serde(default)]
+    pub completed: Option<u32>,
+    /// Share of all lessons completed, 0-100.
+    #[serde(default)]
+    pub completed_percent: Option<u32>,
+    #[serde(default)]
+    pub points: Option<u32>,
+    /// Lessons completed without revealing a hint.
+    #[serde(default)]
+    pub no_hint_solves: Option<u32>,
+    /// Lessons whose best time is at most `within_secs`.
+    #[serde(default)]
+    pub fast_solves: Option<u32>,
+    #[serde(default)]
+    pub within_secs: Option<u64>,
+    /// Lessons passed on the first check.
+    #[serde(default)]
+    pub first_try: Option<u32>,
+    /// Longest streak of days with a passing check.
+    #[serde(default)]
+    pub streak: Option<u32>,
+    /// Quizzes answered fully correct.
+    #[serde(default)]
+    pub quizzes: Option<u32>,
+}
+
+pub fn load(lessons_root: &str) -> Result<Vec<Achievement>> {
+    let p = Path::new(lessons_root).join("achievements.toml");
+    if !p.exists() { return Ok(Vec::new()); }
+    let data = fs::read_to_string(&p).with_context(|| format!("read {}", p.display()))?;
+    let file: AchievementFile = toml::from_str(&data).with_context(|| format!("parse {}", p.display()))?;
+    let mut seen = HashSet::new();
+    for a in &file.achievements {
+        if !seen.insert(a.id.as_str()) { anyhow::bail!("{}: duplicate achievement id '{}'", p.display(), a.id); }
+        if a.condition.fast_solves.is_some() && a.condition.within_secs.is_none() {
+            anyhow::bail!("{}: achievement '{}' sets fast_solves without within_secs", p.display(), a.id);
+        }
+    }
+    Ok(file.achievements)
+}
+
+impl Condition {
+    pub fn holds(&self, prog: &Progress, lessons: &[Exercise]) -> bool {
+        let done = |extra: &dyn Fn(&persistence::ExerciseProgress) -> bool| -> u32 {
+            prog.exercises.iter()
+                .filter(|(_, e)| e.completed && extra(e))
+                .filter(|(id, _)| match &self.difficulty {
+                    Some(d) => lessons.iter().any(|l| &l.meta.id == *id && &l.meta.difficulty == d),
+                    None => true,
+                })
+                .count() as u32
+        };
+        let at_least = |want: Option<u32>, have: &dyn Fn() -> u32| want.is_none_or(|w| have() >= w);
+        let within = self.within_secs.unwrap_or(0);
+        at_least(self.completed, &|| done(&|_| true))
+            && at_least(self.completed_percent, &|| {
+                let total = lessons.len().max(1) as u32;
+                let completed = prog.exercises.iter().filter(|(id, e)| e.completed && lessons.iter().any(|l| &l.meta.id == *id)).count() as u32;
+                completed * 100 / total
+            })
+            && at_least(self.points, &|| prog.total_points)
+            && at_least(self.no_hint_solves, &|| done(&|e| e.hints_at_pass.unwrap_or(e.hints_revealed) == 0))
+            && at_least(self.fast_solves, &|| done(&|e| e.best_duration_secs.is_some_and(|d| d <= within)))
+            && at_least(self.first_try, &|| done(&|e| e.attempts_at_pass == Some(1)))
+            && at_least(self.streak, &|| prog.streak.best)
+            && at_least(self.quizzes, &|| prog.exercises.values().filter(|e| e.quiz_completed).count() as u32)
+    }
+}
+
+/// Achievements not yet unlocked whose condition now holds. Names in the
+/// legacy `badges` list count as unlocked conditions so earned badges carry over.
+pub fn newly_unlocked<'a>(defs: &'a [Achievement], prog: &Progress, lessons: &[Exercise]) -> Vec<&'a Achievement> {
+    defs.iter()
+        .filter(|a| !prog.achievements.contains_key(&a.id))
+        .filter(|a| prog.badges.contains(&a.name) || a.condition.holds(prog, lessons))
+        .collect()
+}
+
+/// Runs the rules and records unlocks at `now`. Returns what was unlocked.
+pub fn evaluate(lessons_root: &str, now: i64) -> Result<Vec<Achievement>> {
+    let defs = load(lessons_root)?;
+    if defs.is_empty() { return Ok(Vec::new()); }
+    let lessons = exercise::load_all(lessons_root)?;
+    persistence::update(|prog| {
+        let unlocked: Vec<Achievement> = newly_unlocked(&defs, prog, &lessons).into_iter().cloned().collect();
+        for a in &unlocked { prog.achievements.insert(a.id.clone(), now); }
+        unlocked
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::ExerciseProgress;
+
+    #[test]
+    fn rules_unlock_once() {
+        let file: AchievementFile = toml::from_str(r#"
+[[achievement]]
+id = "first"
+name = "First Steps"
+description = "Complete a lesson"
+condition = { completed = 1 }
+
+[[achievement]]
+id = "advanced"
+name = "First Advanced"
+description = "Complete an advanced lesson"
+condition = { completed = 1, difficulty = "advanced" }
+
+[[achievement]]
+id = "speedy"
+name = "Speedy"
+description = "Two lessons in under a minute, no hints"
+condition = { fast_solves = 2, within_secs = 60, no_hint_solves = 2 }
+
+[[achievement]]
+id = "century"
+name = "Century"
+description = "100 points"
+condition = { points = 100 }
+"#).unwrap();
+        let defs = file.achievements;
+        let mut prog = Progress::default();
+        prog.exercises.insert("intro/a".into(), ExerciseProgress { completed: true, best_duration_secs: Some(30), ..Default::default() });
+        prog.exercises.insert("intro/b".into(), ExerciseProgress { completed: true, best_duration_secs: Some(90), ..Default::default() });
+        let ids = |xs: Vec<&Achievement>| xs.iter().map(|a| a.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(newly_unlocked(&defs, &prog, &[])), vec!["first"]);
+
+        prog.exercises.get_mut("intro/b").unwrap().best_duration_secs = Some(45);
+        prog.achievements.insert("first".into(), 1);
+        prog.badges.push("Century".into());
+        assert_eq!(ids(newly_unlocked(&defs, &prog, &[])), vec!["speedy", "century"]);
+
+        prog.exercises.get_mut("intro/a").unwrap().hints_revealed = 1;
+        assert_eq!(ids(newly_unlocked(&defs, &prog, &[])), vec!["century"]);
+        // A hint opened after passing doesn't count against the solve.
+        prog.exercises.get_mut("intro/a").unwrap().hints_at_pass = Some(0);
+        assert_eq!(ids(newly_unlocked(&defs, &prog, &[])), vec!["speedy", "century"]);
+
+        // First try is judged at the pass; checks after it don't undo it.
+        let sharp: AchievementFile = toml::from_str("[[achievement]]\nid = \"sharp\"\nname = \"Sharp\"\ndescription = \"First try\"\ncondition = { first_try = 1 }\n").unwrap();
+        prog.exercises.get_mut("intro/a").unwrap().attempts = 3;
+        assert!(newly_unlocked(&sharp.achievements, &prog, &[]).is_empty());
+        prog.exercises.get_mut("intro/a").unwrap().attempts_at_pass = Some(1);
+        assert_eq!(ids(newly_unlocked(&sharp.achievements, &prog, &[])), vec!["sharp"]);
+    }
+}
@@ -1,8 +1,10 @@
 use anyhow::Result;
 use eframe::egui;
 use std::sync::{Arc, Mutex};
-use webbrowser;
 use egui::WidgetText;
+use rust_game::persistence::Progress;
+use rust_game::quiz::{Answer, Question, QuestionKind, QuizConfig};
+use rust_game::storage::Storage;
 
 #[derive(Default, Clone)]
 struct AppState {
@@ -12,32 +14,63 @@ struct AppState {
     last_output: String,
     filter_text: String,
     show_only_incomplete: bool,
-    show_output_tab: bool,
+    tab: Tab,
     text_scale: f32,
     auth_username: String,
     auth_password: String,
     auth_show_password: bool,
+    bundle_path: String,
+    diff_pick: Option<i64>,
+    progress: Progress,
+    review_answer: Option<Answer>,
+    review_feedback: String,
+    board_weekly: bool,
+    board_lesson: Option<String>,
+    /// The board for the filters above, or why it failed; `None` until loaded.
+    board: Option<Result<rust_game::leaderboard::Board, String>>,
+    /// `achievements.toml` as of the last `reload_progress`, or why it failed to load.
+    achievements: Vec<rust_game::achievements::Achievement>,
+    achievements_error: Option<String>,
+    /// Snapshots of the lesson the history panel last showed, by lesson id.
+    history: Option<(String, Vec<rust_game::storage::Snapshot>)>,
+    measure_coverage: bool,
+    /// Lesson id, line coverage and the source it was measured on, from the last passing check.
+    coverage: Option<(String, rust_game::coverage::Coverage, String)>,
+    /// Completed lessons whose tests changed since they were solved.
+    updated: Vec<String>,
+    /// What the Review tab shows, as of the last `reload_progress`.
+    review_due: Vec<rust_game::review::DueItem>,
+    /// The first due quiz question: lesson id, index and the question.
+    review_question: Option<(String, usize, rust_game::quiz::Question)>,
+    /// The running exam and the scoring rules, as of the last `reload_progress`.
+    exam: Option<rust_game::exam::ExamSession>,
+    scoring: rust_game::scoring::ScoringConfig,
+    daily_pool: Vec<String>,
+    /// Today's challenge and the streak; `None` until `daily_ui` recomputes it after a reload.
+    daily: Option<rust_game::daily::DailyStatus>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum Tab {
+    #[default]
+    Details,
+    Output,
+    Review,
+    Achievements,
+    Leaderboard,
 }
 
 pub struct GuiApp {
     state: Arc<Mutex<AppState>>,
+    storage: Storage,
 }
 
 impl GuiApp {
     fn is_unlocked(state: &AppState, id: &str) -> bool {
         if let Ok(list) = rust_game::exercise::load_all(&state.lessons_root) {
-            if let Some(ex) = list.iter().find(|e| e.meta.id == id) {
-                let diff = ex.meta.difficulty.clone();
-                let mut same: Vec<&rust_game::exercise::Exercise> = list.iter().filter(|e| e.meta.difficulty == diff).collect();
-                same.sort_by(|a,b| a.meta.id.cmp(&b.meta.id));
-                if let Some(pos) = same.iter().position(|e| e.meta.id == id) {
-                    if pos == 0 { return true; }
-                    let prev_id = &same[pos - 1].meta.id;
-                    if let Ok(p) = rust_game::persistence::load() {
-                        return p.exercises.get(prev_id).map(|e| e.completed).unwrap_or(false);
-                    }
-                }
-            }
+            let today = rust_game::daily::day_of(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64);
+            if rust_game::daily::challenge_for(&list, &state.daily_pool, today).is_some_and(|e| e.meta.id == id) { return true; }
+            return rust_game::exercise::unlocked(&list, id, |p| state.progress.exercises.get(p).is_some_and(|e| e.completed));
         }
         false
     }
@@ -45,8 +78,8 @@ impl GuiApp {
     fn next_unlocked_id(state: &AppState, current_id: &str) -> Option<String> {
         if let Ok(list) = rust_game::exercise::load_all(&state.lessons_root) {
             if let Some((idx, _)) = list.iter().enumerate().find(|(_, e)| e.meta.id == current_id) {
-                for j in (idx + 1)..list.len() {
-                    let nid = &list[j].meta.id;
+                for next in list.iter().skip(idx + 1) {
+                    let nid = &next.meta.id;
                     if Self::is_unlocked(state, nid) { return Some(nid.clone()); }
                 }
             }
@@ -63,19 +96,25 @@ impl GuiApp {
         style.spacing.item_spacing = egui::vec2(8.0, 6.0);
         style.spacing.button_padding = egui::vec2(12.0, 8.0);
         cc.egui_ctx.set_style(style);
-        Self {
+        let storage = Storage::open_default()
+            .or_else(|_| Storage::open_in_memory())
+            .expect("open storage");
+        let app = Self {
+            storage,
             state: Arc::new(Mutex::new(AppState {
                 lessons_root: "lessons".to_string(),
                 filter_text: String::new(),
                 show_only_incomplete: false,
-                show_output_tab: false,
                 text_scale: 1.0,
                 auth_username: String::new(),
                 auth_password: String::new(),
                 auth_show_password: true,
+                bundle_path: dirs::home_dir().unwrap_or_default().join("rustlearn-export.json").display().to_string(),
                 ..Default::default()
             })),
-        }
+        };
+        Self::reload_progress(&mut app.state.lock().unwrap());
+        app
     }
 
     fn refresh_list(state: &mut AppState) {
@@ -92,7 +131,473 @@ impl GuiApp {
         }
     }
 
-    fn run_engine_check(state: &mut AppState) {
+    fn reload_progress(state: &mut AppState) {
+        // Keep what we have on screen if the file can't be read or recovered.
+        match rust_game::persistence::load() {
+            Ok(p) => state.progress = p,
+            Err(e) => state.last_output = format!("Failed to load progress: {e:#}"),
+        }
+        for r in rust_game::persistence::take_recoveries() { state.last_output.push_str(&format!("\nWarning: {r}")); }
+        state.exam = rust_game::exam::active().unwrap_or_else(|e| { state.last_output = format!("Failed to read the exam session: {e:#}"); None });
+        state.scoring = rust_game::scoring::load(&state.lessons_root).unwrap_or_default();
+        state.daily_pool = rust_game::daily::load_pool(&state.lessons_root).unwrap_or_default();
+        state.daily = None;
+        state.board = None;
+        state.history = None;
+        (state.achievements, state.achievements_error) = match rust_game::achievements::load(&state.lessons_root) {
+            Ok(defs) => (defs, None),
+            Err(e) => (Vec::new(), Some(format!("{e:#}"))),
+        };
+        if let Ok(list) = rust_game::exercise::load_all(&state.lessons_root) {
+            state.updated = rust_game::reverify::updated(&list, &state.progress).into_iter().map(|e| e.meta.id.clone()).collect();
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+            state.review_due = rust_game::review::due(&list, &state.progress, now);
+            state.review_question = state.review_due.iter().find_map(|d| match &d.item {
+                rust_game::review::ReviewItem::Question { lesson_id, index } => {
+                    let ex = list.iter().find(|e| &e.meta.id == lesson_id)?;
+                    let q = rust_game::quiz::load_quiz(&ex.root).ok().flatten()?.questions.get(*index).cloned()?;
+                    Some((lesson_id.clone(), *index, q))
+                }
+                rust_game::review::ReviewItem::Lesson { .. } => None,
+            });
+        }
+    }
+
+    /// Grades the saved solutions of `lessons` against their current tests.
+    fn run_reverify(storage: &Storage, state: &mut AppState, lessons: &[rust_game::exercise::Exercise]) {
+        let mut out = String::new();
+        for ex in lessons {
+            let line = match rust_game::reverify::reverify(storage, ex, ex.meta.timeout_secs.unwrap_or(15)) {
+                Ok(rust_game::reverify::Reverified::Passed) => format!("✓ {} still passes", ex.meta.title),
+                Ok(rust_game::reverify::Reverified::NoSolution) => format!("- {}: no saved solution to check", ex.meta.title),
+                Ok(rust_game::reverify::Reverified::Failed(o)) => {
+                    let why = o.hang.as_ref().map(|h| h.to_string()).unwrap_or_else(|| "the new tests fail".to_string());
+                    format!("✗ {}: {why}. Fix it and Check again.\n{}\n{}", ex.meta.title, o.stdout.trim_end(), o.stderr.trim_end())
+                }
+                Err(e) => format!("✗ {}: {e:#}", ex.meta.title),
+            };
+            out.push_str(&line);
+            out.push('\n');
+        }
+        Self::reload_progress(state);
+        state.last_output = out;
+        state.tab = Tab::Output;
+    }
+
+    /// Changes only what `f` touches, under the progress lock, so edits the
+    /// CLI made while the window was open survive; then reloads.
+    fn update_progress(state: &mut AppState, f: impl FnOnce(&mut Progress)) {
+        if let Err(e) = rust_game::persistence::update(f) {
+            state.last_output = format!("Failed to save progress: {e:#}");
+        }
+        Self::reload_progress(state);
+    }
+
+    /// Reveals the next hint tier and returns every revealed hint as text.
+    fn reveal_hint(state: &mut AppState, ex: &rust_game::exercise::Exercise) -> String {
+        if ex.meta.hints.is_empty() { return "No hint available.".to_string(); }
+        if let Err(e) = rust_game::hints::reveal_next(&ex.meta) { return format!("{e}"); }
+        Self::reload_progress(state);
+        let total = ex.meta.hints.len();
+        rust_game::hints::revealed(&ex.meta, &state.progress)
+            .iter().enumerate()
+            .map(|(i, h)| format!("Hint {}/{}: {}", i + 1, total, h))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn hints_ui(ui: &mut egui::Ui, state: &mut AppState, ex: &rust_game::exercise::Exercise) {
+        let total = ex.meta.hints.len();
+        if total == 0 { return; }
+        if state.exam.is_some() {
+            ui.label("Hints are locked during the exam.");
+            return;
+        }
+        for (i, h) in rust_game::hints::revealed(&ex.meta, &state.progress).iter().enumerate() {
+            ui.label(format!("Hint {}/{}: {}", i + 1, total, h));
+        }
+        let shown = rust_game::hints::revealed_count(&ex.meta, &state.progress);
+        if shown < total {
+            let label = format!("Reveal hint {}/{} (-{} pts)", shown + 1, total, state.scoring.hint_cost(&ex.meta));
+            if ui.button(label).clicked() { Self::reveal_hint(state, ex); }
+        }
+    }
+
+    fn history_ui(ui: &mut egui::Ui, storage: &Storage, state: &mut AppState, ex: &rust_game::exercise::Exercise) {
+        let uid = state.progress.current_user_id;
+        if state.history.as_ref().is_none_or(|(id, _)| id != &ex.meta.id) {
+            state.history = Some((ex.meta.id.clone(), rust_game::history::list(storage, uid, &ex.meta.id).unwrap_or_default()));
+        }
+        // Taken out while the rows below edit the state; put back unless a restore changed it.
+        let Some((id, snaps)) = state.history.take() else { return };
+        let mut restored = false;
+        Self::history_rows(ui, storage, state, ex, &snaps, &mut restored);
+        if !restored { state.history = Some((id, snaps)); }
+    }
+
+    fn history_rows(ui: &mut egui::Ui, storage: &Storage, state: &mut AppState, ex: &rust_game::exercise::Exercise, snaps: &[rust_game::storage::Snapshot], restored: &mut bool) {
+        let uid = state.progress.current_user_id;
+        if snaps.is_empty() { return; }
+        let Ok(file) = ex.working_file() else { return };
+        ui.separator();
+        egui::CollapsingHeader::new(format!("History ({} snapshots)", snaps.len())).id_source("history").show(ui, |ui| {
+            if let Some(a) = state.diff_pick {
+                ui.label(egui::RichText::new(format!("Comparing from #{a}; pick another snapshot")).small().weak());
+            }
+            for snap in snaps.iter().rev() {
+                ui.horizontal(|ui| {
+                    let status = match snap.passed { Some(true) => "✓", Some(false) => "✗", None => "•" };
+                    ui.monospace(format!("#{} {} {}", snap.id, status, snap.timestamp));
+                    if ui.small_button("Diff current").clicked() {
+                        state.last_output = rust_game::history::diff(storage, uid, &ex.meta.id, snap.id, None, &file).unwrap_or_else(|e| format!("{e}"));
+                        state.tab = Tab::Output;
+                    }
+                    if ui.small_button("Compare").clicked() {
+                        match state.diff_pick.take() {
+                            Some(a) if a != snap.id => {
+                                state.last_output = rust_game::history::diff(storage, uid, &ex.meta.id, a, Some(snap.id), &file).unwrap_or_else(|e| format!("{e}"));
+                                state.tab = Tab::Output;
+                            }
+                            _ => state.diff_pick = Some(snap.id),
+                        }
+                    }
+                    if ui.small_button("Restore").clicked() {
+                        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+                        state.last_output = match rust_game::history::restore(storage, uid, &ex.meta.id, snap.id, &file, now) {
+                            Ok(()) => format!("Restored snapshot #{} (previous copy saved to history)", snap.id),
+                            Err(e) => format!("{e}"),
+                        };
+                        *restored = true;
+                    }
+                });
+            }
+        });
+    }
+
+    /// Input widgets for one question. Returns the edited answer, if any, and
+    /// whether it is final enough to save (a click, or a text field losing focus).
+    fn answer_ui(ui: &mut egui::Ui, q: &Question, cur: Option<Answer>) -> (Option<Answer>, bool) {
+        if q.kind != QuestionKind::CompileError {
+            if let Some(code) = &q.code { ui.monospace(code.trim_end()); }
+        }
+        let mut pick = None;
+        let mut commit = false;
+        match q.kind {
+            QuestionKind::Single => {
+                for (i, opt) in q.options.iter().enumerate() {
+                    if ui.radio(cur == Some(Answer::Choice(i)), opt).clicked() { pick = Some(Answer::Choice(i)); }
+                }
+            }
+            QuestionKind::Multi => {
+                let mut chosen = match cur { Some(Answer::Choices(xs)) => xs, _ => Vec::new() };
+                for (i, opt) in q.options.iter().enumerate() {
+                    let mut on = chosen.contains(&i);
+                    if ui.checkbox(&mut on, opt).changed() {
+                        if on { chosen.push(i); } else { chosen.retain(|x| *x != i); }
+                        pick = Some(Answer::Choices(chosen.clone()));
+                    }
+                }
+            }
+            QuestionKind::TrueFalse => {
+                ui.horizontal(|ui| {
+                    for b in [true, false] {
+                        if ui.radio(cur == Some(Answer::Bool(b)), if b { "True" } else { "False" }).clicked() { pick = Some(Answer::Bool(b)); }
+                    }
+                });
+            }
+            QuestionKind::FillBlank | QuestionKind::Output => {
+                let mut text = match cur { Some(Answer::Text(t)) => t, _ => String::new() };
+                let resp = if q.kind == QuestionKind::Output {
+                    ui.add(egui::TextEdit::multiline(&mut text).font(egui::TextStyle::Monospace).desired_rows(2).hint_text("Program output"))
+                } else {
+                    ui.add(egui::TextEdit::singleline(&mut text).hint_text("Your answer"))
+                };
+                if resp.changed() { pick = Some(Answer::Text(text)); }
+                if resp.lost_focus() { commit = true; }
+            }
+            QuestionKind::CompileError => {
+                for (i, line) in q.code_lines().iter().enumerate() {
+                    let label = egui::RichText::new(format!("{:>3} | {}", i + 1, line)).monospace();
+                    if ui.radio(cur == Some(Answer::Line(i + 1)), label).clicked() { pick = Some(Answer::Line(i + 1)); }
+                }
+            }
+        }
+        if pick.is_some() && !matches!(pick, Some(Answer::Text(_))) { commit = true; }
+        (pick, commit)
+    }
+
+    /// Renders the lesson quiz. Answers live in `progress` so they survive
+    /// redraws and restarts; choices are saved on click, text when the field loses focus.
+    fn quiz_ui(ui: &mut egui::Ui, state: &mut AppState, ex: &rust_game::exercise::Exercise, qz: &QuizConfig) {
+        let qp = state.progress.exercises.get(&ex.meta.id).map(|e| e.quiz.clone()).unwrap_or_default();
+        let mut answers = qp.answers.clone();
+        let result = qp.submitted.then(|| rust_game::quiz::grade(qz, &answers));
+        let mut dirty = false;
+        ui.separator();
+        ui.label(egui::RichText::new(&qz.title).strong());
+        if let Some(best) = qp.best_score {
+            ui.label(format!("Best score: {}/{} ({} attempt(s))", best, qp.total, qp.attempts));
+        }
+        ui.add_enabled_ui(!qp.submitted, |ui| {
+            for (qi, q) in qz.questions.iter().enumerate() {
+                ui.label(format!("Q{}: {}", qi + 1, q.prompt));
+                let (answer, commit) = Self::answer_ui(ui, q, answers.get(&qi).cloned());
+                if let Some(a) = answer { answers.insert(qi, a); }
+                dirty |= commit;
+                if let Some(r) = &result {
+                    ui.label(if r.per_question[qi] { "✅ Correct" } else { "❌ Incorrect" });
+                    if let Some(e) = &q.explanation { ui.label(egui::RichText::new(e).italics()); }
+                }
+                ui.add_space(8.0);
+            }
+        });
+        if answers != qp.answers {
+            state.progress.exercises.entry(ex.meta.id.clone()).or_default().quiz.answers = answers.clone();
+        }
+        if dirty {
+            let id = ex.meta.id.clone();
+            Self::update_progress(state, |p| p.exercises.entry(id).or_default().quiz.answers = answers.clone());
+        }
+        ui.horizontal(|ui| {
+            if !qp.submitted {
+                if ui.button("Submit quiz").clicked() {
+                    state.last_output = match rust_game::quiz::submit(&ex.meta.id, qz, answers) {
+                        Ok(r) if r.perfect() => format!("Quiz: {}/{} — all correct!", r.correct, r.total),
+                        Ok(r) => format!("Quiz: {}/{}. Review the explanations and retake it.", r.correct, r.total),
+                        Err(e) => format!("Failed to save quiz: {e:#}"),
+                    };
+                    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+                    state.last_output.push_str(&Self::unlock_achievements(&state.lessons_root, now));
+                    Self::reload_progress(state);
+                }
+            } else if ui.button("Retake quiz").clicked() {
+                let id = ex.meta.id.clone();
+                Self::update_progress(state, |p| {
+                    let entry = p.exercises.entry(id).or_default();
+                    entry.quiz.answers.clear();
+                    entry.quiz.submitted = false;
+                });
+            }
+        });
+    }
+
+    /// Review tab: answers due quiz questions one at a time and starts
+    /// re-solves of due lessons.
+    fn review_ui(ui: &mut egui::Ui, storage: &Storage, state: &mut AppState) {
+        use rust_game::review::{self, ReviewItem};
+        ui.heading("Review");
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+        let items = state.review_due.clone();
+        if !state.review_feedback.is_empty() {
+            ui.label(&state.review_feedback);
+            ui.separator();
+        }
+        let in_progress: Vec<String> = state.progress.exercises.iter()
+            .filter(|(_, ep)| ep.review_session.is_some())
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &in_progress {
+            ui.label(format!("Re-solving {id}: edit the working file and Check it in the Output tab."));
+        }
+        if items.is_empty() {
+            ui.label("Nothing is due for review.");
+            return;
+        }
+        ui.label(format!("{} item(s) due", items.len()));
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            if let Some((lesson_id, index, q)) = state.review_question.clone() {
+                ui.separator();
+                ui.label(egui::RichText::new(format!("{lesson_id} · Q{}", index + 1)).weak());
+                ui.label(&q.prompt);
+                let (answer, _) = Self::answer_ui(ui, &q, state.review_answer.clone());
+                if answer.is_some() { state.review_answer = answer; }
+                if ui.add_enabled(state.review_answer.is_some(), egui::Button::new("Check answer")).clicked() {
+                    let correct = state.review_answer.take().is_some_and(|a| q.is_correct(&a));
+                    state.review_feedback = match review::answer_question(&lesson_id, &q, index, correct, now) {
+                        Ok(card) => {
+                            let verdict = if correct { "✅ Correct".to_string() } else { format!("❌ Incorrect. Expected: {}", q.expected()) };
+                            let explanation = q.explanation.as_deref().map(|e| format!("\n{e}")).unwrap_or_default();
+                            format!("{verdict} (next review in {} day(s)){explanation}", card.interval_days)
+                        }
+                        Err(e) => format!("Failed to save review: {e:#}"),
+                    };
+                    Self::reload_progress(state);
+                }
+            }
+            let due_lessons: Vec<&String> = items.iter().filter_map(|d| match &d.item {
+                ReviewItem::Lesson { id } => Some(id),
+                ReviewItem::Question { .. } => None,
+            }).collect();
+            if !due_lessons.is_empty() {
+                ui.separator();
+                ui.label(egui::RichText::new("Lessons to re-solve").strong());
+            }
+            for id in due_lessons {
+                ui.horizontal(|ui| {
+                    ui.label(id.as_str());
+                    let busy = in_progress.contains(id);
+                    if ui.add_enabled(!busy, egui::Button::new("Re-solve from starter")).clicked() {
+                        let lessons = rust_game::exercise::load_all(&state.lessons_root).unwrap_or_default();
+                        let Some(ex) = lessons.iter().find(|e| &e.meta.id == id) else { return };
+                        let res = ex.working_file().and_then(|file| {
+                            rust_game::history::record(storage, state.progress.current_user_id, id, None, None, now, &file)?;
+                            review::start_lesson(ex, now)
+                        });
+                        Self::reload_progress(state);
+                        state.selected_id = Some(id.clone());
+                        state.tab = Tab::Output;
+                        state.last_output = match res {
+                            Ok(()) => format!("Re-solving {id} from the starter. Your previous solution is in History. Open, edit, then Check."),
+                            Err(e) => format!("{e:#}"),
+                        };
+                    }
+                });
+            }
+        });
+    }
+
+    /// Runs the achievement rules; returns a line per unlock, each starting
+    /// with a newline, or the error if the definitions are broken.
+    fn unlock_achievements(lessons_root: &str, now: i64) -> String {
+        match rust_game::achievements::evaluate(lessons_root, now) {
+            Ok(list) => list.iter().map(|a| format!("\n{} Achievement unlocked: {} — {}", a.icon.as_deref().unwrap_or("🏅"), a.name, a.description)).collect(),
+            Err(e) => format!("\nAchievements not evaluated: {e:#}"),
+        }
+    }
+
+    /// Achievements gallery: unlocked ones with their date, locked ones greyed out.
+    fn achievements_ui(ui: &mut egui::Ui, state: &AppState) {
+        ui.heading("Achievements");
+        if let Some(e) = &state.achievements_error { ui.colored_label(egui::Color32::LIGHT_RED, e); return; }
+        let defs = &state.achievements;
+        if defs.is_empty() { ui.label("No achievements are defined for these lessons."); return; }
+        let unlocked = defs.iter().filter(|a| state.progress.achievements.contains_key(&a.id)).count();
+        ui.label(format!("{unlocked}/{} unlocked", defs.len()));
+        ui.separator();
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            egui::Grid::new("achievements").num_columns(3).spacing([12.0, 8.0]).striped(true).show(ui, |ui| {
+                for a in defs {
+                    let icon = a.icon.as_deref().unwrap_or("🏅");
+                    match state.progress.achievements.get(&a.id) {
+                        Some(at) => {
+                            ui.label(egui::RichText::new(icon).size(24.0));
+                            ui.vertical(|ui| { ui.strong(&a.name); ui.label(&a.description); });
+                            ui.label(format!("Unlocked {}", rust_game::util::format_date(*at)));
+                        }
+                        None => {
+                            ui.label(egui::RichText::new("🔒").size(24.0).weak());
+                            ui.vertical(|ui| { ui.label(egui::RichText::new(&a.name).weak()); ui.label(egui::RichText::new(&a.description).weak()); });
+                            ui.label(egui::RichText::new("Locked").weak());
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    /// Leaderboard tab: standings and fastest times from the attempts table.
+    fn leaderboard_ui(ui: &mut egui::Ui, storage: &Storage, state: &mut AppState) {
+        use rust_game::leaderboard;
+        ui.heading("Leaderboard");
+        let filter = (state.board_weekly, state.board_lesson.clone());
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut state.board_weekly, false, "All time");
+            ui.selectable_value(&mut state.board_weekly, true, "This week");
+            let label = state.board_lesson.clone().unwrap_or_else(|| "All lessons".to_string());
+            egui::ComboBox::from_id_source("board_lesson").selected_text(label).show_ui(ui, |ui| {
+                ui.selectable_value(&mut state.board_lesson, None, "All lessons");
+                for (id, _, _) in &state.exercises {
+                    ui.selectable_value(&mut state.board_lesson, Some(id.clone()), id.as_str());
+                }
+            });
+        });
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+        let since = if state.board_weekly { leaderboard::parse_since("week", now).unwrap_or(0) } else { 0 };
+        // Reloaded when a filter changes, the week rolls over or progress is reloaded after a check.
+        if filter != (state.board_weekly, state.board_lesson.clone()) || state.board.as_ref().is_some_and(|b| b.as_ref().is_ok_and(|b| b.since != since)) {
+            state.board = None;
+        }
+        if state.board.is_none() {
+            state.board = Some(leaderboard::load(storage, state.board_lesson.as_deref(), since).map_err(|e| format!("{e:#}")));
+        }
+        let board = match state.board.as_ref() {
+            Some(Ok(b)) => b,
+            Some(Err(e)) => { ui.colored_label(egui::Color32::LIGHT_RED, format!("Failed to load leaderboard: {e}")); return; }
+            None => return,
+        };
+        if board.standings.is_empty() {
+            ui.label("No attempts recorded yet. Log in so your checks are recorded.");
+            return;
+        }
+        ui.separator();
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            egui::Grid::new("standings").num_columns(5).spacing([16.0, 6.0]).striped(true).show(ui, |ui| {
+                for h in ["#", "Player", "Points", "Lessons", "Fastest"] { ui.strong(h); }
+                ui.end_row();
+                for (i, s) in board.standings.iter().enumerate() {
+                    let me = state.progress.current_user_id == Some(s.user_id);
+                    let text = |t: String| if me { egui::RichText::new(t).strong().color(egui::Color32::LIGHT_GREEN) } else { egui::RichText::new(t) };
+                    ui.label(text(format!("{}", i + 1)));
+                    ui.label(text(format!("{} {}", s.avatar.as_deref().unwrap_or("🦀"), s.name)));
+                    ui.label(text(s.points.to_string()));
+                    ui.label(text(s.completed.to_string()));
+                    ui.label(text(s.fastest_secs.map(leaderboard::format_secs).unwrap_or_else(|| "-".into())));
+                    ui.end_row();
+                }
+            });
+            if !board.fastest.is_empty() {
+                ui.separator();
+                ui.label(egui::RichText::new("Fastest times").strong());
+                egui::Grid::new("fastest").num_columns(3).spacing([16.0, 4.0]).striped(true).show(ui, |ui| {
+                    for r in &board.fastest {
+                        ui.label(&r.lesson_id);
+                        ui.label(leaderboard::format_secs(r.duration_secs));
+                        ui.label(&r.name);
+                        ui.end_row();
+                    }
+                });
+            }
+        });
+    }
+
+    /// Footer line with the streak and today's challenge.
+    fn daily_ui(ui: &mut egui::Ui, storage: &Storage, state: &mut AppState, lessons: &[rust_game::exercise::Exercise]) {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+        // Also recompute when the day rolls over with the window open.
+        if state.daily.as_ref().is_none_or(|st| st.day != rust_game::daily::day_of(now)) {
+            let attempts = state.progress.current_user_id.and_then(|uid| storage.attempts_for_user(uid).ok()).unwrap_or_default();
+            state.daily = Some(rust_game::daily::status(lessons, &state.daily_pool, &attempts, &state.progress, now));
+        }
+        let Some(st) = state.daily.clone() else { return };
+        if state.progress.current_user_id.is_some() {
+            ui.small(format!("🔥 Streak: {} day(s) (best {})", st.streak, st.best));
+        }
+        if let Some(id) = st.lesson_id {
+            let label = if st.done { format!("Daily ✓ {id}") } else { format!("Daily: {id} (+{})", rust_game::daily::DAILY_BONUS) };
+            if ui.small_button(label).on_hover_text("Select today's challenge").clicked() {
+                state.selected_id = Some(id);
+            }
+        }
+    }
+
+    /// The measured `lib.rs` with the lines the tests never ran highlighted.
+    fn coverage_ui(ui: &mut egui::Ui, cov: &rust_game::coverage::Coverage, src: &str) {
+        ui.separator();
+        ui.label(egui::RichText::new(format!("Coverage {}% ({}/{} lines)", cov.percent(), cov.covered, cov.total)).strong());
+        for (i, line) in src.lines().enumerate() {
+            let text = egui::RichText::new(format!("{:>4} │ {}", i + 1, line)).monospace();
+            let text = if cov.uncovered.contains(&(i as u32 + 1)) {
+                text.color(egui::Color32::WHITE).background_color(egui::Color32::from_rgb(140, 40, 40))
+            } else {
+                text
+            };
+            ui.label(text);
+        }
+    }
+
+    fn run_engine_check(storage: &Storage, state: &mut AppState) {
+        state.coverage = None;
         if let Some(id) = state.selected_id.clone() {
             let out = (|| -> Result<String> {
                 // Load exercise
@@ -100,33 +605,95 @@ impl GuiApp {
                     .into_iter()
                     .find(|e| e.meta.id == id)
                     .ok_or_else(|| anyhow::anyhow!("Exercise not found"))?;
+                state.exam = rust_game::exam::active()?;
+                if state.exam.as_ref().is_some_and(|s| s.lessons.contains(&ex.meta.id)) {
+                    return Ok(format!("{} is part of the running exam and is graded on submit.", ex.meta.id));
+                }
                 let file = ex.working_file()?;
                 if !file.exists() {
                     return Ok("Working file not found. Use Start first.".to_string());
                 }
                 let timeout = ex.meta.timeout_secs.unwrap_or(15);
                 let outcome = rust_game::grader::grade(&ex, &file, timeout)?;
+                let content_hash = ex.content_hash().ok();
                 // Update progress similar to CLI engine
-                let mut prog = rust_game::persistence::load().unwrap_or_default();
-                let entry = prog.exercises.entry(ex.meta.id.clone()).or_default();
-                entry.attempts += 1;
-                if outcome.passed {
-                    entry.completed = true;
-                }
-                rust_game::persistence::save(&prog)?;
+                let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+                let scoring_cfg = rust_game::scoring::load(&state.lessons_root)?;
+                let lint = if outcome.passed && scoring_cfg.lint_bonus_percent > 0 && rust_game::scoring::needs_award(&state.progress, &ex.meta.id) {
+                    rust_game::grader::lint(&ex, timeout.max(60))?
+                } else {
+                    None
+                };
+                let (breakdown, duration, uid) = rust_game::persistence::update(|p| {
+                    let entry = p.exercises.entry(ex.meta.id.clone()).or_default();
+                    entry.attempts += 1;
+                    let mut breakdown = None;
+                    let mut duration = None;
+                    if outcome.passed {
+                        entry.completed = true;
+                        entry.completed_at.get_or_insert(now);
+                        entry.content_hash = content_hash.clone();
+                        if let Some(start) = entry.last_started_at {
+                            let dur = (now - start).max(0) as u64;
+                            entry.last_duration_secs = Some(dur);
+                            entry.best_duration_secs = Some(entry.best_duration_secs.map_or(dur, |b| b.min(dur)));
+                            duration = Some(dur);
+                        }
+                        breakdown = rust_game::scoring::award(p, &ex.meta, &scoring_cfg, lint);
+                    }
+                    (breakdown, duration, p.current_user_id)
+                })?;
+                let attempt_id = uid.and_then(|uid| storage.record_attempt(uid, &ex.meta.id, outcome.passed, duration, now, content_hash.as_deref()).ok());
+                if let (Some(id), Some(b)) = (attempt_id, &breakdown) { storage.set_attempt_points(id, b.total)?; }
+                let _ = rust_game::history::record(storage, uid, &ex.meta.id, attempt_id, Some(outcome.passed), now, &file);
+                let reviewed = rust_game::review::on_check(&ex.meta.id, outcome.passed, now)?;
+                let bonuses = match (uid, attempt_id) {
+                    (Some(uid), Some(_)) if outcome.passed => rust_game::daily::award(storage, &state.lessons_root, &rust_game::exercise::load_all(&state.lessons_root)?, uid, now)?,
+                    _ => Vec::new(),
+                };
+                let unlocked = Self::unlock_achievements(&state.lessons_root, now);
+                Self::reload_progress(state);
                 if outcome.passed {
-                    Ok("All tests passed 🎉".to_string())
+                    let mut msg = "All tests passed 🎉".to_string();
+                    for note in &outcome.notes { msg.push('\n'); msg.push_str(note); }
+                    if let Some(b) = breakdown { msg.push_str(&format!("\n+{} points\n{b}", b.total)); }
+                    if let Some(card) = reviewed { msg.push_str(&format!("\nReview recorded. Next review in {} day(s).", card.interval_days)); }
+                    for b in bonuses { msg.push('\n'); msg.push_str(&b); }
+                    msg.push_str(&unlocked);
+                    if state.measure_coverage || ex.meta.coverage {
+                        match rust_game::coverage::measure(&ex, timeout.max(120)) {
+                            Ok(Some(cov)) => {
+                                msg.push_str(&format!("\nCoverage: {}% of lib.rs ({}/{} lines); uncovered lines are highlighted below.", cov.percent(), cov.covered, cov.total));
+                                state.coverage = Some((ex.meta.id.clone(), cov, std::fs::read_to_string(&file).unwrap_or_default()));
+                            }
+                            Ok(None) => msg.push_str("\nCoverage skipped: llvm-cov not found (rustup component add llvm-tools-preview)"),
+                            Err(e) => msg.push_str(&format!("\nCoverage unavailable: {e:#}")),
+                        }
+                    }
+                    Ok(msg)
                 } else {
                     let mut s = String::new();
+                    if let Some(hang) = &outcome.hang { s.push_str(&format!("{hang}\n\n")); }
+                    if !outcome.ub.is_empty() {
+                        s.push_str("Tests pass, but Miri found undefined behavior:\n");
+                        for ub in &outcome.ub { s.push_str(&format!("  - {ub}\n")); }
+                        s.push('\n');
+                    }
                     if !outcome.stdout.is_empty() { s.push_str("stdout:\n"); s.push_str(&outcome.stdout); }
                     if !outcome.stderr.is_empty() { s.push_str("\nstderr:\n"); s.push_str(&outcome.stderr); }
                     if s.is_empty() { s = "Some tests failed".to_string(); }
+                    let targeted = rust_game::hints::contextual(&ex.meta, &outcome.stdout, &outcome.stderr);
+                    if !targeted.is_empty() {
+                        let list: Vec<String> = targeted.iter().map(|h| format!("  - {h}")).collect();
+                        s = format!("{}\n\nHints for this failure:\n{}", s, list.join("\n"));
+                    }
+                    s.push_str(&unlocked);
                     Ok(s)
                 }
             })();
             let msg = match out { Ok(s) => s, Err(e) => format!("{e}") };
-            // On success, auto-select next lesson if available
-            if msg.starts_with("All tests passed") {
+            // On success, auto-select next lesson if available (but stay to show coverage)
+            if msg.starts_with("All tests passed") && state.coverage.is_none() {
                 if let Ok(list) = rust_game::exercise::load_all(&state.lessons_root) {
                     if let Some((idx, _)) = list.iter().enumerate().find(|(_, e)| Some(&e.meta.id) == state.selected_id.as_ref()) {
                         if idx + 1 < list.len() {
@@ -145,9 +712,11 @@ impl GuiApp {
 impl eframe::App for GuiApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let mut state = self.state.lock().unwrap();
+        let storage = &self.storage;
         // Keyboard shortcut: F5 refreshes lesson list
         if ctx.input(|i| i.key_pressed(egui::Key::F5)) {
             Self::refresh_list(&mut state);
+            Self::reload_progress(&mut state);
             state.last_output = "Lessons refreshed".to_string();
         }
 
@@ -182,30 +751,31 @@ impl eframe::App for GuiApp {
                             if ui.selectable_label(false, "Light").clicked() { let mut v = egui::Visuals::light(); v.window_rounding = 8.0.into(); v.panel_fill = egui::Color32::from_rgb(245,246,248); ctx.set_visuals(v); }
                             if ui.selectable_label(false, "Ocean").clicked() { let mut v = egui::Visuals::dark(); v.override_text_color = Some(egui::Color32::from_rgb(208, 230, 255)); v.panel_fill = egui::Color32::from_rgb(10, 25, 47); ctx.set_visuals(v); }
                         });
-                    if ui.button("Refresh").on_hover_text("Reload lessons (F5)").clicked() { Self::refresh_list(&mut state); }
+                    if ui.button("Refresh").on_hover_text("Reload lessons (F5)").clicked() { Self::refresh_list(&mut state); Self::reload_progress(&mut state); }
                 });
                 columns[2].with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     egui::menu::bar(ui, |ui| {
-                        let p = rust_game::persistence::load().ok();
-                        let (logged_in, label) = match &p {
-                            Some(pp) if pp.current_user_id.is_some() => {
-                                let name = pp.current_username.clone().unwrap_or_else(|| "User".into());
-                                let avatar = pp.avatar.clone().unwrap_or_else(|| "🦀".into());
-                                (true, format!("{} {}", avatar, name))
-                            }
-                            _ => (false, "Log in".to_string()),
+                        let pp = &state.progress;
+                        let (logged_in, label) = if pp.current_user_id.is_some() {
+                            let name = pp.current_username.clone().unwrap_or_else(|| "User".into());
+                            let avatar = pp.avatar.clone().unwrap_or_else(|| "🦀".into());
+                            (true, format!("{} {}", avatar, name))
+                        } else {
+                            (false, "Log in".to_string())
                         };
                         ui.menu_button(WidgetText::from(label), |ui| {
                             if logged_in {
                                 ui.label("Status: Logged in");
                                 if ui.button("Fresh Start (Clear All)").clicked() {
-                                    let _ = rust_game::engine::run(state.lessons_root.clone(), rust_game::engine::Command::ClearAll);
+                                    let _ = rust_game::engine::run(storage, state.lessons_root.clone(), rust_game::engine::Command::ClearAll);
+                                    Self::reload_progress(&mut state);
                                     state.last_output = "All local progress and attempts cleared".to_string();
                                 }
                                 if ui.button("Logout").clicked() {
-                                    if let Ok(mut pp) = rust_game::persistence::load() {
-                                        pp.current_user_id = None; pp.current_username = None; let _ = rust_game::persistence::save(&pp);
-                                    }
+                                    Self::update_progress(&mut state, |p| {
+                                        p.current_user_id = None;
+                                        p.current_username = None;
+                                    });
                                     state.last_output = "Logged out".to_string();
                                     state.auth_password.clear();
                                     ui.close_menu();
@@ -222,13 +792,13 @@ impl eframe::App for GuiApp {
                                     ui.checkbox(&mut state.auth_show_password, "Show");
                                 });
                                 if ui.button("Login").clicked() {
-                                    match rust_game::storage::authenticate(&state.auth_username, &state.auth_password) {
+                                    match storage.authenticate(&state.auth_username, &state.auth_password) {
                                         Ok(Some(u)) => {
-                                            let mut pp = rust_game::persistence::load().unwrap_or_default();
-                                            pp.current_user_id = Some(u.id);
-                                            pp.current_username = Some(u.username);
-                                            let _ = rust_game::persistence::save(&pp);
-                                            state.last_output = format!("Logged in as {}", pp.current_username.clone().unwrap_or_default());
+                                            Self::update_progress(&mut state, |p| {
+                                                p.current_user_id = Some(u.id);
+                                                p.current_username = Some(u.username);
+                                            });
+                                            state.last_output = format!("Logged in as {}", state.progress.current_username.clone().unwrap_or_default());
                                             state.auth_password.clear();
                                             ui.close_menu();
                                         }
@@ -236,28 +806,55 @@ impl eframe::App for GuiApp {
                                         Err(e) => { state.last_output = format!("Login error: {e}"); }
                                     }
                                 }
-                                if ui.button("Register").clicked() {
-                                    if !state.auth_username.is_empty() && !state.auth_password.is_empty() {
-                                        let _ = rust_game::storage::register_user(&state.auth_username, &state.auth_password);
-                                        if let Ok(Some(u)) = rust_game::storage::authenticate(&state.auth_username, &state.auth_password) {
-                                            let mut pp = rust_game::persistence::load().unwrap_or_default();
-                                            pp.current_user_id = Some(u.id);
-                                            pp.current_username = Some(u.username);
-                                            let _ = rust_game::persistence::save(&pp);
-                                            state.last_output = format!("Registered and logged in as {}", pp.current_username.clone().unwrap_or_default());
-                                            state.auth_password.clear();
-                                            ui.close_menu();
-                                        }
+                                if ui.button("Register").clicked() && !state.auth_username.is_empty() && !state.auth_password.is_empty() {
+                                    let _ = storage.register_user(&state.auth_username, &state.auth_password);
+                                    if let Ok(Some(u)) = storage.authenticate(&state.auth_username, &state.auth_password) {
+                                        Self::update_progress(&mut state, |p| {
+                                            p.current_user_id = Some(u.id);
+                                            p.current_username = Some(u.username);
+                                        });
+                                        state.last_output = format!("Registered and logged in as {}", state.progress.current_username.clone().unwrap_or_default());
+                                        state.auth_password.clear();
+                                        ui.close_menu();
                                     }
                                 }
                             }
+                            ui.separator();
+                            ui.label("Backup file");
+                            ui.text_edit_singleline(&mut state.bundle_path);
+                            ui.horizontal(|ui| {
+                                let path = std::path::PathBuf::from(state.bundle_path.trim());
+                                if ui.button("Export Progress").clicked() {
+                                    state.last_output = match rust_game::bundle::export(storage, &path) {
+                                        Ok(b) => format!("Exported {} lessons and {} files to {}", b.progress.exercises.len(), b.work.len(), path.display()),
+                                        Err(e) => format!("Export failed: {e:#}"),
+                                    };
+                                    state.tab = Tab::Output;
+                                    ui.close_menu();
+                                }
+                                if ui.button("Import Progress").clicked() {
+                                    state.last_output = match rust_game::bundle::import(storage, &path) {
+                                        Ok(s) => {
+                                            let mut msg = format!("Merged {} lessons, restored {} files, added {} attempts", s.lessons_merged, s.files_restored, s.attempts_added);
+                                            for f in &s.files_conflicting { msg.push_str(&format!("\nKept local work/{f}; imported copy saved as work/{f}.imported")); }
+                                            if s.attempts_skipped { msg.push_str("\nAttempt history skipped: log in first to import it."); }
+                                            msg
+                                        }
+                                        Err(e) => format!("Import failed: {e:#}"),
+                                    };
+                                    Self::reload_progress(&mut state);
+                                    state.tab = Tab::Output;
+                                    ui.close_menu();
+                                }
+                            });
                         });
                     });
                     ui.add_space(8.0);
                     let mut lr = state.lessons_root.clone();
                     ui.add(egui::TextEdit::singleline(&mut lr).hint_text("lessons path"));
-                    if lr != state.lessons_root { state.lessons_root = lr; }
-                    if let Ok(p) = rust_game::persistence::load() {
+                    if lr != state.lessons_root { state.lessons_root = lr; Self::reload_progress(&mut state); }
+                    {
+                        let p = state.progress.clone();
                         ui.separator();
                         ui.label(egui::RichText::new(p.avatar.clone().unwrap_or_else(|| "🦀".to_string())).size(18.0));
                         ui.label(egui::RichText::new(format!("{} ({})", p.display_name.clone().unwrap_or_else(|| "Player".into()), p.total_points)).monospace());
@@ -277,7 +874,14 @@ impl eframe::App for GuiApp {
                                 for t in ["Dark","Light","Ocean"] { ui.selectable_value(&mut theme, t.to_string(), t); }
                             });
                             prog.theme = Some(theme);
-                            if ui.button("Save").clicked() { let _ = rust_game::persistence::save(&prog); ui.close_menu(); }
+                            if ui.button("Save").clicked() {
+                                Self::update_progress(&mut state, |p| {
+                                    p.display_name = prog.display_name;
+                                    p.avatar = prog.avatar;
+                                    p.theme = prog.theme;
+                                });
+                                ui.close_menu();
+                            }
                         });
                     }
                 });
@@ -294,7 +898,16 @@ impl eframe::App for GuiApp {
                 ui.toggle_value(&mut state.show_only_incomplete, egui::RichText::new("Incomplete").small());
             });
             ui.add_space(6.0);
-            let progress = rust_game::persistence::load().ok();
+            if !state.updated.is_empty() {
+                let label = format!("⟳ Re-verify {} updated lesson(s)", state.updated.len());
+                if ui.button(label).on_hover_text("These lessons changed after you solved them; run your solutions against the new tests").clicked() {
+                    let list = rust_game::exercise::load_all(&state.lessons_root).unwrap_or_default();
+                    let lessons: Vec<_> = list.into_iter().filter(|e| state.updated.contains(&e.meta.id)).collect();
+                    Self::run_reverify(storage, &mut state, &lessons);
+                }
+                ui.add_space(6.0);
+            }
+            let progress = state.progress.clone();
             let filtered: Vec<(usize, (String, String, String))> = state
                 .exercises
                 .iter().cloned().enumerate()
@@ -306,7 +919,7 @@ impl eframe::App for GuiApp {
             egui::ScrollArea::vertical().auto_shrink([false;2]).show(ui, |ui| {
                 let mut items = filtered;
                 fn diff_order(d: &str) -> u8 { match d { "beginner" => 0, "intermediate" => 1, _ => 2 } }
-                fn slug<'a>(id: &'a str) -> &'a str { id.rsplit('/').next().unwrap_or(id) }
+                fn slug(id: &str) -> &str { id.rsplit('/').next().unwrap_or(id) }
                 fn slug_rank(diff: &str, slug: &str) -> u32 {
                     let (beginner, intermediate, advanced): (&[&str], &[&str], &[&str]) = (
                         &[
@@ -353,24 +966,24 @@ impl eframe::App for GuiApp {
                     a.1 .0.cmp(&b.1 .0)
                 });
                 let mut current: Option<String> = None;
-                for (index, (id, title, diff)) in items.into_iter().map(|(i, v)| (i, v)) {
+                for (_, (id, title, diff)) in items.into_iter() {
                     if current.as_deref() != Some(&diff) {
                         current = Some(diff.clone());
                         ui.separator();
                         ui.label(egui::RichText::new(current.clone().unwrap()).small().strong());
                     }
-                    let completed = progress.as_ref().and_then(|p| p.exercises.get(&id)).map(|ep| ep.completed).unwrap_or(false);
+                    let completed = progress.exercises.get(&id).map(|ep| ep.completed).unwrap_or(false);
                     if state.show_only_incomplete && completed { continue; }
                     let locked = !Self::is_unlocked(&state, &id);
-                    let check = if completed { "✓" } else if locked { "🔒" } else { "•" };
+                    let check = if state.updated.contains(&id) { "⟳" } else if completed { "✓" } else if locked { "🔒" } else { "•" };
                     let is_selected = state.selected_id.as_ref() == Some(&id);
                     let row = ui.add_sized([ui.available_width(), 28.0], egui::SelectableLabel::new(is_selected, format!("{}  {}", check, title)));
                     if row.clicked() {
                         if !locked { state.selected_id = Some(id.clone()); }
-                        let logged_in = rust_game::persistence::load().ok().and_then(|p| p.current_user_id).is_some();
+                        let logged_in = state.progress.current_user_id.is_some();
                         if !logged_in {
                             state.last_output = "User must be logged in to attempt this".to_string();
-                            state.show_output_tab = true;
+                            state.tab = Tab::Output;
                         }
                     }
                     ui.horizontal(|ui| {
@@ -391,24 +1004,36 @@ impl eframe::App for GuiApp {
 
         egui::CentralPanel::default().frame(egui::Frame::default().fill(egui::Color32::from_rgb(26, 29, 35)).inner_margin(egui::Margin::same(12.0))).show(ctx, |ui| {
             ui.horizontal(|ui| {
-                if ui.selectable_label(!state.show_output_tab, "Details").clicked() { state.show_output_tab = false; }
-                if ui.selectable_label(state.show_output_tab, "Output").clicked() { state.show_output_tab = true; }
+                for (tab, label) in [(Tab::Details, "Details"), (Tab::Output, "Output"), (Tab::Review, "Review"), (Tab::Achievements, "Achievements"), (Tab::Leaderboard, "Leaderboard")] {
+                    if ui.selectable_label(state.tab == tab, label).clicked() { state.tab = tab; }
+                }
                 ui.separator();
                 ui.label("Text size");
                 let mut scale = state.text_scale;
-                if ui.add(egui::Slider::new(&mut scale, 0.8..=1.6).show_value(false)).changed() {
+                let slider = ui.add(egui::Slider::new(&mut scale, 0.8..=1.6).show_value(false));
+                if slider.changed() {
                     state.text_scale = scale;
                     let mut style = (*ctx.style()).clone();
                     style.text_styles.iter_mut().for_each(|(_, f)| f.size *= scale);
                     ctx.set_style(style);
-                    if let Ok(mut p) = rust_game::persistence::load() {
-                        p.text_scale = Some(scale);
-                        let _ = rust_game::persistence::save(&p);
-                    }
+                }
+                // Saved once the drag ends rather than on every step.
+                if slider.drag_stopped() || (slider.changed() && !slider.dragged()) {
+                    Self::update_progress(&mut state, |p| p.text_scale = Some(scale));
                 }
             });
             ui.separator();
-            if !state.show_output_tab {
+            if let Some(session) = &state.exam {
+                let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+                let left = session.remaining_secs(now);
+                ui.colored_label(egui::Color32::YELLOW, format!(
+                    "Exam running: {}m{:02}s left, {} lesson(s). Hints and solutions are locked; submit with `rust-game exam submit`.",
+                    left / 60, left % 60, session.lessons.len()
+                ));
+                ctx.request_repaint_after(std::time::Duration::from_secs(1));
+            }
+            match state.tab {
+            Tab::Details => {
                 egui::ScrollArea::vertical().auto_shrink([false;2]).show(ui, |ui| {
                     ui.heading("Details");
                     if let Some(sel) = &state.selected_id {
@@ -416,17 +1041,11 @@ impl eframe::App for GuiApp {
                             if let Some(ex) = list.into_iter().find(|e| &e.meta.id == sel) {
                                 ui.label(format!("Title: {}", ex.meta.title));
                                 ui.label(format!("Difficulty: {}", ex.meta.difficulty));
-                                if let Some(h) = ex.meta.hint.as_deref() { ui.label(format!("Hint: {h}")); }
-                                let can_view = {
-                                    if let Ok(p) = rust_game::persistence::load() {
-                                        p.exercises
-                                            .get(&ex.meta.id)
-                                            .map(|ep| ep.attempts > 0)
-                                            .unwrap_or(false)
-                                    } else {
-                                        false
-                                    }
-                                };
+                                Self::hints_ui(ui, &mut state, &ex);
+                                let can_view = state.exam.is_none() && state.progress.exercises
+                                    .get(&ex.meta.id)
+                                    .map(|ep| ep.attempts > 0)
+                                    .unwrap_or(false);
                                 if let Some(sol) = ex.solution_rs.clone() {
                                     ui.separator();
                                     if ui.add_enabled(can_view, egui::Button::new("View Solution")).clicked() {
@@ -437,7 +1056,9 @@ impl eframe::App for GuiApp {
                                             })
                                             .unwrap_or_else(|e| format!("Failed to load solution: {e}"));
                                         state.last_output = preview;
-                                        state.show_output_tab = true;
+                                        state.tab = Tab::Output;
+                                        let id = ex.meta.id.clone();
+                                        Self::update_progress(&mut state, |p| p.exercises.entry(id).or_default().solution_viewed = true);
                                     }
                                     ui.label(egui::RichText::new(format!("Solution available: {}", sol.display())).small().weak());
                                 }
@@ -445,39 +1066,48 @@ impl eframe::App for GuiApp {
                                     ui.separator();
                                     if ui.add_enabled(can_view, egui::Button::new("Open Explanation in Output")).clicked() {
                                         state.last_output = std::fs::read_to_string(&exp).unwrap_or_else(|_| "Failed to load explanation".to_string());
-                                        state.show_output_tab = true;
+                                        state.tab = Tab::Output;
                                     }
                                     ui.label(egui::RichText::new(format!("Source: {}", exp.display())).small().weak());
                                 }
-                                if let Ok(p) = rust_game::persistence::load() {
-                                    if let Some(ep) = p.exercises.get(&ex.meta.id) {
-                                        ui.label(format!("Attempts: {}", ep.attempts));
-                                        ui.label(format!("Completed: {}", ep.completed));
-                                    }
+                                if let Some(ep) = state.progress.exercises.get(&ex.meta.id) {
+                                    ui.label(format!("Attempts: {}", ep.attempts));
+                                    ui.label(format!("Completed: {}", ep.completed));
+                                }
+                                if state.updated.contains(&ex.meta.id) {
+                                    ui.horizontal(|ui| {
+                                        ui.colored_label(egui::Color32::from_rgb(255, 170, 0), "Updated since you solved it");
+                                        if ui.button("Re-verify").on_hover_text("Run your last passing solution against the new tests").clicked() {
+                                            Self::run_reverify(storage, &mut state, std::slice::from_ref(&ex));
+                                        }
+                                    });
                                 }
+                                Self::history_ui(ui, storage, &mut state, &ex);
                             }
                         }
                     }
                 });
-            } else {
+            }
+            Tab::Output => {
                 ui.horizontal(|ui| {
                     ui.heading("Output");
                     let has_sel = state.selected_id.is_some();
-                    let logged_in = rust_game::persistence::load().ok().and_then(|p| p.current_user_id).is_some();
+                    let logged_in = state.progress.current_user_id.is_some();
                     let locked = if let Some(sel) = &state.selected_id { !Self::is_unlocked(&state, sel) } else { true };
                     if ui.add_enabled(has_sel && !locked && logged_in, egui::Button::new("Start")).clicked() {
                         if let Some(id) = state.selected_id.clone() {
                             let res = (|| -> Result<()> {
-                                rust_game::engine::run(state.lessons_root.clone(), rust_game::engine::Command::Start { id: id.clone() })?;
-                                rust_game::engine::run(state.lessons_root.clone(), rust_game::engine::Command::Open { id })?;
+                                rust_game::engine::run(storage, state.lessons_root.clone(), rust_game::engine::Command::Start { id: id.clone() })?;
+                                rust_game::engine::run(storage, state.lessons_root.clone(), rust_game::engine::Command::Open { id })?;
                                 Ok(())
                             })();
+                            Self::reload_progress(&mut state);
                             if let Err(e) = res { state.last_output = format!("{e}"); }
                         }
                     }
                     if ui.add_enabled(has_sel && logged_in, egui::Button::new("Open")).clicked() {
                         if let Some(id) = state.selected_id.clone() {
-                            let _ = rust_game::engine::run(state.lessons_root.clone(), rust_game::engine::Command::Open { id });
+                            let _ = rust_game::engine::run(storage, state.lessons_root.clone(), rust_game::engine::Command::Open { id });
                         }
                     }
                     if ui.add_enabled(has_sel, egui::Button::new("Next ▶")).clicked() {
@@ -492,52 +1122,40 @@ impl eframe::App for GuiApp {
                 });
                 egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
                     ui.add(egui::TextEdit::multiline(&mut state.last_output).font(egui::TextStyle::Monospace).desired_rows(18).desired_width(f32::INFINITY));
+                    if let Some((_, cov, src)) = state.coverage.as_ref().filter(|(id, _, _)| Some(id) == state.selected_id.as_ref()) {
+                        Self::coverage_ui(ui, cov, src);
+                    }
                 });
             }
+            Tab::Review => Self::review_ui(ui, storage, &mut state),
+            Tab::Achievements => Self::achievements_ui(ui, &state),
+            Tab::Leaderboard => Self::leaderboard_ui(ui, storage, &mut state),
+            }
             if let Some(sel) = &state.selected_id {
                 if let Ok(list) = rust_game::exercise::load_all(&state.lessons_root) {
                     if let Some(ex) = list.into_iter().find(|e| &e.meta.id == sel) {
                         ui.label(format!("Title: {}", ex.meta.title));
                         ui.label(format!("Difficulty: {}", ex.meta.difficulty));
-                        if let Some(h) = ex.meta.hint.as_deref() { ui.label(format!("Hint: {h}")); }
-                        if let Ok(Some(qz)) = rust_game::quiz::load_quiz(&ex.root) {
-                            ui.separator();
-                            ui.label(egui::RichText::new(qz.title).strong());
-                            for (qi, q) in qz.questions.iter().enumerate() {
-                                ui.label(format!("Q{}: {}", qi + 1, q.prompt));
-                                let mut selected: Option<usize> = None;
-                                for (i, opt) in q.options.iter().enumerate() {
-                                    if ui.selectable_label(false, opt).clicked() { selected = Some(i); }
-                                }
-                                if let Some(i) = selected {
-                                    let correct = i == q.answer_index;
-                                    let msg = if correct { "✅ Correct!" } else { "❌ Try again." };
-                                    ui.label(msg);
-                                    if correct {
-                                        if let Ok(mut p) = rust_game::persistence::load() {
-                                            let e = p.exercises.entry(ex.meta.id.clone()).or_default();
-                                            e.quiz_completed = true;
-                                            let _ = rust_game::persistence::save(&p);
-                                        }
-                                    }
-                                }
-                                ui.add_space(8.0);
-                            }
+                        if !ex.meta.hints.is_empty() {
+                            let n = rust_game::hints::revealed_count(&ex.meta, &state.progress);
+                            ui.label(format!("Hints revealed: {}/{}", n, ex.meta.hints.len()));
                         }
-                        if let Ok(p) = rust_game::persistence::load() {
-                            if let Some(ep) = p.exercises.get(&ex.meta.id) {
-                                ui.label(format!("Attempts: {}", ep.attempts));
-                                ui.label(format!("Completed: {}", ep.completed));
-                                if let Some(b) = ep.best_duration_secs { ui.label(format!("Best time: {}s", b)); }
-                                if let Some(l) = ep.last_duration_secs { ui.label(format!("Last time: {}s", l)); }
-                                if ep.points_earned > 0 { ui.label(format!("Points: {}", ep.points_earned)); }
-                            }
-                            if !p.badges.is_empty() {
+                        match rust_game::quiz::load_quiz(&ex.root) {
+                            Ok(Some(qz)) => Self::quiz_ui(ui, &mut state, &ex, &qz),
+                            Ok(None) => {}
+                            Err(e) => {
                                 ui.separator();
-                                ui.label("Badges:");
-                                for b in &p.badges { ui.label(format!("🏅 {}", b)); }
+                                ui.colored_label(egui::Color32::LIGHT_RED, format!("Quiz could not be loaded: {e:#}"));
                             }
                         }
+                        let p = &state.progress;
+                        if let Some(ep) = p.exercises.get(&ex.meta.id) {
+                            ui.label(format!("Attempts: {}", ep.attempts));
+                            ui.label(format!("Completed: {}", ep.completed));
+                            if let Some(b) = ep.best_duration_secs { ui.label(format!("Best time: {}s", b)); }
+                            if let Some(l) = ep.last_duration_secs { ui.label(format!("Last time: {}s", l)); }
+                            if ep.points_earned > 0 { ui.label(format!("Points: {}", ep.points_earned)); }
+                        }
                         if let Ok(path) = ex.working_file() {
                             ui.separator();
                             ui.label("Edit your solution here:");
@@ -550,17 +1168,18 @@ impl eframe::App for GuiApp {
             ui.heading("Actions");
             ui.horizontal(|ui| {
                 let has_sel = state.selected_id.is_some();
-                let logged_in = rust_game::persistence::load().ok().and_then(|p| p.current_user_id).is_some();
+                let logged_in = state.progress.current_user_id.is_some();
                 let locked = if let Some(sel) = &state.selected_id { !Self::is_unlocked(&state, sel) } else { true };
                 if ui.add_enabled(has_sel && !locked && logged_in, egui::Button::new("Start")).clicked() {
                     if let Some(id) = state.selected_id.clone() {
                         let res = (|| -> Result<()> {
                             let ex = rust_game::engine::Command::Start { id };
-                            rust_game::engine::run(state.lessons_root.clone(), ex)?;
+                            rust_game::engine::run(storage, state.lessons_root.clone(), ex)?;
                             let id = state.selected_id.clone().unwrap();
-                            rust_game::engine::run(state.lessons_root.clone(), rust_game::engine::Command::Open { id })?;
+                            rust_game::engine::run(storage, state.lessons_root.clone(), rust_game::engine::Command::Open { id })?;
                             Ok(())
                         })();
+                        Self::reload_progress(&mut state);
                         if let Err(e) = res { state.last_output = format!("{e}"); }
                     }
                 }
@@ -568,7 +1187,7 @@ impl eframe::App for GuiApp {
                     if let Some(id) = state.selected_id.clone() {
                         let res = (|| -> Result<()> {
                             let ex = rust_game::engine::Command::Open { id };
-                            rust_game::engine::run(state.lessons_root.clone(), ex)?;
+                            rust_game::engine::run(storage, state.lessons_root.clone(), ex)?;
                             Ok(())
                         })();
                         if let Err(e) = res { state.last_output = format!("{e}"); }
@@ -577,11 +1196,12 @@ impl eframe::App for GuiApp {
                 if ui.add_enabled(has_sel && logged_in, egui::Button::new("Reset Solution")).clicked() {
                     if let Some(id) = state.selected_id.clone() {
                         let res = (|| -> Result<()> {
-                            rust_game::engine::run(state.lessons_root.clone(), rust_game::engine::Command::Reset { id: id.clone() })?;
-                            rust_game::engine::run(state.lessons_root.clone(), rust_game::engine::Command::Start { id: id.clone() })?;
-                            rust_game::engine::run(state.lessons_root.clone(), rust_game::engine::Command::Open { id })?;
+                            rust_game::engine::run(storage, state.lessons_root.clone(), rust_game::engine::Command::Reset { id: id.clone() })?;
+                            rust_game::engine::run(storage, state.lessons_root.clone(), rust_game::engine::Command::Start { id: id.clone() })?;
+                            rust_game::engine::run(storage, state.lessons_root.clone(), rust_game::engine::Command::Open { id })?;
                             Ok(())
                         })();
+                        Self::reload_progress(&mut state);
                         if let Err(e) = res { state.last_output = format!("{e}"); }
                     }
                 }
@@ -599,13 +1219,15 @@ impl eframe::App for GuiApp {
                     }
                 }
                 if ui.add_enabled(has_sel && logged_in, egui::Button::new("Check")).clicked() {
-                    Self::run_engine_check(&mut state);
+                    Self::run_engine_check(storage, &mut state);
                 }
+                ui.checkbox(&mut state.measure_coverage, "Coverage")
+                    .on_hover_text("After a pass, rebuild with coverage instrumentation and highlight lines the tests never ran");
                 if ui.add_enabled(has_sel && logged_in, egui::Button::new("Reset")).clicked() {
                     if let Some(id) = state.selected_id.clone() {
                         let res = (|| -> Result<()> {
                             let ex = rust_game::engine::Command::Reset { id };
-                            rust_game::engine::run(state.lessons_root.clone(), ex)?;
+                            rust_game::engine::run(storage, state.lessons_root.clone(), ex)?;
                             Ok(())
                         })();
                         if let Err(e) = res { state.last_output = format!("{e}"); }
@@ -617,7 +1239,7 @@ impl eframe::App for GuiApp {
                             let ex = rust_game::exercise::load_all(&state.lessons_root)?
                                 .into_iter().find(|e| e.meta.id == id)
                                 .ok_or_else(|| anyhow::anyhow!("Exercise not found"))?;
-                            Ok(ex.meta.hint.unwrap_or_else(|| "No hint available.".to_string()))
+                            Ok(Self::reveal_hint(&mut state, &ex))
                         })();
                         state.last_output = match out { Ok(s) => s, Err(e) => format!("{e}") };
                     }
@@ -641,17 +1263,15 @@ impl eframe::App for GuiApp {
                 ui.add_space(12.0);
                 if let Ok(list) = rust_game::exercise::load_all(&state.lessons_root) {
                     let total = list.len();
-                    if let Ok(p) = rust_game::persistence::load() {
-                        let done = p.exercises.values().filter(|e| e.completed).count();
-                        let badge = match (done * 100) / (total.max(1)) {
-                            0..=24 => "Novice",
-                            25..=49 => "Bronze",
-                            50..=74 => "Silver",
-                            75..=99 => "Gold",
-                            _ => "Platinum",
-                        };
-                        ui.small(format!("Progress: {done}/{total} – Badge: {badge}"));
+                    let done = state.progress.exercises.values().filter(|e| e.completed).count();
+                    ui.small(format!("Progress: {done}/{total}"));
+                    let defs = &state.achievements;
+                    let unlocked = defs.iter().filter(|a| state.progress.achievements.contains_key(&a.id)).count();
+                    if !defs.is_empty() && ui.small_button(format!("🏅 {unlocked}/{}", defs.len())).on_hover_text("Achievements").clicked() {
+                        state.tab = Tab::Achievements;
                     }
+                    ui.add_space(12.0);
+                    Self::daily_ui(ui, storage, &mut state, &list);
                 }
             });
         });
@@ -0,0 +1,277 @@
+//! Portable progress bundles: progress, working copies and attempt history in one JSON file.
+
+use crate::{
+    exercise::Exercise,
+    persistence::{self, Progress},
+    review::ReviewCard,
+    storage::{Attempt, Storage},
+    util,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use walkdir::WalkDir;
+
+const FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    pub format: u32,
+    pub exported_at: i64,
+    #[serde(default)]
+    pub username: Option<String>,
+    pub progress: Progress,
+    /// Files under `work/`, keyed by their `/`-separated relative path.
+    #[serde(default)]
+    pub work: BTreeMap<String, String>,
+    #[serde(default)]
+    pub attempts: Vec<Attempt>,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub lessons_merged: usize,
+    pub files_restored: usize,
+    /// Local files that differed; the imported copy was saved beside them.
+    pub files_conflicting: Vec<String>,
+    pub attempts_added: usize,
+    pub attempts_skipped: bool,
+}
+
+pub fn build(storage: &Storage) -> Result<Bundle> {
+    let progress = persistence::load()?;
+    let mut work = BTreeMap::new();
+    let root = Exercise::working_dir()?;
+    for entry in WalkDir::new(&root).into_iter().filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        let rel = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+        let key = rel.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect::<Vec<_>>().join("/");
+        if let Ok(data) = fs::read_to_string(entry.path()) { work.insert(key, data); }
+    }
+    let attempts = match progress.current_user_id { Some(uid) => storage.attempts_for_user(uid)?, None => Vec::new() };
+    Ok(Bundle {
+        format: FORMAT,
+        exported_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64,
+        username: progress.current_username.clone(),
+        progress,
+        work,
+        attempts,
+    })
+}
+
+pub fn export(storage: &Storage, path: &Path) -> Result<Bundle> {
+    let bundle = build(storage)?;
+    let data = serde_json::to_string_pretty(&bundle)?;
+    util::write_atomic(path, data.as_bytes())?;
+    Ok(bundle)
+}
+
+pub fn read(path: &Path) -> Result<Bundle> {
+    let data = fs::read_to_string(path).with_context(|| format!("read bundle {}", path.display()))?;
+    let bundle: Bundle = serde_json::from_str(&data).with_context(|| format!("parse bundle {}", path.display()))?;
+    if bundle.format > FORMAT { anyhow::bail!("Bundle format {} is newer than this version supports ({})", bundle.format, FORMAT); }
+    Ok(bundle)
+}
+
+pub fn import(storage: &Storage, path: &Path) -> Result<ImportSummary> {
+    let bundle = read(path)?;
+    let mut summary = ImportSummary::default();
+
+    let (lessons_merged, uid) = persistence::update(|prog| (merge_progress(prog, &bundle.progress), prog.current_user_id))?;
+    summary.lessons_merged = lessons_merged;
+
+    let root = Exercise::working_dir()?;
+    for (key, data) in &bundle.work {
+        let rel = Path::new(key);
+        if !rel.components().all(|c| matches!(c, Component::Normal(_))) { continue; }
+        let dest = root.join(rel);
+        match fs::read_to_string(&dest) {
+            Ok(local) if local == *data => {}
+            Ok(_) => {
+                let mut side = dest.as_os_str().to_owned();
+                side.push(".imported");
+                util::write_atomic(Path::new(&side), data.as_bytes())?;
+                summary.files_conflicting.push(key.clone());
+            }
+            Err(_) => {
+                util::write_atomic(&dest, data.as_bytes())?;
+                summary.files_restored += 1;
+            }
+        }
+    }
+
+    match uid {
+        Some(uid) => {
+            summary.attempts_added = storage.transaction(|db| {
+                let existing = db.attempts_for_user(uid)?;
+                let mut added = 0;
+                for a in &bundle.attempts {
+                    let dup = existing.iter().any(|e| e.lesson_id == a.lesson_id && e.timestamp == a.timestamp && e.passed == a.passed);
+                    if !dup {
+                        let id = db.record_attempt(uid, &a.lesson_id, a.passed, a.duration_secs, a.timestamp, a.content_hash.as_deref())?;
+                        if let Some(p) = a.points { db.set_attempt_points(id, p)?; }
+                        added += 1;
+                    }
+                }
+                Ok(added)
+            })?;
+        }
+        None => summary.attempts_skipped = !bundle.attempts.is_empty(),
+    }
+    Ok(summary)
+}
+
+/// Merges `incoming` into `local`: completions are unioned, best times and
+/// points keep the better value, review cards and the daily state keep the
+/// more recent one, and local profile settings win. Returns the number of
+/// lessons touched.
+pub fn merge_progress(local: &mut Progress, incoming: &Progress) -> usize {
+    let mut touched = 0;
+    for (id, theirs) in &incoming.exercises {
+        touched += 1;
+        let ours = local.exercises.entry(id.clone()).or_default();
+        let before = ours.points_earned;
+        // What the first pass recorded comes from whichever side passed first.
+        let theirs_first = theirs.attempts_at_pass.is_some()
+            && (ours.attempts_at_pass.is_none() || theirs.completed_at.is_some_and(|t| ours.completed_at.is_none_or(|o| t < o)));
+        if theirs_first {
+            ours.attempts_at_pass = theirs.attempts_at_pass;
+            ours.hints_at_pass = theirs.hints_at_pass;
+            ours.solution_viewed_at_pass = theirs.solution_viewed_at_pass;
+            ours.duration_at_pass = theirs.duration_at_pass;
+            ours.lint_warnings = theirs.lint_warnings;
+        }
+        ours.attempts = ours.attempts.max(theirs.attempts);
+        ours.completed |= theirs.completed;
+        ours.quiz_completed |= theirs.quiz_completed;
+        ours.first_started_at = min_some(ours.first_started_at, theirs.first_started_at);
+        ours.last_started_at = ours.last_started_at.max(theirs.last_started_at);
+        ours.completed_at = min_some(ours.completed_at, theirs.completed_at);
+        ours.best_duration_secs = min_some(ours.best_duration_secs, theirs.best_duration_secs);
+        if ours.last_duration_secs.is_none() { ours.last_duration_secs = theirs.last_duration_secs; }
+        ours.points_earned = ours.points_earned.max(theirs.points_earned);
+        if ours.feedback_helpful.is_none() { ours.feedback_helpful = theirs.feedback_helpful; }
+        ours.hints_revealed = ours.hints_revealed.max(theirs.hints_revealed);
+        ours.solution_viewed |= theirs.solution_viewed;
+        if ours.content_hash.is_none() { ours.content_hash = theirs.content_hash.clone(); }
+        let mut quiz_cards = std::mem::take(&mut ours.quiz.review);
+        if theirs.quiz.best_score > ours.quiz.best_score { ours.quiz = theirs.quiz.clone(); }
+        for (i, card) in &theirs.quiz.review {
+            let mine = quiz_cards.remove(i);
+            quiz_cards.insert(*i, newer_card(mine.as_ref(), card).clone());
+        }
+        ours.quiz.review = quiz_cards;
+        if let Some(card) = &theirs.review {
+            ours.review = Some(newer_card(ours.review.as_ref(), card).clone());
+        }
+        local.total_points = local.total_points.saturating_add(ours.points_earned - before);
+    }
+    local.streak.best = local.streak.best.max(incoming.streak.best);
+    local.streak.last_daily_day = local.streak.last_daily_day.max(incoming.streak.last_daily_day);
+    // Milestones belong to the streak starting on `start_day`: the newer streak wins, the same one unions.
+    if incoming.streak.start_day > local.streak.start_day {
+        local.streak.start_day = incoming.streak.start_day;
+        local.streak.milestones = incoming.streak.milestones.clone();
+    } else if incoming.streak.start_day == local.streak.start_day {
+        for m in &incoming.streak.milestones {
+            if !local.streak.milestones.contains(m) { local.streak.milestones.push(*m); }
+        }
+        local.streak.milestones.sort_unstable();
+    }
+    for b in &incoming.badges {
+        if !local.badges.contains(b) { local.badges.push(b.clone()); }
+    }
+    for (id, at) in &incoming.achievements {
+        let ours = local.achievements.entry(id.clone()).or_insert(*at);
+        *ours = (*ours).min(*at);
+    }
+    touched
+}
+
+/// The card reviewed last, or due later when neither or both were reviewed at the same time.
+fn newer_card<'a>(ours: Option<&'a ReviewCard>, theirs: &'a ReviewCard) -> &'a ReviewCard {
+    match ours {
+        Some(o) if (o.last_reviewed_at, o.due_at) >= (theirs.last_reviewed_at, theirs.due_at) => o,
+        _ => theirs,
+    }
+}
+
+fn min_some<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(x), Some(y)) => Some(x.min(y)),
+        (x, y) => x.or(y),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::ExerciseProgress;
+
+    #[test]
+    fn merge_keeps_best_of_both() {
+        let mut local = Progress::default();
+        local.exercises.insert("intro/variables".into(), ExerciseProgress { attempts: 3, completed: true, completed_at: Some(500), best_duration_secs: Some(90), points_earned: 10, attempts_at_pass: Some(3), lint_warnings: Some(2), ..Default::default() });
+        local.total_points = 10;
+
+        let mut theirs = Progress::default();
+        theirs.exercises.insert("intro/variables".into(), ExerciseProgress {
+            attempts: 1, completed: true, completed_at: Some(200), best_duration_secs: Some(40), points_earned: 10,
+            attempts_at_pass: Some(1), hints_at_pass: Some(0), solution_viewed_at_pass: Some(false), lint_warnings: Some(0), solution_viewed: true,
+            ..Default::default()
+        });
+        theirs.exercises.insert("intro/functions".into(), ExerciseProgress { attempts: 2, completed: true, points_earned: 10, content_hash: Some("3f2a".into()), ..Default::default() });
+        theirs.badges.push("Century".into());
+
+        assert_eq!(merge_progress(&mut local, &theirs), 2);
+        let v = &local.exercises["intro/variables"];
+        assert_eq!(v.best_duration_secs, Some(40));
+        assert_eq!(v.attempts, 3);
+        // Their pass came first, so its record wins.
+        assert_eq!((v.attempts_at_pass, v.hints_at_pass, v.lint_warnings), (Some(1), Some(0), Some(0)));
+        assert!(v.solution_viewed);
+        assert!(local.exercises["intro/functions"].completed);
+        assert_eq!(local.exercises["intro/functions"].content_hash.as_deref(), Some("3f2a"));
+        assert_eq!(local.total_points, 20);
+        assert_eq!(local.badges, vec!["Century".to_string()]);
+
+        // Merging the same bundle again changes nothing.
+        merge_progress(&mut local, &theirs);
+        assert_eq!(local.total_points, 20);
+    }
+
+    #[test]
+    fn merge_keeps_newer_schedules_and_daily_state() {
+        let card = |due_at, last| ReviewCard { due_at, last_reviewed_at: last, ..ReviewCard::new(0) };
+        let mut local = Progress::default();
+        (local.streak.start_day, local.streak.milestones, local.streak.last_daily_day) = (Some(10), vec![3], Some(12));
+        let ep = local.exercises.entry("intro/a".into()).or_default();
+        ep.review = Some(card(500, Some(100)));
+        ep.quiz.review.insert(0, card(900, Some(300)));
+        ep.quiz.review.insert(1, card(200, None));
+
+        let mut theirs = Progress::default();
+        (theirs.streak.start_day, theirs.streak.milestones, theirs.streak.last_daily_day) = (Some(10), vec![7], Some(15));
+        let ep = theirs.exercises.entry("intro/a".into()).or_default();
+        ep.review = Some(card(800, Some(400)));
+        ep.quiz.best_score = Some(3);
+        ep.quiz.review.insert(0, card(600, Some(200)));
+        ep.quiz.review.insert(2, card(700, Some(250)));
+
+        merge_progress(&mut local, &theirs);
+        assert_eq!((local.streak.milestones.clone(), local.streak.last_daily_day), (vec![3, 7], Some(15)));
+        let ep = &local.exercises["intro/a"];
+        assert_eq!(ep.review.as_ref().map(|c| c.due_at), Some(800));
+        let dues: Vec<(usize, i64)> = ep.quiz.review.iter().map(|(i, c)| (*i, c.due_at)).collect();
+        assert_eq!(dues, vec![(0, 900), (1, 200), (2, 700)]);
+
+        // A newer streak replaces the milestones of the old one.
+        theirs.streak.start_day = Some(20);
+        merge_progress(&mut local, &theirs);
+        assert_eq!((local.streak.start_day, local.streak.milestones.clone()), (Some(20), vec![7]));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,189 @@
+//! Instructor view over a cohort: learners × lessons built from exported
+//! bundles or a shared SQLite file, with stuck lessons flagged.
+
+use crate::{
+    bundle,
+    exercise::Exercise,
+    persistence::ExerciseProgress,
+    storage::{Attempt, Storage},
+};
+use anyhow::Result;
+use std::{collections::BTreeMap, fmt::Write as _, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    NotStarted,
+    InProgress,
+    Completed,
+}
+
+impl Status {
+    pub fn as_str(self) -> &'static str {
+        match self { Status::NotStarted => "not_started", Status::InProgress => "in_progress", Status::Completed => "completed" }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub status: Status,
+    pub attempts: u32,
+    /// From the first start (or attempt) to the pass, or to the latest attempt.
+    pub time_secs: Option<u64>,
+}
+
+impl Cell {
+    pub const EMPTY: Cell = Cell { status: Status::NotStarted, attempts: 0, time_secs: None };
+}
+
+#[derive(Debug, Clone)]
+pub struct Learner {
+    pub name: String,
+    pub lessons: BTreeMap<String, Cell>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Matrix {
+    /// Catalog order, then lessons only seen in learner data.
+    pub lessons: Vec<String>,
+    pub learners: Vec<Learner>,
+}
+
+/// A learner is stuck on a lesson after this many checks without passing, by default.
+pub const STUCK_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct StuckLesson {
+    pub lesson_id: String,
+    pub stuck: Vec<String>,
+    /// Learners who have started the lesson.
+    pub started: usize,
+}
+
+/// Combines what a progress file and the attempts table know about one lesson.
+pub fn cell(ep: Option<&ExerciseProgress>, attempts: &[&Attempt]) -> Cell {
+    let passed = attempts.iter().filter(|a| a.passed).min_by_key(|a| a.timestamp);
+    let completed = ep.is_some_and(|e| e.completed) || passed.is_some();
+    let started = ep.is_some_and(|e| e.first_started_at.is_some() || e.attempts > 0) || !attempts.is_empty();
+    let status = if completed { Status::Completed } else if started { Status::InProgress } else { Status::NotStarted };
+    let n = ep.map(|e| e.attempts).unwrap_or(0).max(attempts.len() as u32);
+    let first = attempts.iter().map(|a| a.timestamp).min();
+    let last = attempts.iter().map(|a| a.timestamp).max();
+    let start = ep.and_then(|e| e.first_started_at).into_iter().chain(first).min();
+    let end = if completed { ep.and_then(|e| e.completed_at).or(passed.map(|a| a.timestamp)) } else { last };
+    let span = start.zip(end).map(|(s, e)| (e - s).max(0) as u64);
+    let time_secs = match (span, passed.and_then(|a| a.duration_secs)) {
+        (Some(s), Some(d)) => Some(s.max(d)),
+        (s, d) => s.or(d),
+    };
+    Cell { status, attempts: n, time_secs }
+}
+
+fn learner(name: String, exercises: &std::collections::HashMap<String, ExerciseProgress>, attempts: &[Attempt]) -> Learner {
+    let mut by_lesson: BTreeMap<&str, Vec<&Attempt>> = BTreeMap::new();
+    for a in attempts { by_lesson.entry(&a.lesson_id).or_default().push(a); }
+    let mut ids: Vec<&str> = exercises.keys().map(String::as_str).chain(by_lesson.keys().copied()).collect();
+    ids.sort();
+    ids.dedup();
+    let lessons = ids.into_iter()
+        .map(|id| (id.to_string(), cell(exercises.get(id), by_lesson.get(id).map(Vec::as_slice).unwrap_or(&[]))))
+        .filter(|(_, c)| c.status != Status::NotStarted)
+        .collect();
+    Learner { name, lessons }
+}
+
+/// One learner per bundle, named after its user, display name or file.
+pub fn from_bundle(path: &Path) -> Result<Learner> {
+    let b = bundle::read(path)?;
+    let name = b.username.clone()
+        .or_else(|| b.progress.display_name.clone().filter(|n| n != "Player"))
+        .unwrap_or_else(|| path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default());
+    Ok(learner(name, &b.progress.exercises, &b.attempts))
+}
+
+/// One learner per user in the database, from attempts alone.
+pub fn from_storage(storage: &Storage) -> Result<Vec<Learner>> {
+    let none = Default::default();
+    storage.users()?.into_iter()
+        .map(|u| Ok(learner(u.username.clone(), &none, &storage.attempts_for_user(u.id)?)))
+        .collect()
+}
+
+pub fn matrix(catalog: &[Exercise], learners: Vec<Learner>) -> Matrix {
+    let mut lessons: Vec<String> = catalog.iter().map(|e| e.meta.id.clone()).collect();
+    for l in &learners {
+        for id in l.lessons.keys() {
+            if !lessons.contains(id) { lessons.push(id.clone()); }
+        }
+    }
+    Matrix { lessons, learners }
+}
+
+impl Matrix {
+    pub fn get(&self, learner: &Learner, lesson_id: &str) -> Cell {
+        learner.lessons.get(lesson_id).cloned().unwrap_or(Cell::EMPTY)
+    }
+
+    /// Lessons where at least `min_share` percent of the learners who started
+    /// them have `attempts` or more checks without a pass, worst first.
+    pub fn stuck(&self, attempts: u32, min_share: u32) -> Vec<StuckLesson> {
+        let mut out: Vec<StuckLesson> = self.lessons.iter().filter_map(|id| {
+            let cells: Vec<(&Learner, Cell)> = self.learners.iter().map(|l| (l, self.get(l, id))).collect();
+            let started = cells.iter().filter(|(_, c)| c.status != Status::NotStarted).count();
+            let stuck: Vec<String> = cells.iter()
+                .filter(|(_, c)| c.status == Status::InProgress && c.attempts >= attempts)
+                .map(|(l, _)| l.name.clone())
+                .collect();
+            (!stuck.is_empty() && stuck.len() * 100 >= started * min_share as usize)
+                .then(|| StuckLesson { lesson_id: id.clone(), stuck, started })
+        }).collect();
+        out.sort_by(|a, b| b.stuck.len().cmp(&a.stuck.len()).then_with(|| a.lesson_id.cmp(&b.lesson_id)));
+        out
+    }
+
+    /// `learner,lesson,status,attempts,time_secs`, one row per learner and lesson.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("learner,lesson,status,attempts,time_secs\n");
+        for l in &self.learners {
+            for id in &self.lessons {
+                let c = self.get(l, id);
+                let time = c.time_secs.map(|t| t.to_string()).unwrap_or_default();
+                let _ = writeln!(out, "{},{},{},{},{}", csv_field(&l.name), csv_field(id), c.status.as_str(), c.attempts, time);
+            }
+        }
+        out
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) { format!("\"{}\"", s.replace('"', "\"\"")) } else { s.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(lesson: &str, passed: bool, ts: i64) -> Attempt {
+        Attempt { id: 0, user_id: 1, lesson_id: lesson.into(), passed, duration_secs: None, timestamp: ts, points: None, content_hash: None }
+    }
+
+    #[test]
+    fn builds_matrix_and_flags_stuck() {
+        let mut progress = std::collections::HashMap::new();
+        progress.insert("intro/a".to_string(), ExerciseProgress { completed: true, attempts: 2, first_started_at: Some(100), completed_at: Some(400), ..Default::default() });
+        let ana = learner("ana".into(), &progress, &[attempt("intro/b", false, 10), attempt("intro/b", false, 20), attempt("intro/b", false, 70)]);
+        let bo = learner("bo, jr".into(), &Default::default(), &[attempt("intro/b", false, 5), attempt("intro/b", true, 50)]);
+        let m = Matrix { lessons: vec!["intro/a".into(), "intro/b".into()], learners: vec![ana, bo] };
+
+        assert_eq!(m.get(&m.learners[0], "intro/a"), Cell { status: Status::Completed, attempts: 2, time_secs: Some(300) });
+        assert_eq!(m.get(&m.learners[0], "intro/b"), Cell { status: Status::InProgress, attempts: 3, time_secs: Some(60) });
+        assert_eq!(m.get(&m.learners[1], "intro/b").status, Status::Completed);
+
+        let stuck = m.stuck(STUCK_ATTEMPTS, 50);
+        assert_eq!((stuck[0].lesson_id.as_str(), stuck[0].stuck.clone(), stuck[0].started), ("intro/b", vec!["ana".to_string()], 2));
+        assert!(m.stuck(STUCK_ATTEMPTS, 60).is_empty());
+
+        let csv = m.to_csv();
+        assert!(csv.contains("\n\"bo, jr\",intro/a,not_started,0,\n"), "{csv}");
+        assert!(csv.contains("\nana,intro/b,in_progress,3,60\n"));
+    }
+}
@@ -0,0 +1,175 @@
+//! Optional line coverage of the learner's `lib.rs`: rebuilds the sandbox
+//! with `-C instrument-coverage`, runs the tests and reads the result back
+//! through `llvm-profdata` and `llvm-cov`.
+
+use crate::{exercise::Exercise, grader};
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+use wait_timeout::ChildExt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    /// Lines with code.
+    pub total: u32,
+    pub covered: u32,
+    /// 1-based line numbers in `lib.rs` the tests never reached.
+    pub uncovered: Vec<u32>,
+}
+
+impl Coverage {
+    pub fn percent(&self) -> u32 {
+        (self.covered * 100).checked_div(self.total).unwrap_or(100)
+    }
+}
+
+struct Tools {
+    profdata: PathBuf,
+    cov: PathBuf,
+}
+
+/// Prefers rustup's `llvm-tools` component, whose LLVM matches rustc's,
+/// then whatever is on `PATH`.
+fn tools() -> Option<Tools> {
+    let sysroot = Command::new("rustc").args(["--print", "sysroot"]).output().ok()
+        .map(|o| PathBuf::from(String::from_utf8_lossy(&o.stdout).trim()));
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Some(root) = sysroot {
+        if let Ok(targets) = fs::read_dir(root.join("lib").join("rustlib")) {
+            dirs.extend(targets.filter_map(|t| t.ok()).map(|t| t.path().join("bin")));
+        }
+    }
+    dirs.extend(std::env::var_os("PATH").map(|p| std::env::split_paths(&p).collect::<Vec<_>>()).unwrap_or_default());
+    dirs.iter().find_map(|d| {
+        let (profdata, cov) = (d.join("llvm-profdata"), d.join("llvm-cov"));
+        (profdata.is_file() && cov.is_file()).then_some(Tools { profdata, cov })
+    })
+}
+
+/// Coverage of the sandbox from the last `grade`. `None` if the LLVM tools are
+/// not installed; an error if the instrumented build or the report fails.
+pub fn measure(ex: &Exercise, timeout_secs: u64) -> Result<Option<Coverage>> {
+    let Some(tools) = tools() else { return Ok(None) };
+    let proj = grader::sandbox_dir(ex)?;
+    let _lock = grader::lock_sandbox(&proj)?;
+    if !proj.join("src").join("lib.rs").exists() { bail!("run a check first"); }
+    // A separate target dir keeps the normal build cached.
+    let target = proj.join("target-cov");
+    let profiles = proj.join("coverage");
+    let _ = fs::remove_dir_all(&profiles);
+    fs::create_dir_all(&profiles)?;
+
+    let mut build = Command::new("cargo");
+    build.args(["test", "--no-run", "--quiet", "--message-format", "json"])
+        .env("RUSTFLAGS", "-C instrument-coverage")
+        .env("CARGO_TARGET_DIR", &target)
+        .env("LLVM_PROFILE_FILE", profiles.join("build-%p.profraw"))
+        .current_dir(&proj)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let out = run(&mut build, timeout_secs, "instrumented build")?;
+    let binaries = test_binaries(&out);
+    if binaries.is_empty() { bail!("instrumented build produced no test binaries"); }
+
+    for bin in &binaries {
+        let mut cmd = Command::new(bin);
+        cmd.arg("--quiet")
+            .env("LLVM_PROFILE_FILE", profiles.join("test-%p-%m.profraw"))
+            .current_dir(&proj)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // Failing tests still leave a profile, so the status doesn't matter.
+        let _ = run(&mut cmd, timeout_secs, "instrumented tests");
+    }
+
+    let raw: Vec<PathBuf> = fs::read_dir(&profiles)?.filter_map(|e| e.ok()).map(|e| e.path())
+        .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with("test-")))
+        .collect();
+    if raw.is_empty() { bail!("the tests wrote no coverage profile"); }
+    let merged = profiles.join("merged.profdata");
+    let mut merge = Command::new(&tools.profdata);
+    merge.args(["merge", "-sparse"]).args(&raw).arg("-o").arg(&merged);
+    run(&mut merge, timeout_secs, "llvm-profdata merge")
+        .context("llvm-profdata must match rustc's LLVM; try `rustup component add llvm-tools-preview`")?;
+
+    let mut export = Command::new(&tools.cov);
+    export.args(["export", "--format=lcov", "--instr-profile"]).arg(&merged);
+    for (i, bin) in binaries.iter().enumerate() {
+        if i > 0 { export.arg("--object"); }
+        export.arg(bin);
+    }
+    let lcov = run(&mut export, timeout_secs, "llvm-cov export")?;
+    Ok(Some(parse_lcov(&lcov, "src/lib.rs")))
+}
+
+/// Runs `cmd` to completion and returns its stdout, failing on timeout or a non-zero exit.
+fn run(cmd: &mut Command, timeout_secs: u64, what: &str) -> Result<String> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().with_context(|| format!("spawn {what}"))?;
+    // Read while waiting: a full pipe would stall the child until the timeout.
+    let (stdout, stderr) = (grader::drain(child.stdout.take()), grader::drain(child.stderr.take()));
+    let Some(status) = child.wait_timeout(Duration::from_secs(timeout_secs)).with_context(|| format!("waiting for {what}"))? else {
+        child.kill().ok();
+        child.wait().ok();
+        bail!("{what} timed out after {timeout_secs}s");
+    };
+    let (stdout, err) = (stdout.join().unwrap_or_default(), stderr.join().unwrap_or_default());
+    if !status.success() {
+        bail!("{what} failed: {}", err.lines().find(|l| l.contains("error")).unwrap_or(err.trim()));
+    }
+    Ok(stdout)
+}
+
+/// `"executable"` paths from `cargo --message-format json` artifact messages.
+fn test_binaries(json_lines: &str) -> Vec<PathBuf> {
+    json_lines.lines()
+        .filter_map(|l| serde_json::from_str::<serde_json::Value>(l).ok())
+        .filter(|v| v["reason"] == "compiler-artifact" && v["profile"]["test"] == true)
+        .filter_map(|v| v["executable"].as_str().map(PathBuf::from))
+        .collect()
+}
+
+/// Line hits (`DA:<line>,<count>`) for the source file ending in `suffix`.
+/// A line counts as covered if any region on it ran.
+pub fn parse_lcov(lcov: &str, suffix: &str) -> Coverage {
+    let mut hits: std::collections::BTreeMap<u32, bool> = Default::default();
+    let mut in_file = false;
+    for line in lcov.lines() {
+        if let Some(path) = line.strip_prefix("SF:") {
+            in_file = Path::new(path).ends_with(suffix);
+        } else if line == "end_of_record" {
+            in_file = false;
+        } else if let Some(da) = line.strip_prefix("DA:").filter(|_| in_file) {
+            let mut parts = da.split(',');
+            let (Some(Ok(n)), Some(Ok(count))) = (parts.next().map(str::parse::<u32>), parts.next().map(str::parse::<u64>)) else { continue };
+            *hits.entry(n).or_default() |= count > 0;
+        }
+    }
+    let uncovered: Vec<u32> = hits.iter().filter(|(_, hit)| !**hit).map(|(n, _)| *n).collect();
+    Coverage { total: hits.len() as u32, covered: (hits.len() - uncovered.len()) as u32, uncovered }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_lib_rs_lines_from_lcov() {
+        let lcov = "SF:/s/src/lib.rs\nFN:1,add\nDA:1,3\nDA:2,3\nDA:4,0\nDA:4,1\nDA:6,0\nDA:7,0\nend_of_record\n\
+                    SF:/s/tests/exercise.rs\nDA:1,1\nDA:9,0\nend_of_record\n";
+        let c = parse_lcov(lcov, "src/lib.rs");
+        assert_eq!(c, Coverage { total: 5, covered: 3, uncovered: vec![6, 7] });
+        assert_eq!(c.percent(), 60);
+        assert_eq!(parse_lcov("", "src/lib.rs").percent(), 100);
+
+        let json = r#"{"reason":"compiler-artifact","profile":{"test":true},"executable":"/t/exercise-1"}
+{"reason":"compiler-artifact","profile":{"test":false},"executable":null}
+{"reason":"build-finished","success":true}"#;
+        assert_eq!(test_binaries(json), vec![PathBuf::from("/t/exercise-1")]);
+    }
+}
@@ -0,0 +1,176 @@
+//! Daily challenge and streaks. Days are UTC calendar days; a streak counts
+//! consecutive days with at least one passing check in the attempts table.
+
+use crate::{
+    exercise::Exercise,
+    persistence::{self, Progress},
+    storage::{Attempt, Storage},
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fs, path::Path};
+
+pub const DAY: i64 = 24 * 60 * 60;
+
+/// Streak length in days and the bonus it awards, once per streak.
+pub const MILESTONES: &[(u32, u32)] = &[(3, 10), (7, 25), (14, 50), (30, 100), (100, 300)];
+
+/// Bonus for solving the daily challenge, once per day.
+pub const DAILY_BONUS: u32 = 5;
+
+/// Streak bookkeeping kept in progress so bonuses are paid once.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreakProgress {
+    /// First day of the streak the `milestones` belong to.
+    #[serde(default)]
+    pub start_day: Option<i64>,
+    #[serde(default)]
+    pub milestones: Vec<u32>,
+    #[serde(default)]
+    pub best: u32,
+    #[serde(default)]
+    pub last_daily_day: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DailyStatus {
+    pub day: i64,
+    pub lesson_id: Option<String>,
+    pub done: bool,
+    pub streak: u32,
+    pub best: u32,
+    /// Next milestone not yet reached in the current streak, with its bonus.
+    pub next_milestone: Option<(u32, u32)>,
+}
+
+pub fn day_of(timestamp: i64) -> i64 { timestamp.div_euclid(DAY) }
+
+pub fn passing_days(attempts: &[Attempt]) -> BTreeSet<i64> {
+    attempts.iter().filter(|a| a.passed).map(|a| day_of(a.timestamp)).collect()
+}
+
+/// Current streak. It stays alive through today until midnight, so a streak
+/// that ended yesterday still counts until then.
+pub fn current_streak(days: &BTreeSet<i64>, today: i64) -> u32 {
+    let mut d = if days.contains(&today) { today } else { today - 1 };
+    let mut n = 0;
+    while days.contains(&d) { n += 1; d -= 1; }
+    n
+}
+
+pub fn longest_streak(days: &BTreeSet<i64>) -> u32 {
+    let (mut best, mut run, mut prev) = (0, 0, None);
+    for d in days {
+        run = if prev == Some(d - 1) { run + 1 } else { 1 };
+        best = best.max(run);
+        prev = Some(*d);
+    }
+    best
+}
+
+/// Lesson ids listed as `pool = [...]` in `<lessons_root>/daily.toml`, if present.
+pub fn load_pool(lessons_root: &str) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    struct Pool { #[serde(default)] pool: Vec<String> }
+    let p = Path::new(lessons_root).join("daily.toml");
+    if !p.exists() { return Ok(Vec::new()); }
+    let data = fs::read_to_string(&p).with_context(|| format!("read {}", p.display()))?;
+    Ok(toml::from_str::<Pool>(&data).with_context(|| format!("parse {}", p.display()))?.pool)
+}
+
+/// The day's challenge: drawn from `pool` when it names known lessons,
+/// otherwise from every lesson. Same day, same lesson, for everyone.
+pub fn challenge_for<'a>(lessons: &'a [Exercise], pool: &[String], day: i64) -> Option<&'a Exercise> {
+    let mut candidates: Vec<&Exercise> = lessons.iter().filter(|e| pool.contains(&e.meta.id)).collect();
+    if candidates.is_empty() { candidates = lessons.iter().collect(); }
+    candidates.sort_by(|a, b| a.meta.id.cmp(&b.meta.id));
+    if candidates.is_empty() { return None; }
+    Some(candidates[(mix(day as u64) % candidates.len() as u64) as usize])
+}
+
+/// splitmix64, so neighbouring days land on unrelated lessons.
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+pub fn status(lessons: &[Exercise], pool: &[String], attempts: &[Attempt], prog: &Progress, now: i64) -> DailyStatus {
+    let day = day_of(now);
+    let days = passing_days(attempts);
+    let lesson_id = challenge_for(lessons, pool, day).map(|e| e.meta.id.clone());
+    let done = lesson_id.as_ref().is_some_and(|id| attempts.iter().any(|a| a.passed && &a.lesson_id == id && day_of(a.timestamp) == day));
+    let streak = current_streak(&days, day);
+    let start = day - streak as i64 + if days.contains(&day) { 1 } else { 0 };
+    let awarded: &[u32] = if prog.streak.start_day == Some(start) { &prog.streak.milestones } else { &[] };
+    let next_milestone = MILESTONES.iter().copied().find(|(m, _)| *m > streak && !awarded.contains(m));
+    DailyStatus { day, lesson_id, done, streak, best: longest_streak(&days).max(prog.streak.best), next_milestone }
+}
+
+/// Pays streak milestones and the daily bonus after a passing check. Returns
+/// a message per bonus awarded.
+pub fn award(storage: &Storage, lessons_root: &str, lessons: &[Exercise], user_id: i64, now: i64) -> Result<Vec<String>> {
+    let attempts = storage.attempts_for_user(user_id)?;
+    let pool = load_pool(lessons_root)?;
+    let day = day_of(now);
+    let days = passing_days(&attempts);
+    let streak = current_streak(&days, day);
+    let challenge = challenge_for(lessons, &pool, day).map(|e| e.meta.id.clone());
+    let daily_done = challenge.as_ref().is_some_and(|id| attempts.iter().any(|a| a.passed && &a.lesson_id == id && day_of(a.timestamp) == day));
+    persistence::update(|prog| apply_bonuses(prog, day - streak as i64 + 1, streak, daily_done.then_some(day)))
+}
+
+/// Records the streak starting on `start_day` and pays any milestone it has
+/// newly reached, plus the daily bonus for `daily_day` if not yet paid.
+pub fn apply_bonuses(prog: &mut Progress, start_day: i64, streak: u32, daily_day: Option<i64>) -> Vec<String> {
+    let mut msgs = Vec::new();
+    let s = &mut prog.streak;
+    if s.start_day != Some(start_day) {
+        s.start_day = Some(start_day);
+        s.milestones.clear();
+    }
+    s.best = s.best.max(streak);
+    let mut bonus = 0;
+    for (m, pts) in MILESTONES {
+        if streak >= *m && !s.milestones.contains(m) {
+            s.milestones.push(*m);
+            bonus += pts;
+            msgs.push(format!("🔥 {}-day streak! +{} bonus points", m, pts));
+        }
+    }
+    if let Some(day) = daily_day.filter(|d| s.last_daily_day != Some(*d)) {
+        s.last_daily_day = Some(day);
+        bonus += DAILY_BONUS;
+        msgs.push(format!("Daily challenge complete! +{} bonus points", DAILY_BONUS));
+    }
+    prog.total_points = prog.total_points.saturating_add(bonus);
+    msgs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_streaks() {
+        let days: BTreeSet<i64> = [1, 2, 3, 5, 6, 10, 11, 12, 13].into_iter().collect();
+        assert_eq!(current_streak(&days, 13), 4);
+        assert_eq!(current_streak(&days, 14), 4, "alive until the day is over");
+        assert_eq!(current_streak(&days, 15), 0);
+        assert_eq!(current_streak(&days, 7), 2);
+        assert_eq!(longest_streak(&days), 4);
+        assert_eq!(day_of(-1), -1);
+    }
+
+    #[test]
+    fn awards_milestones_once_per_streak() {
+        let mut prog = Progress::default();
+        assert_eq!(apply_bonuses(&mut prog, 100, 3, Some(102)).len(), 2);
+        assert!(apply_bonuses(&mut prog, 100, 3, Some(102)).is_empty());
+        assert_eq!(prog.total_points, 10 + DAILY_BONUS);
+        // A new streak can earn the same milestone again.
+        assert_eq!(apply_bonuses(&mut prog, 200, 3, None).len(), 1);
+        assert_eq!((prog.total_points, prog.streak.best), (20 + DAILY_BONUS, 3));
+    }
+}
@@ -1,31 +1,54 @@
 //! Orchestrates commands and UX.
 
 use crate::{
+    achievements, bundle, classroom, coverage, daily, exam,
     exercise::{self, Exercise},
-    grader,
-    persistence::{self, ExerciseProgress},
+    grader, hints, history, leaderboard, mutants, packs,
+    persistence, quiz, reverify, review,
+    scheduler::{self, Outcome, Priority, Scheduler},
+    scoring, server,
+    storage::Storage,
     util,
 };
 use anyhow::{Context, Result};
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::BTreeMap;
 use std::env;
-use std::{fs, path::PathBuf};
+use std::io::{self, BufRead, Write};
+use std::{fs, path::{Path, PathBuf}};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-pub fn run(lessons_root: String, cmd: Command) -> Result<()> {
+pub fn run(storage: &Storage, lessons_root: String, cmd: Command) -> Result<()> {
     match cmd {
         Command::List => cmd_list(&lessons_root),
         Command::Start { id } => cmd_start(&lessons_root, &id),
         Command::Open { id } => cmd_open(&id),
-        Command::Check { id, timeout } => cmd_check(&lessons_root, &id, timeout),
+        Command::Check { id, timeout, coverage } => cmd_check(storage, &lessons_root, &id, timeout, coverage),
         Command::Hint { id } => cmd_hint(&lessons_root, &id),
         Command::Progress => cmd_progress(),
-        Command::Reset { id } => cmd_reset(&id),
-        Command::CheckAll => cmd_check_all(&lessons_root),
+        Command::Reset { id } => cmd_reset(storage, &id),
+        Command::CheckAll { jobs, fail_fast, timeout } => cmd_check_all(&lessons_root, jobs, fail_fast, timeout),
         Command::Validate => cmd_validate(&lessons_root),
         Command::Solution { id } => cmd_solution(&lessons_root, &id),
-        Command::ClearAll => cmd_clear_all(),
+        Command::ClearAll => cmd_clear_all(storage),
+        Command::Profiles => cmd_profiles(),
+        Command::Export { file } => cmd_export(storage, &file),
+        Command::Import { file } => cmd_import(storage, &file),
+        Command::History { id } => cmd_history(storage, &id),
+        Command::Diff { id, a, b } => cmd_diff(storage, &id, a, b),
+        Command::Restore { id, snapshot } => cmd_restore(storage, &id, snapshot),
+        Command::Quiz { id, answers } => cmd_quiz(&lessons_root, &id, answers),
+        Command::Review { id } => cmd_review(storage, &lessons_root, id),
+        Command::Exam(action) => cmd_exam(&lessons_root, action),
+        Command::Daily => cmd_daily(storage, &lessons_root),
+        Command::Achievements => cmd_achievements(&lessons_root),
+        Command::Rescore { dry_run } => cmd_rescore(&lessons_root, dry_run),
+        Command::Leaderboard { lesson, since } => cmd_leaderboard(storage, lesson, &since),
+        Command::Instructor(opts) => cmd_instructor(&lessons_root, opts),
+        Command::Serve { host, port, workers, queue, timeout } => cmd_serve(lessons_root, &host, port, workers, queue, timeout),
+        Command::Pack(action) => cmd_pack(action),
+        Command::Reverify { id, timeout } => cmd_reverify(storage, &lessons_root, id, timeout),
     }
 }
 
@@ -34,14 +57,66 @@ pub enum Command {
     List,
     Start { id: String },
     Open { id: String },
-    Check { id: String, timeout: Option<u64> },
+    Check { id: String, timeout: Option<u64>, coverage: bool },
     Hint { id: String },
     Progress,
     Reset { id: String },
-    CheckAll,
+    CheckAll { jobs: usize, fail_fast: bool, timeout: Option<u64> },
     Validate,
     Solution { id: String },
     ClearAll,
+    Profiles,
+    Export { file: PathBuf },
+    Import { file: PathBuf },
+    History { id: String },
+    Diff { id: String, a: i64, b: Option<i64> },
+    Restore { id: String, snapshot: i64 },
+    /// `answers` skips the prompts: one entry per question, in order.
+    Quiz { id: String, answers: Option<Vec<String>> },
+    /// Without `id`, works through everything due; with it, re-solves that lesson from the starter.
+    Review { id: Option<String> },
+    Exam(ExamCommand),
+    Daily,
+    Achievements,
+    /// Recomputes lesson points under the current `scoring.toml`.
+    Rescore { dry_run: bool },
+    /// `since` is a window accepted by `leaderboard::parse_since`.
+    Leaderboard { lesson: Option<String>, since: String },
+    Instructor(InstructorOptions),
+    Serve { host: String, port: u16, workers: usize, queue: usize, timeout: Option<u64> },
+    Pack(PackCommand),
+    /// Grades saved solutions against the current tests: of `id`, or of every lesson updated since it was solved.
+    Reverify { id: Option<String>, timeout: Option<u64> },
+}
+
+#[derive(Debug)]
+pub enum PackCommand {
+    /// `force` installs even when the pack needs a newer Rust.
+    Install { source: PathBuf, force: bool },
+    List,
+    Remove { name: String },
+}
+
+#[derive(Debug)]
+pub struct InstructorOptions {
+    pub bundles: Vec<PathBuf>,
+    /// A shared `app.db` with one user per learner.
+    pub db: Option<PathBuf>,
+    pub csv: Option<PathBuf>,
+    pub stuck_attempts: u32,
+    /// Percent of the learners who started a lesson that must be stuck to flag it.
+    pub stuck_share: u32,
+}
+
+#[derive(Debug)]
+pub enum ExamCommand {
+    Start(exam::ExamSpec),
+    Status,
+    /// Grade now; optionally also write the report as JSON and/or HTML.
+    Submit { json: Option<PathBuf>, html: Option<PathBuf> },
+    /// Re-export the most recent report.
+    Report { json: Option<PathBuf>, html: Option<PathBuf> },
+    Abort,
 }
 
 fn load_by_id(lessons_root: &str, id: &str) -> Result<Exercise> {
@@ -57,14 +132,19 @@ fn cmd_list(lessons_root: &str) -> Result<()> {
         println!("No exercises found under '{}'", lessons_root);
         return Ok(());
     }
+    let prog = persistence::load().unwrap_or_default();
+    let mut updated = 0;
     for x in xs {
+        let flag = if reverify::is_updated(&x, prog.exercises.get(&x.meta.id)) { updated += 1; "  (updated since you solved it)".yellow().to_string() } else { String::new() };
         println!(
-            "{}  {}  [{}]",
+            "{}  {}  [{}]{}",
             x.meta.id.bold(),
             x.meta.title,
-            x.meta.difficulty
+            x.meta.difficulty,
+            flag
         );
     }
+    if updated > 0 { println!("{} lesson(s) changed after you solved them; `rust-game reverify` checks your solutions against the new tests", updated); }
     Ok(())
 }
 
@@ -86,25 +166,17 @@ fn cmd_start(lessons_root: &str, id: &str) -> Result<()> {
         fs::copy(&ex.starter_rs, &work_file)?;
         println!("Initialized working copy at {:?}", work_file);
     }
-    let mut prog = persistence::load().unwrap_or_default();
-    let entry = prog.exercises.entry(ex.meta.id.clone()).or_insert(ExerciseProgress::default());
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
-    if entry.first_started_at.is_none() { entry.first_started_at = Some(now); }
-    entry.last_started_at = Some(now);
-    persistence::save(&prog)?;
+    persistence::update(|prog| {
+        let entry = prog.exercises.entry(ex.meta.id.clone()).or_default();
+        if entry.first_started_at.is_none() { entry.first_started_at = Some(now); }
+        entry.last_started_at = Some(now);
+    })?;
     Ok(())
 }
 
 fn cmd_open(id: &str) -> Result<()> {
-    let dummy_meta = crate::exercise::ExerciseMeta {
-        id: id.to_string(),
-        title: "".into(),
-        difficulty: "beginner".into(),
-        hint: None,
-        timeout_secs: None,
-    };
-    let ex = Exercise { meta: dummy_meta, root: PathBuf::new(), starter_rs: PathBuf::new(), tests_rs: PathBuf::new(), solution_rs: None, explanation_md: None };
-    let file = ex.working_file()?;
+    let file = Exercise::working_file_for(id)?;
     if !file.exists() {
         anyhow::bail!("Working file not found: {:?}. Run `start` first to create it, then edit this file.", file);
     }
@@ -113,8 +185,11 @@ fn cmd_open(id: &str) -> Result<()> {
     Ok(())
 }
 
-fn cmd_check(lessons_root: &str, id: &str, timeout: Option<u64>) -> Result<()> {
+fn cmd_check(storage: &Storage, lessons_root: &str, id: &str, timeout: Option<u64>, coverage: bool) -> Result<()> {
     let ex = load_by_id(lessons_root, id)?;
+    if exam::active()?.is_some_and(|s| s.lessons.iter().any(|l| l == id)) {
+        anyhow::bail!("'{}' is part of the running exam and is graded on `rust-game exam submit`", id);
+    }
     let file = ex.working_file()?;
     if !file.exists() {
         anyhow::bail!("Working file not found: {:?}. Run `start` first.", file);
@@ -128,68 +203,101 @@ fn cmd_check(lessons_root: &str, id: &str, timeout: Option<u64>) -> Result<()> {
 
     let outcome = grader::grade(&ex, &file, timeout)?;
     bar.finish_and_clear();
+    let content_hash = ex.content_hash().ok();
 
     if outcome.passed {
         println!("{}", "All tests passed 🎉".green().bold());
-        let mut prog = persistence::load().unwrap_or_default();
+        for note in &outcome.notes { println!("{}", note.dimmed()); }
         let lesson_id = ex.meta.id.clone();
-        let (now, points, should_award, last_duration_secs) = {
-            let entry = prog.exercises.entry(lesson_id.clone()).or_insert(ExerciseProgress::default());
-            entry.completed = true;
-            entry.attempts += 1;
-            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
-            entry.completed_at = Some(now);
-            if let Some(start) = entry.last_started_at {
-                let dur = (now - start).max(0) as u64;
-                entry.last_duration_secs = Some(dur);
-                entry.best_duration_secs = Some(entry.best_duration_secs.map(|b| b.min(dur)).unwrap_or(dur));
-            }
-            let points = match ex.meta.difficulty.as_str() { "beginner" => 10, "intermediate" => 25, _ => 50 };
-            let should_award = entry.points_earned == 0;
-            let last_duration_secs = entry.last_duration_secs;
-            (now, points, should_award, last_duration_secs)
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let scoring_cfg = scoring::load(lessons_root)?;
+        let lint = if scoring_cfg.lint_bonus_percent > 0 && scoring::needs_award(&persistence::load()?, &lesson_id) {
+            grader::lint(&ex, timeout.max(60))?
+        } else {
+            None
         };
-        if should_award {
-            prog.total_points = prog.total_points.saturating_add(points as u32);
-            if points >= 50 && !prog.badges.contains(&"First Advanced".to_string()) {
-                prog.badges.push("First Advanced".to_string());
-            }
-            if prog.exercises.values().filter(|e| e.completed).count() as u32 >= 5 && !prog.badges.contains(&"Getting Serious".to_string()) {
-                prog.badges.push("Getting Serious".to_string());
+        let (uid, last_duration_secs, breakdown) = persistence::update(|prog| {
+            let last_duration_secs = {
+                let entry = prog.exercises.entry(lesson_id.clone()).or_default();
+                entry.completed = true;
+                entry.attempts += 1;
+                entry.completed_at.get_or_insert(now);
+                entry.content_hash = content_hash.clone();
+                if let Some(start) = entry.last_started_at {
+                    let dur = (now - start).max(0) as u64;
+                    entry.last_duration_secs = Some(dur);
+                    entry.best_duration_secs = Some(entry.best_duration_secs.map(|b| b.min(dur)).unwrap_or(dur));
+                }
+                entry.last_duration_secs
+            };
+            let breakdown = scoring::award(prog, &ex.meta, &scoring_cfg, lint);
+            if let Some(b) = breakdown.as_ref().filter(|b| b.total > 0) {
+                let name = prog.display_name.clone().unwrap_or_else(|| "Player".to_string());
+                let avatar = prog.avatar.clone();
+                let lb = persistence::LeaderboardEntry { name, avatar, lesson_id: lesson_id.clone(), points: b.total, duration_secs: last_duration_secs, timestamp: now };
+                let _ = persistence::add_leaderboard_entry(lb);
             }
-            if prog.total_points >= 100 && !prog.badges.contains(&"Century".to_string()) {
-                prog.badges.push("Century".to_string());
+            (prog.current_user_id, last_duration_secs, breakdown)
+        })?;
+        let attempt_id = uid.and_then(|uid| storage.record_attempt(uid, &lesson_id, true, last_duration_secs, now, content_hash.as_deref()).ok());
+        if let Some(b) = breakdown {
+            println!("+{} points", b.total);
+            println!("{}", b.to_string().dimmed());
+            if let Some(id) = attempt_id { storage.set_attempt_points(id, b.total)?; }
+        }
+        let _ = history::record(storage, uid, &lesson_id, attempt_id, Some(true), now, &file);
+        if let (Some(uid), Some(_)) = (uid, attempt_id) {
+            for msg in daily::award(storage, lessons_root, &exercise::load_all(lessons_root)?, uid, now)? {
+                println!("{}", msg.yellow().bold());
             }
-            let name = prog.display_name.clone().unwrap_or_else(|| "Player".to_string());
-            let avatar = prog.avatar.clone();
-            let lb = persistence::LeaderboardEntry { name, avatar, lesson_id: lesson_id.clone(), points: points as u32, duration_secs: last_duration_secs, timestamp: now };
-            let _ = persistence::add_leaderboard_entry(lb);
-            if let Some(ent) = prog.exercises.get_mut(&lesson_id) { ent.points_earned = points; }
         }
-        persistence::save(&prog)?;
-        if let Some(uid) = prog.current_user_id {
-            let _ = crate::storage::record_attempt(uid, &lesson_id, true, last_duration_secs, now);
+        if let Some(card) = review::on_check(&lesson_id, true, now)? {
+            println!("Review recorded. Next review in {} day(s).", card.interval_days);
         }
+        if coverage || ex.meta.coverage { print_coverage(&ex, &file, timeout.max(120)); }
+        print_unlocked(lessons_root, now)?;
     } else {
-        println!("{}", "Some tests failed".red().bold());
+        let headline = if !outcome.ub.is_empty() {
+            "Tests pass, but Miri found undefined behavior".to_string()
+        } else if let Some(hang) = &outcome.hang {
+            hang.to_string()
+        } else if !outcome.survivors.is_empty() {
+            format!("{} mutant(s) survived your tests", outcome.survivors.len())
+        } else {
+            "Some tests failed".to_string()
+        };
+        println!("{}", headline.red().bold());
+        for ub in &outcome.ub { println!("  - {}", ub); }
         if !outcome.stdout.is_empty() { println!("stdout:\n{}", outcome.stdout); }
         if !outcome.stderr.is_empty() {
             println!("stderr:\n{}", outcome.stderr);
-            let codes = extract_error_codes(&outcome.stderr);
+            let codes = hints::error_codes(&outcome.stderr);
             if !codes.is_empty() {
                 println!("Help:");
                 for c in codes { println!("  - https://doc.rust-lang.org/error_codes/{}.html", c); }
             }
         }
-        let mut prog = persistence::load().unwrap_or_default();
-        let entry = prog.exercises.entry(ex.meta.id.clone()).or_insert(ExerciseProgress::default());
-        entry.attempts += 1;
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
-        let lesson_id = ex.meta.id.clone();
-        persistence::save(&prog)?;
-        if let Some(uid) = prog.current_user_id {
-            let _ = crate::storage::record_attempt(uid, &lesson_id, false, None, now);
+        let targeted = hints::contextual(&ex.meta, &outcome.stdout, &outcome.stderr);
+        if !targeted.is_empty() {
+            println!("{}", "Hints for this failure:".yellow().bold());
+            for h in targeted { println!("  - {}", h); }
         }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let uid = persistence::update(|prog| {
+            prog.exercises.entry(ex.meta.id.clone()).or_default().attempts += 1;
+            prog.current_user_id
+        })?;
+        let attempt_id = uid.and_then(|uid| storage.record_attempt(uid, &ex.meta.id, false, None, now, content_hash.as_deref()).ok());
+        let _ = history::record(storage, uid, &ex.meta.id, attempt_id, Some(false), now, &file);
+        review::on_check(&ex.meta.id, false, now)?;
+        print_unlocked(lessons_root, now)?;
+    }
+    Ok(())
+}
+
+fn print_unlocked(lessons_root: &str, now: i64) -> Result<()> {
+    for a in achievements::evaluate(lessons_root, now)? {
+        println!("{} {} {} — {}", "Achievement unlocked:".yellow().bold(), a.icon.as_deref().unwrap_or("🏅"), a.name.bold(), a.description);
     }
     Ok(())
 }
@@ -197,83 +305,26 @@ fn cmd_check(lessons_root: &str, id: &str, timeout: Option<u64>) -> Result<()> {
 fn start_unlocked(lessons_root: &str, id: &str) -> Result<bool> {
     if env::var("RUST_GAME_FORCE").ok().as_deref() == Some("1") { return Ok(true); }
     let all = exercise::load_all(lessons_root)?;
-    let ex = match all.iter().find(|e| e.meta.id == id) { Some(e) => e, None => return Ok(false) };
-    let diff = ex.meta.difficulty.clone();
-    fn slug(id: &str) -> &str { id.rsplit('/').next().unwrap_or(id) }
-    fn rank(diff: &str, slug: &str) -> u32 {
-        let beginner: &[&str] = &[
-            "variables",
-            "control_flow_loops",
-            "arrays_slices_basics",
-            "functions",
-            "ownership",
-            "strings_utf8",
-            "pattern_matching_ergonomics",
-            "match_enums",
-            "hashmap",
-            "iterators",
-            "result_error",
-            "traits",
-        ];
-        let intermediate: &[&str] = &[
-            "lifetimes_longest",
-            "generics_asref_maxlen",
-            "result_map_errors",
-            "iterators_combinators",
-            "iterators_ownership",
-            "rc_refcell",
-            "thiserror",
-        ];
-        let advanced: &[&str] = &[
-            "arc_mutex_counter",
-            "arc_rwlock_counter",
-            "channels_mpsc",
-            "tokio_mpsc",
-            "tokio_timeout_race",
-            "traits_dyn_dispatch",
-        ];
-        let list = match diff { "beginner" => beginner, "intermediate" => intermediate, _ => advanced };
-        list.iter().position(|s| s == &slug).map(|i| i as u32).unwrap_or(999)
-    }
-    let mut same: Vec<&exercise::Exercise> = all.iter().filter(|e| e.meta.difficulty == diff).collect();
-    same.sort_by(|a,b| {
-        let ra = rank(&a.meta.difficulty, slug(&a.meta.id));
-        let rb = rank(&b.meta.difficulty, slug(&b.meta.id));
-        if ra != rb { return ra.cmp(&rb); }
-        a.meta.id.cmp(&b.meta.id)
-    });
-    let pos = same.iter().position(|e| e.meta.id == id).unwrap_or(0);
-    if pos == 0 { return Ok(true); }
-    let prev_id = &same[pos - 1].meta.id;
+    // Today's challenge is open to everyone.
+    let today = daily::day_of(now_secs());
+    if daily::challenge_for(&all, &daily::load_pool(lessons_root)?, today).is_some_and(|e| e.meta.id == id) { return Ok(true); }
     let prog = persistence::load().unwrap_or_default();
-    let completed = prog.exercises.get(prev_id).map(|e| e.completed).unwrap_or(false);
-    Ok(completed)
+    Ok(exercise::unlocked(&all, id, |prev| prog.exercises.get(prev).is_some_and(|e| e.completed)))
 }
 
 fn cmd_hint(lessons_root: &str, id: &str) -> Result<()> {
     let ex = load_by_id(lessons_root, id)?;
-    match ex.meta.hint.as_deref() { Some(h) => println!("Hint: {}", h), None => println!("No hint available."), }
-    Ok(())
-}
-
-fn extract_error_codes(stderr: &str) -> Vec<String> {
-    let mut out = Vec::new();
-    let bytes = stderr.as_bytes();
-    let mut i = 0;
-    while i + 5 <= bytes.len() {
-        if bytes[i] == b'E' && bytes[i + 1].is_ascii_digit() && bytes[i + 2].is_ascii_digit()
-            && bytes[i + 3].is_ascii_digit() && bytes[i + 4].is_ascii_digit()
-        {
-            let code = &stderr[i..i + 5];
-            if !out.contains(&code.to_string()) {
-                out.push(code.to_string());
-            }
-            i += 5;
-            continue;
-        }
-        i += 1;
+    let total = ex.meta.hints.len();
+    if total == 0 { println!("No hint available."); return Ok(()); }
+    let before = hints::revealed_count(&ex.meta, &persistence::load().unwrap_or_default());
+    let shown = hints::reveal_next(&ex.meta)?;
+    for (i, h) in ex.meta.hints[..shown].iter().enumerate() {
+        println!("Hint {}/{}: {}", i + 1, total, h);
     }
-    out
+    let cost = scoring::load(lessons_root)?.hint_cost(&ex.meta);
+    if shown > before { println!("{}", format!("(-{} points on your first solve)", cost).yellow()); }
+    if shown < total { println!("Run `hint {}` again for the next hint.", id); }
+    Ok(())
 }
 
 fn cmd_progress() -> Result<()> {
@@ -286,35 +337,99 @@ fn cmd_progress() -> Result<()> {
     Ok(())
 }
 
-fn cmd_reset(id: &str) -> Result<()> {
-    let dummy_meta = crate::exercise::ExerciseMeta { id: id.to_string(), title: "".into(), difficulty: "beginner".into(), hint: None, timeout_secs: None };
-    let ex = Exercise { meta: dummy_meta, root: PathBuf::new(), starter_rs: PathBuf::new(), tests_rs: PathBuf::new(), solution_rs: None, explanation_md: None };
-    let dir = ex.working_file()?.parent().unwrap().to_path_buf();
+fn cmd_reset(storage: &Storage, id: &str) -> Result<()> {
+    let file = Exercise::working_file_for(id)?;
+    let uid = persistence::load().ok().and_then(|p| p.current_user_id);
+    if let Some(snap) = history::record(storage, uid, id, None, None, now_secs(), &file)? {
+        println!("Saved current working copy as snapshot #{snap}");
+    }
+    let dir = file.parent().unwrap().to_path_buf();
     if dir.exists() { fs::remove_dir_all(&dir)?; println!("Reset working dir {:?}", dir); } else { println!("No working dir for {}", id); }
-    let sandbox = crate::util::data_dir()?.join("sandboxes").join(id.replace('/', "_"));
+    let sandbox = crate::util::data_dir()?.join("sandboxes").join(exercise::dir_name(id));
     if sandbox.exists() { fs::remove_dir_all(&sandbox)?; println!("Reset sandbox dir {:?}", sandbox); }
     Ok(())
 }
 
-fn cmd_check_all(lessons_root: &str) -> Result<()> {
+/// Line coverage of the passing solution, with the lines the tests never ran.
+fn print_coverage(ex: &Exercise, file: &Path, timeout: u64) {
+    let bar = ProgressBar::new_spinner();
+    bar.set_style(ProgressStyle::with_template("{spinner} Measuring coverage... {msg}").unwrap());
+    bar.enable_steady_tick(std::time::Duration::from_millis(100));
+    let result = coverage::measure(ex, timeout);
+    bar.finish_and_clear();
+    let cov = match result {
+        Ok(Some(cov)) => cov,
+        Ok(None) => { println!("{}", "Coverage skipped: llvm-cov not found (rustup component add llvm-tools-preview)".dimmed()); return; }
+        Err(e) => { println!("{}", format!("Coverage unavailable: {:#}", e).dimmed()); return; }
+    };
+    let summary = format!("Coverage: {}% of lib.rs ({}/{} lines)", cov.percent(), cov.covered, cov.total);
+    if cov.uncovered.is_empty() { println!("{}", summary.green()); return; }
+    println!("{}", summary.yellow());
+    println!("Lines your tests never ran:");
+    let src = fs::read_to_string(file).unwrap_or_default();
+    let lines: Vec<&str> = src.lines().collect();
+    for n in &cov.uncovered {
+        let text = lines.get(*n as usize - 1).copied().unwrap_or("");
+        println!("{} {}", format!("{:>4} |", n).dimmed(), text.red());
+    }
+}
+
+fn cmd_check_all(lessons_root: &str, jobs: usize, fail_fast: bool, timeout: Option<u64>) -> Result<()> {
     let all = exercise::load_all(lessons_root)?;
     if all.is_empty() { println!("No lessons found"); return Ok(()); }
-    let mut total = 0usize;
-    let mut passed = 0usize;
-    for ex in all {
-        total += 1;
-        let file = ex.working_file()?;
-        let source = if file.exists() { file.clone() } else { ex.starter_rs.clone() };
-        let timeout = ex.meta.timeout_secs.unwrap_or(15);
-        let out = grader::grade(&ex, &source, timeout)?;
-        let mark = if out.passed { passed += 1; "✓" } else { "✗" };
-        println!("{} {} - {}", mark, ex.meta.id, if out.passed { "pass" } else { "fail" });
-        if !out.passed {
-            if !out.stdout.is_empty() { println!("stdout:\n{}", out.stdout); }
-            if !out.stderr.is_empty() { println!("stderr:\n{}", out.stderr); }
+    let sched = Scheduler::new(jobs.max(1));
+    let canceller = sched.canceller();
+    ctrlc::set_handler({
+        let canceller = canceller.clone();
+        let interrupted = std::sync::atomic::AtomicBool::new(false);
+        // The first Ctrl-C cancels and still prints the summary; a second one exits.
+        move || {
+            if interrupted.swap(true, std::sync::atomic::Ordering::Relaxed) { std::process::exit(130); }
+            canceller.cancel_all();
         }
+    }).context("install Ctrl-C handler")?;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    for ex in &all {
+        let file = ex.working_file()?;
+        let source = if file.exists() { file } else { ex.starter_rs.clone() };
+        let timeout_secs = timeout.or(ex.meta.timeout_secs).unwrap_or(15);
+        let job = scheduler::Job { ex: ex.clone(), source, sandbox: grader::sandbox_dir(ex)?, timeout_secs, priority: Priority::Batch };
+        sched.submit(job, tx.clone())?;
+    }
+    drop(tx);
+
+    let bar = ProgressBar::new(all.len() as u64);
+    bar.set_style(ProgressStyle::with_template("{spinner} [{bar:30}] {pos}/{len} {msg}").unwrap().progress_chars("=> "));
+    bar.enable_steady_tick(std::time::Duration::from_millis(100));
+    bar.set_message(format!("grading with {} worker(s)", jobs.max(1)));
+    let (mut passed, mut failed, mut cancelled) = (0usize, 0usize, 0usize);
+    let mut results: BTreeMap<String, Outcome> = BTreeMap::new();
+    for done in rx {
+        let line = match &done.outcome {
+            Outcome::Graded(o) if o.passed => { passed += 1; format!("{} {} - pass", "✓".green(), done.lesson_id) }
+            Outcome::Graded(_) => { failed += 1; format!("{} {} - fail", "✗".red(), done.lesson_id) }
+            Outcome::Failed(e) => { failed += 1; format!("{} {} - error: {:#}", "✗".red(), done.lesson_id, e) }
+            Outcome::Cancelled => { cancelled += 1; format!("- {} - cancelled", done.lesson_id) }
+        };
+        bar.println(line);
+        bar.inc(1);
+        bar.set_message(format!("{passed} passed, {failed} failed"));
+        let is_failure = !matches!(&done.outcome, Outcome::Graded(o) if o.passed) && !matches!(done.outcome, Outcome::Cancelled);
+        if fail_fast && is_failure { canceller.cancel_all(); }
+        results.insert(done.lesson_id, done.outcome);
+    }
+    bar.finish_and_clear();
+
+    for ex in &all {
+        let Some(Outcome::Graded(out)) = results.get(&ex.meta.id) else { continue };
+        if out.passed { continue; }
+        println!("{} {}", "✗".red(), ex.meta.id.bold());
+        if !out.stdout.is_empty() { println!("stdout:\n{}", out.stdout); }
+        if !out.stderr.is_empty() { println!("stderr:\n{}", out.stderr); }
     }
-    println!("Summary: {passed}/{total} passed");
+    let skipped = if cancelled > 0 { format!(", {cancelled} cancelled") } else { String::new() };
+    println!("Summary: {passed}/{} passed{skipped}", all.len());
     Ok(())
 }
 
@@ -323,20 +438,46 @@ fn cmd_validate(lessons_root: &str) -> Result<()> {
     let mut ok = true;
     for ex in &all {
         if !ex.starter_rs.exists() { ok = false; println!("Missing starter.rs for {}", ex.meta.id); }
-        if !ex.tests_rs.exists() { ok = false; println!("Missing tests.rs for {}", ex.meta.id); }
+        match ex.meta.kind {
+            exercise::ExerciseKind::Implement => {
+                if !ex.tests_rs.exists() { ok = false; println!("Missing tests.rs for {}", ex.meta.id); }
+            }
+            exercise::ExerciseKind::Tests => {
+                if !mutants::reference(ex).exists() { ok = false; println!("Missing reference.rs for {}", ex.meta.id); }
+                if mutants::load(ex)?.is_empty() { ok = false; println!("No mutants/*.rs for {}", ex.meta.id); }
+            }
+        }
         if ex.meta.title.trim().is_empty() { ok = false; println!("Empty title for {}", ex.meta.id); }
         if !matches!(ex.meta.difficulty.as_str(), "beginner"|"intermediate"|"advanced") {
             ok = false; println!("Invalid difficulty for {}: {}", ex.meta.id, ex.meta.difficulty);
         }
     }
-    if ok { println!("All lesson metadata OK ({} lessons)", all.len()); }
+    let mut quizzes = 0;
+    for ex in &all {
+        let path = ex.root.join("quiz.toml");
+        if !path.exists() { continue; }
+        quizzes += 1;
+        let src = fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+        for issue in quiz::check_source(&src) {
+            ok = false;
+            println!("{}:{}:{}: {}", path.display(), issue.line, issue.column, issue.message);
+        }
+    }
+    let defs = match achievements::load(lessons_root) {
+        Ok(defs) => defs.len(),
+        Err(e) => { ok = false; println!("{:#}", e); 0 }
+    };
+    if !ok { anyhow::bail!("lesson metadata has errors"); }
+    println!("All lesson metadata OK ({} lessons, {} quizzes, {} achievements)", all.len(), quizzes, defs);
     Ok(())
 }
 
 fn cmd_solution(lessons_root: &str, id: &str) -> Result<()> {
+    exam::ensure_unlocked("Solutions")?;
     let ex = load_by_id(lessons_root, id)?;
     let sol = ex.root.join("solution.rs");
     if sol.exists() {
+        persistence::update(|prog| prog.exercises.entry(ex.meta.id.clone()).or_default().solution_viewed = true)?;
         println!("Solution: {}", sol.display());
         if let Ok(data) = std::fs::read_to_string(&sol) {
             let preview: String = data.lines().take(40).collect::<Vec<_>>().join("\n");
@@ -348,7 +489,7 @@ fn cmd_solution(lessons_root: &str, id: &str) -> Result<()> {
     Ok(())
 }
 
-fn cmd_clear_all() -> Result<()> {
+fn cmd_clear_all(storage: &Storage) -> Result<()> {
     let uid = persistence::load().ok().and_then(|p| p.current_user_id);
     if let Ok(p) = persistence::progress_path() { let _ = std::fs::remove_file(p); }
     if let Ok(lp) = persistence::leaderboard_path() { let _ = std::fs::remove_file(lp); }
@@ -356,7 +497,465 @@ fn cmd_clear_all() -> Result<()> {
     if work.exists() { let _ = std::fs::remove_dir_all(&work); }
     let sand = crate::util::data_dir()?.join("sandboxes");
     if sand.exists() { let _ = std::fs::remove_dir_all(&sand); }
-    if let Some(id) = uid { let _ = crate::storage::clear_attempts_for_user(id); }
+    if let Some(id) = uid { let _ = storage.clear_attempts_for_user(id); }
     println!("Reset complete. Fresh start ready.");
     Ok(())
-}
\ No newline at end of file
+}
+
+fn cmd_profiles() -> Result<()> {
+    let active = util::profile();
+    let mark = |on: bool| if on { "*" } else { " " };
+    println!("Data dir: {}", util::base_data_dir()?.display());
+    println!("{} default", mark(active.is_none()));
+    for name in util::list_profiles()? {
+        println!("{} {}", mark(active.as_deref() == Some(name.as_str())), name);
+    }
+    Ok(())
+}
+
+fn cmd_reverify(storage: &Storage, lessons_root: &str, id: Option<String>, timeout: Option<u64>) -> Result<()> {
+    let all = exercise::load_all(lessons_root)?;
+    let lessons: Vec<&Exercise> = match &id {
+        Some(id) => vec![all.iter().find(|e| &e.meta.id == id).with_context(|| format!("Exercise '{}' not found", id))?],
+        None => reverify::updated(&all, &persistence::load()?),
+    };
+    if lessons.is_empty() { println!("No lessons changed since you solved them"); return Ok(()); }
+    for ex in lessons {
+        let timeout = timeout.or(ex.meta.timeout_secs).unwrap_or(15);
+        match reverify::reverify(storage, ex, timeout)? {
+            reverify::Reverified::Passed => println!("{} {} still passes", "✓".green(), ex.meta.id),
+            reverify::Reverified::NoSolution => println!("{} {}: no saved solution to check", "-".dimmed(), ex.meta.id),
+            reverify::Reverified::Failed(o) => {
+                let why = o.hang.as_ref().map(|h| h.to_string()).unwrap_or_else(|| "the new tests fail".to_string());
+                println!("{} {}: {}. Fix it and run `rust-game check {}`", "✗".red(), ex.meta.id, why, ex.meta.id);
+                if !o.stdout.is_empty() { println!("{}", o.stdout.trim_end().dimmed()); }
+                if !o.stderr.is_empty() { println!("{}", o.stderr.trim_end().dimmed()); }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_pack(action: PackCommand) -> Result<()> {
+    match action {
+        PackCommand::Install { source, force } => {
+            let i = packs::install(&source, force)?;
+            let m = &i.manifest;
+            match i.replaced {
+                Some(old) => println!("Upgraded {} {} -> {} ({} lessons)", m.name, old, m.version, m.lessons.len()),
+                None => println!("Installed {} {} ({} lessons); lesson ids start with `{}:`", m.name, m.version, m.lessons.len(), m.name),
+            }
+        }
+        PackCommand::List => {
+            let installed = packs::installed()?;
+            if installed.is_empty() { println!("No packs installed; add one with `rust-game pack install <dir or archive>`"); return Ok(()); }
+            let progress = persistence::load()?;
+            let rustc = packs::rustc_version().ok();
+            for p in installed {
+                let m = &p.manifest;
+                let done = progress.exercises.iter().filter(|(id, e)| e.completed && id.strip_prefix(&m.name).is_some_and(|r| r.starts_with(':'))).count();
+                let author = m.author.as_deref().map(|a| format!(" by {}", a)).unwrap_or_default();
+                println!("{} {}{}  {}/{} done", m.name.bold(), m.version, author, done, m.lessons.len());
+                if let (Some(need), Some(have)) = (&m.rust_version, &rustc) {
+                    if !packs::version_at_least(have, need) { println!("  {}", format!("needs Rust {}, rustc is {}", need, have).yellow()); }
+                }
+            }
+        }
+        PackCommand::Remove { name } => {
+            let version = packs::remove(&name)?.map(|m| format!(" {}", m.version)).unwrap_or_default();
+            println!("Removed {}{}. Progress on its lessons is kept for a reinstall", name, version);
+        }
+    }
+    Ok(())
+}
+
+fn cmd_export(storage: &Storage, file: &Path) -> Result<()> {
+    let b = bundle::export(storage, file)?;
+    println!("Exported {} lessons, {} files and {} attempts to {}", b.progress.exercises.len(), b.work.len(), b.attempts.len(), file.display());
+    Ok(())
+}
+
+fn cmd_import(storage: &Storage, file: &Path) -> Result<()> {
+    let s = bundle::import(storage, file)?;
+    println!("Merged progress for {} lessons, restored {} files, added {} attempts", s.lessons_merged, s.files_restored, s.attempts_added);
+    for f in &s.files_conflicting { println!("  kept local work/{f}; imported copy saved as work/{f}.imported"); }
+    if s.attempts_skipped { println!("Attempt history skipped: log in first to import it into your account."); }
+    Ok(())
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
+}
+
+fn cmd_history(storage: &Storage, id: &str) -> Result<()> {
+    let uid = persistence::load().ok().and_then(|p| p.current_user_id);
+    let snaps = history::list(storage, uid, id)?;
+    if snaps.is_empty() {
+        println!("No snapshots for {} yet. Each `check` saves one.", id);
+        return Ok(());
+    }
+    for s in snaps {
+        let status = match s.passed { Some(true) => "pass".green(), Some(false) => "fail".red(), None => "saved".normal() };
+        println!("#{:<5} {}  {:<5}  {} lines", s.id, s.timestamp, status, s.source.lines().count());
+    }
+    Ok(())
+}
+
+fn cmd_diff(storage: &Storage, id: &str, a: i64, b: Option<i64>) -> Result<()> {
+    let uid = persistence::load().ok().and_then(|p| p.current_user_id);
+    let out = history::diff(storage, uid, id, a, b, &Exercise::working_file_for(id)?)?;
+    for line in out.lines() {
+        if line.starts_with("+ ") { println!("{}", line.green()); }
+        else if line.starts_with("- ") { println!("{}", line.red()); }
+        else { println!("{}", line); }
+    }
+    Ok(())
+}
+
+fn cmd_restore(storage: &Storage, id: &str, snapshot: i64) -> Result<()> {
+    let uid = persistence::load().ok().and_then(|p| p.current_user_id);
+    let file = Exercise::working_file_for(id)?;
+    history::restore(storage, uid, id, snapshot, &file, now_secs())?;
+    println!("Restored snapshot #{} to {:?} (previous copy saved; see `history {}`)", snapshot, file, id);
+    Ok(())
+}
+
+fn cmd_quiz(lessons_root: &str, id: &str, scripted: Option<Vec<String>>) -> Result<()> {
+    let ex = load_by_id(lessons_root, id)?;
+    let qz = quiz::load_quiz(&ex.root)?.with_context(|| format!("'{}' has no quiz", id))?;
+    let total = qz.questions.len();
+    if let Some(xs) = &scripted {
+        if xs.len() != total { anyhow::bail!("Expected {} answers, got {}", total, xs.len()); }
+    }
+    println!("{}", qz.title.bold());
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut answers = BTreeMap::new();
+    for (i, q) in qz.questions.iter().enumerate() {
+        print_question(&format!("Q{}/{}", i + 1, total), q);
+        let answer = match &scripted {
+            Some(xs) => q.parse_answer(&xs[i]).with_context(|| format!("answer {}", i + 1))?,
+            None => ask(&mut input, q)?,
+        };
+        print_feedback(q, q.is_correct(&answer));
+        answers.insert(i, answer);
+    }
+    let r = quiz::submit(id, &qz, answers)?;
+    println!("\nScore: {}/{}", r.correct, r.total);
+    if r.perfect() { println!("{}", "Quiz completed!".green().bold()); }
+    print_unlocked(lessons_root, now_secs())
+}
+
+fn print_question(label: &str, q: &quiz::Question) {
+    println!("\n{}: {}", label, q.prompt);
+    let lines = q.code_lines();
+    match q.kind {
+        quiz::QuestionKind::CompileError => {
+            for (n, l) in lines.iter().enumerate() { println!("  {:>3} | {}", n + 1, l); }
+        }
+        _ => for l in &lines { println!("    {}", l.dimmed()); },
+    }
+    for (n, opt) in q.options.iter().enumerate() { println!("  {}) {}", n + 1, opt); }
+}
+
+fn print_feedback(q: &quiz::Question, correct: bool) {
+    if correct {
+        println!("{}", "✅ Correct".green());
+    } else {
+        println!("{} Expected: {}", "❌ Incorrect.".red(), q.expected());
+    }
+    if let Some(e) = &q.explanation { println!("   {}", e); }
+}
+
+fn cmd_review(storage: &Storage, lessons_root: &str, id: Option<String>) -> Result<()> {
+    let now = now_secs();
+    if let Some(id) = id {
+        let ex = load_by_id(lessons_root, &id)?;
+        let prog = persistence::load()?;
+        if !prog.exercises.get(&id).is_some_and(|e| e.completed) { anyhow::bail!("'{}' has not been completed yet", id); }
+        let file = ex.working_file()?;
+        history::record(storage, prog.current_user_id, &id, None, None, now, &file)?;
+        review::start_lesson(&ex, now)?;
+        println!("Re-solve {} from the starter: {}", id.bold(), file.display());
+        println!("Your previous solution is saved in `rust-game history {}`. Run `rust-game check {}` when done.", id, id);
+        return Ok(());
+    }
+    let (lessons, items) = review::due_now(lessons_root, now)?;
+    if items.is_empty() {
+        println!("Nothing is due for review.");
+        return Ok(());
+    }
+    println!("{} item(s) due for review", items.len());
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut lessons_due = Vec::new();
+    for d in items {
+        match d.item {
+            review::ReviewItem::Lesson { id } => lessons_due.push(id),
+            review::ReviewItem::Question { lesson_id, index } => {
+                let Some(ex) = lessons.iter().find(|e| e.meta.id == lesson_id) else { continue };
+                let Some(qz) = quiz::load_quiz(&ex.root)? else { continue };
+                let Some(q) = qz.questions.get(index) else { continue };
+                print_question(&format!("[{}] Q{}", lesson_id, index + 1), q);
+                let correct = q.is_correct(&ask(&mut input, q)?);
+                print_feedback(q, correct);
+                let card = review::answer_question(&lesson_id, q, index, correct, now)?;
+                println!("   Next review in {} day(s).", card.interval_days);
+            }
+        }
+    }
+    if !lessons_due.is_empty() {
+        println!("\n{}", "Lessons due for a re-solve:".bold());
+        for id in lessons_due { println!("  rust-game review {}", id); }
+    }
+    Ok(())
+}
+
+/// Prompts until the answer parses. `output` questions read lines until an empty one.
+fn ask(input: &mut impl BufRead, q: &quiz::Question) -> Result<quiz::Answer> {
+    let prompt = match q.kind {
+        quiz::QuestionKind::Single => format!("Answer (1-{}): ", q.options.len()),
+        quiz::QuestionKind::Multi => "Answers, comma-separated (e.g. 1,3): ".to_string(),
+        quiz::QuestionKind::TrueFalse => "true/false: ".to_string(),
+        quiz::QuestionKind::FillBlank => "Answer: ".to_string(),
+        quiz::QuestionKind::Output => "Output (end with an empty line):\n".to_string(),
+        quiz::QuestionKind::CompileError => "Line number: ".to_string(),
+    };
+    loop {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                if text.is_empty() { anyhow::bail!("No answer given"); }
+                break;
+            }
+            if q.kind != quiz::QuestionKind::Output { text = line; break; }
+            if line.trim().is_empty() { break; }
+            text.push_str(&line);
+        }
+        match q.parse_answer(&text) {
+            Ok(a) => return Ok(a),
+            Err(e) => println!("{}", format!("{e:#}").yellow()),
+        }
+    }
+}
+
+fn cmd_exam(lessons_root: &str, action: ExamCommand) -> Result<()> {
+    let now = now_secs();
+    match action {
+        ExamCommand::Start(spec) => {
+            let all = exercise::load_all(lessons_root)?;
+            let session = exam::start(&all, &spec, now)?;
+            println!("{}", format!("Exam started: {} lesson(s), {} minute(s)", session.lessons.len(), spec.minutes).bold());
+            print_exam_files(&session)?;
+            println!("Hints and solutions are locked. Run `rust-game exam submit` when done; `exam status` shows the time left.");
+        }
+        ExamCommand::Status => {
+            let Some(session) = exam::active()? else {
+                println!("No exam is running.");
+                return Ok(());
+            };
+            let left = session.remaining_secs(now);
+            if left == 0 {
+                println!("{}", "Time is up. Grading...".yellow().bold());
+                return exam_submit(lessons_root, now, None, None);
+            }
+            println!("Time left: {}m{:02}s", left / 60, left % 60);
+            print_exam_files(&session)?;
+        }
+        ExamCommand::Submit { json, html } => exam_submit(lessons_root, now, json, html)?,
+        ExamCommand::Report { json, html } => {
+            let report = exam::last_report()?.context("No exam report yet")?;
+            print_exam_report(&report);
+            export_exam_report(&report, json, html)?;
+        }
+        ExamCommand::Abort => match exam::abort()? {
+            Some(_) => println!("Exam aborted. Nothing was graded."),
+            None => println!("No exam is running."),
+        },
+    }
+    Ok(())
+}
+
+fn print_exam_files(session: &exam::ExamSession) -> Result<()> {
+    for id in &session.lessons {
+        println!("  {:<40} {}", id, exam::working_file(id)?.display());
+    }
+    Ok(())
+}
+
+fn exam_submit(lessons_root: &str, now: i64, json: Option<PathBuf>, html: Option<PathBuf>) -> Result<()> {
+    let all = exercise::load_all(lessons_root)?;
+    let bar = ProgressBar::new_spinner();
+    bar.set_style(ProgressStyle::with_template("{spinner} Grading {msg}").unwrap());
+    bar.enable_steady_tick(std::time::Duration::from_millis(100));
+    let report = exam::finish(&all, &scoring::load(lessons_root)?, now, |r| bar.set_message(r.id.clone()))?;
+    bar.finish_and_clear();
+    print_exam_report(&report);
+    export_exam_report(&report, json, html)
+}
+
+fn print_exam_report(r: &exam::ExamReport) {
+    println!("{}", "Exam report".bold());
+    for l in &r.lessons {
+        let status = match (l.passed, l.late) {
+            (true, false) => "PASS".green(),
+            (true, true) => "LATE".yellow(),
+            _ => "FAIL".red(),
+        };
+        println!("  {} {:<40} {:>3}/{} pts", status, l.id, l.points, l.max_points);
+    }
+    println!(
+        "Total: {}/{} points, {}/{} lessons, {}m{:02}s of {}m",
+        r.points, r.max_points, r.passed, r.lessons.len(), r.elapsed_secs / 60, r.elapsed_secs % 60, r.time_limit_secs / 60
+    );
+}
+
+fn export_exam_report(r: &exam::ExamReport, json: Option<PathBuf>, html: Option<PathBuf>) -> Result<()> {
+    if let Some(p) = json {
+        util::write_atomic(&p, serde_json::to_string_pretty(r)?.as_bytes())?;
+        println!("Wrote {}", p.display());
+    }
+    if let Some(p) = html {
+        util::write_atomic(&p, exam::to_html(r).as_bytes())?;
+        println!("Wrote {}", p.display());
+    }
+    Ok(())
+}
+
+fn cmd_achievements(lessons_root: &str) -> Result<()> {
+    let defs = achievements::load(lessons_root)?;
+    if defs.is_empty() { println!("No achievements defined in {}/achievements.toml", lessons_root); return Ok(()); }
+    let prog = persistence::load()?;
+    for a in &defs {
+        let icon = a.icon.as_deref().unwrap_or("🏅");
+        match prog.achievements.get(&a.id) {
+            Some(at) => println!("{} {} - {} (unlocked {})", icon, a.name.bold(), a.description, util::format_date(*at)),
+            None => println!("🔒 {} - {}", a.name.dimmed(), a.description.dimmed()),
+        }
+    }
+    println!("{}/{} unlocked", defs.iter().filter(|a| prog.achievements.contains_key(&a.id)).count(), defs.len());
+    Ok(())
+}
+
+fn cmd_rescore(lessons_root: &str, dry_run: bool) -> Result<()> {
+    let changes = scoring::rescore(lessons_root, !dry_run)?;
+    if changes.is_empty() { println!("All lesson points already match the scoring formula."); return Ok(()); }
+    for c in &changes { println!("{:<32} {:>4} -> {:>4}", c.id, c.old, c.new); }
+    let delta: i64 = changes.iter().map(|c| c.new as i64 - c.old as i64).sum();
+    if dry_run {
+        println!("{} lesson(s) would change ({:+} points). Run without --dry-run to apply.", changes.len(), delta);
+    } else {
+        println!("Rescored {} lesson(s) ({:+} points). Total: {}", changes.len(), delta, persistence::load()?.total_points);
+    }
+    Ok(())
+}
+
+fn cmd_leaderboard(storage: &Storage, lesson: Option<String>, since: &str) -> Result<()> {
+    let board = leaderboard::load(storage, lesson.as_deref(), leaderboard::parse_since(since, now_secs())?)?;
+    let window = if board.since == 0 { "all time".to_string() } else { format!("since {}", util::format_date(board.since)) };
+    println!("{}", format!("Leaderboard{} ({})", lesson.as_deref().map(|l| format!(" for {l}")).unwrap_or_default(), window).bold());
+    if board.standings.is_empty() {
+        println!("No attempts recorded yet. Log in (in the GUI) so checks are recorded.");
+        return Ok(());
+    }
+    let me = persistence::load().ok().and_then(|p| p.current_user_id);
+    println!("{:>3}  {:<24} {:>6} {:>8} {:>8}", "#", "Player", "Points", "Lessons", "Fastest");
+    for (i, s) in board.standings.iter().enumerate() {
+        let name = format!("{} {}", s.avatar.as_deref().unwrap_or("🦀"), s.name);
+        let fastest = s.fastest_secs.map(leaderboard::format_secs).unwrap_or_else(|| "-".into());
+        let line = format!("{:>3}  {:<24} {:>6} {:>8} {:>8}", i + 1, name, s.points, s.completed, fastest);
+        if Some(s.user_id) == me { println!("{}", line.bold()); } else { println!("{}", line); }
+    }
+    if !board.fastest.is_empty() {
+        println!("\n{}", "Fastest times".bold());
+        for r in &board.fastest {
+            println!("  {:<36} {:>8}  {}", r.lesson_id, leaderboard::format_secs(r.duration_secs), r.name);
+        }
+    }
+    Ok(())
+}
+
+fn cmd_instructor(lessons_root: &str, opts: InstructorOptions) -> Result<()> {
+    if opts.bundles.is_empty() && opts.db.is_none() { anyhow::bail!("Pass exported bundles and/or --db <app.db>"); }
+    let mut learners = Vec::new();
+    for path in &opts.bundles { learners.push(classroom::from_bundle(path)?); }
+    if let Some(db) = &opts.db {
+        if !db.exists() { anyhow::bail!("Database not found: {}", db.display()); }
+        learners.extend(classroom::from_storage(&Storage::open(db)?)?);
+    }
+    let m = classroom::matrix(&exercise::load_all(lessons_root)?, learners);
+    let names: Vec<String> = m.learners.iter().map(|l| l.name.chars().take(10).collect()).collect();
+    print!("{:<36}", "Lesson");
+    for n in &names { print!(" {:>10}", n); }
+    println!();
+    for id in &m.lessons {
+        print!("{:<36}", id);
+        for l in &m.learners {
+            let c = m.get(l, id);
+            let text = match c.status {
+                classroom::Status::NotStarted => "·".to_string(),
+                classroom::Status::InProgress => format!("…{} {}", c.attempts, c.time_secs.map(leaderboard::format_secs).unwrap_or_default()),
+                classroom::Status::Completed => format!("✓{} {}", c.attempts, c.time_secs.map(leaderboard::format_secs).unwrap_or_default()),
+            };
+            let text = format!(" {:>10}", text.trim_end());
+            match c.status {
+                classroom::Status::Completed => print!("{}", text.green()),
+                classroom::Status::InProgress if c.attempts >= opts.stuck_attempts => print!("{}", text.red()),
+                classroom::Status::InProgress => print!("{}", text.yellow()),
+                classroom::Status::NotStarted => print!("{}", text.dimmed()),
+            }
+        }
+        println!();
+    }
+    println!("{}", "✓ completed, … in progress (checks, time), · not started".dimmed());
+    let stuck = m.stuck(opts.stuck_attempts, opts.stuck_share);
+    if !stuck.is_empty() {
+        println!("\n{}", "Stuck lessons".red().bold());
+        for s in &stuck {
+            println!("  {:<36} {}/{} stuck: {}", s.lesson_id, s.stuck.len(), s.started, s.stuck.join(", "));
+        }
+    }
+    if let Some(path) = &opts.csv {
+        util::write_atomic(path, m.to_csv().as_bytes())?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn cmd_serve(lessons_root: String, host: &str, port: u16, workers: usize, queue: usize, timeout_secs: Option<u64>) -> Result<()> {
+    let root = util::data_dir()?.join("serve");
+    let cfg = server::ServeConfig { lessons_root, root: root.clone(), workers: workers.max(1), queue, timeout_secs };
+    let app = server::App::new(cfg, Storage::open_default()?);
+    let listener = std::net::TcpListener::bind((host, port)).with_context(|| format!("bind {}:{}", host, port))?;
+    println!("Serving on http://{} ({} grading worker(s), queue of {})", listener.local_addr()?, workers.max(1), queue);
+    println!("Learner data: {}", root.display());
+    server::serve(app, listener)
+}
+
+fn cmd_daily(storage: &Storage, lessons_root: &str) -> Result<()> {
+    let lessons = exercise::load_all(lessons_root)?;
+    let pool = daily::load_pool(lessons_root)?;
+    let prog = persistence::load()?;
+    let attempts = match prog.current_user_id { Some(uid) => storage.attempts_for_user(uid)?, None => Vec::new() };
+    let st = daily::status(&lessons, &pool, &attempts, &prog, now_secs());
+    match &st.lesson_id {
+        Some(id) => {
+            let mark = if st.done { "✓ done".green() } else { "not yet solved".yellow() };
+            println!("Daily challenge: {} ({})", id.bold(), mark);
+            if !st.done { println!("Solve it today for +{} bonus points: `rust-game start {}`", daily::DAILY_BONUS, id); }
+        }
+        None => println!("No lessons found under '{}'", lessons_root),
+    }
+    if prog.current_user_id.is_none() {
+        println!("Log in (in the GUI) to record attempts and track streaks.");
+        return Ok(());
+    }
+    println!("Streak: {} day(s) (best {})", st.streak, st.best);
+    if let Some((days, bonus)) = st.next_milestone {
+        println!("Next milestone: {} days, +{} points", days, bonus);
+    }
+    Ok(())
+}
@@ -0,0 +1,328 @@
+//! Timed exams: a set of lessons solved from their starters against one
+//! deadline, with hints and solutions locked, graded together at the end.
+
+use crate::{exercise::{self, Exercise}, grader, scoring::ScoringConfig, util};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+/// How to pick the exam's lessons. A non-empty `lessons` list is used as is;
+/// otherwise lessons matching `tag` and `difficulty` are drawn, `count` of them
+/// when set, shuffled by `seed`.
+#[derive(Debug, Clone, Default)]
+pub struct ExamSpec {
+    pub lessons: Vec<String>,
+    pub tag: Option<String>,
+    pub difficulty: Option<String>,
+    pub count: Option<usize>,
+    pub minutes: u64,
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExamSession {
+    pub started_at: i64,
+    pub deadline: i64,
+    pub lessons: Vec<String>,
+    /// Set when grading starts, so a retried submit keeps the first time.
+    #[serde(default)]
+    pub submitted_at: Option<i64>,
+}
+
+impl ExamSession {
+    pub fn remaining_secs(&self, now: i64) -> i64 { (self.deadline - now).max(0) }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LessonResult {
+    pub id: String,
+    pub title: String,
+    pub difficulty: String,
+    pub passed: bool,
+    /// The exam was submitted after the deadline; it is graded but scores nothing.
+    pub late: bool,
+    pub points: u32,
+    pub max_points: u32,
+    /// Test output, only kept for failures.
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExamReport {
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub time_limit_secs: u64,
+    pub elapsed_secs: u64,
+    pub lessons: Vec<LessonResult>,
+    pub passed: usize,
+    pub points: u32,
+    pub max_points: u32,
+}
+
+fn exam_dir() -> Result<PathBuf> {
+    let dir = util::data_dir()?.join("exam");
+    fs::create_dir_all(&dir).ok();
+    Ok(dir)
+}
+
+fn session_path() -> Result<PathBuf> { Ok(exam_dir()?.join("session.json")) }
+
+/// Exam copy of a lesson's `lib.rs`, kept apart from the regular working copy.
+pub fn working_file(id: &str) -> Result<PathBuf> {
+    Ok(exam_dir()?.join("work").join(exercise::dir_name(id)).join("lib.rs"))
+}
+
+/// The running exam, if any.
+pub fn active() -> Result<Option<ExamSession>> {
+    let p = session_path()?;
+    if !p.exists() { return Ok(None); }
+    let data = fs::read_to_string(&p).with_context(|| format!("read {}", p.display()))?;
+    Ok(Some(serde_json::from_str(&data).with_context(|| format!("parse {}", p.display()))?))
+}
+
+/// Fails while an exam is running. `what` names the locked action, e.g. "Hints".
+pub fn ensure_unlocked(what: &str) -> Result<()> {
+    if active()?.is_some() { anyhow::bail!("{} are locked during the exam (see `rust-game exam status`)", what); }
+    Ok(())
+}
+
+/// Picks lessons per `spec`, ordered by difficulty and then the curriculum.
+/// Unlike regular play, gating does not apply: every picked lesson is open.
+pub fn select<'a>(all: &'a [Exercise], spec: &ExamSpec) -> Result<Vec<&'a Exercise>> {
+    let mut picked: Vec<&Exercise> = if !spec.lessons.is_empty() {
+        spec.lessons.iter()
+            .map(|id| all.iter().find(|e| &e.meta.id == id).with_context(|| format!("Exercise '{}' not found", id)))
+            .collect::<Result<_>>()?
+    } else {
+        let mut xs: Vec<&Exercise> = all.iter()
+            .filter(|e| spec.tag.as_ref().is_none_or(|t| e.meta.tags.contains(t)))
+            .filter(|e| spec.difficulty.as_ref().is_none_or(|d| &e.meta.difficulty == d))
+            .collect();
+        if let Some(n) = spec.count.filter(|n| *n < xs.len()) {
+            shuffle(&mut xs, spec.seed.unwrap_or(0));
+            xs.truncate(n);
+        }
+        xs
+    };
+    if picked.is_empty() { anyhow::bail!("No lessons match the exam selection"); }
+    picked.sort_by(|a, b| {
+        (a.meta.difficulty_rank(), a.meta.curriculum_rank(), &a.meta.id).cmp(&(b.meta.difficulty_rank(), b.meta.curriculum_rank(), &b.meta.id))
+    });
+    picked.dedup_by(|a, b| a.meta.id == b.meta.id);
+    Ok(picked)
+}
+
+/// Fisher-Yates with xorshift, so a seed always yields the same exam.
+fn shuffle<T>(xs: &mut [T], seed: u64) {
+    let mut s = seed | 1;
+    for i in (1..xs.len()).rev() {
+        s ^= s << 13;
+        s ^= s >> 7;
+        s ^= s << 17;
+        xs.swap(i, (s % (i as u64 + 1)) as usize);
+    }
+}
+
+/// Starts an exam: writes each starter to its exam working file and sets the deadline.
+pub fn start(all: &[Exercise], spec: &ExamSpec, now: i64) -> Result<ExamSession> {
+    if active()?.is_some() { anyhow::bail!("An exam is already running; submit or abort it first"); }
+    let lessons = select(all, spec)?;
+    for ex in &lessons {
+        let file = working_file(&ex.meta.id)?;
+        if let Some(dir) = file.parent() { fs::create_dir_all(dir)?; }
+        let starter = fs::read(&ex.starter_rs).with_context(|| format!("read {}", ex.starter_rs.display()))?;
+        util::write_atomic(&file, &starter)?;
+    }
+    let session = ExamSession {
+        started_at: now,
+        deadline: now + spec.minutes as i64 * 60,
+        lessons: lessons.iter().map(|e| e.meta.id.clone()).collect(),
+        submitted_at: None,
+    };
+    util::write_atomic(&session_path()?, serde_json::to_string_pretty(&session)?.as_bytes())?;
+    Ok(session)
+}
+
+/// Ends the running exam without grading it.
+pub fn abort() -> Result<Option<ExamSession>> {
+    let session = active()?;
+    if session.is_some() { fs::remove_file(session_path()?)?; }
+    Ok(session)
+}
+
+/// Grades every exam lesson, saves the report and ends the exam. Points
+/// follow `cfg`, like regular play. `on_graded` is called after each lesson so
+/// callers can show progress.
+pub fn finish(all: &[Exercise], cfg: &ScoringConfig, now: i64, mut on_graded: impl FnMut(&LessonResult)) -> Result<ExamReport> {
+    let mut session = active()?.context("No exam is running")?;
+    // Recorded before grading, which can take minutes; file times can be forged.
+    if session.submitted_at.is_none() {
+        session.submitted_at = Some(now);
+        util::write_atomic(&session_path()?, serde_json::to_string_pretty(&session)?.as_bytes())?;
+    }
+    let submitted = session.submitted_at.unwrap_or(now);
+    let late = submitted > session.deadline;
+    let mut lessons = Vec::new();
+    for id in &session.lessons {
+        let ex = all.iter().find(|e| &e.meta.id == id).with_context(|| format!("Exercise '{}' not found", id))?;
+        let file = working_file(id)?;
+        let (passed, output) = if file.exists() {
+            let outcome = grader::grade(ex, &file, ex.meta.timeout_secs.unwrap_or(15))?;
+            let output = if outcome.passed { String::new() } else { tail(&format!("{}\n{}", outcome.stdout, outcome.stderr), 40) };
+            (outcome.passed, output)
+        } else {
+            (false, "Working file missing".to_string())
+        };
+        let max_points = cfg.base_points(&ex.meta);
+        let result = LessonResult {
+            id: id.clone(),
+            title: ex.meta.title.clone(),
+            difficulty: ex.meta.difficulty.clone(),
+            passed,
+            late,
+            points: if passed && !late { max_points } else { 0 },
+            max_points,
+            output,
+        };
+        on_graded(&result);
+        lessons.push(result);
+    }
+    let report = ExamReport {
+        started_at: session.started_at,
+        finished_at: submitted,
+        time_limit_secs: (session.deadline - session.started_at).max(0) as u64,
+        elapsed_secs: (submitted.min(session.deadline) - session.started_at).max(0) as u64,
+        passed: lessons.iter().filter(|l| l.passed && !l.late).count(),
+        points: lessons.iter().map(|l| l.points).sum(),
+        max_points: lessons.iter().map(|l| l.max_points).sum(),
+        lessons,
+    };
+    let path = exam_dir()?.join(format!("report-{}.json", session.started_at));
+    util::write_atomic(&path, serde_json::to_string_pretty(&report)?.as_bytes())?;
+    fs::remove_file(session_path()?)?;
+    Ok(report)
+}
+
+/// The most recent saved report.
+pub fn last_report() -> Result<Option<ExamReport>> {
+    let mut reports: Vec<PathBuf> = fs::read_dir(exam_dir()?)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("report-") && n.ends_with(".json")))
+        .collect();
+    reports.sort();
+    let Some(p) = reports.pop() else { return Ok(None) };
+    let data = fs::read_to_string(&p).with_context(|| format!("read {}", p.display()))?;
+    Ok(Some(serde_json::from_str(&data).with_context(|| format!("parse {}", p.display()))?))
+}
+
+fn tail(s: &str, lines: usize) -> String {
+    let all: Vec<&str> = s.trim().lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Stand-alone HTML page for a report.
+pub fn to_html(r: &ExamReport) -> String {
+    let mut rows = String::new();
+    for l in &r.lessons {
+        let status = match (l.passed, l.late) { (true, false) => "passed", (true, true) => "late", _ => "failed" };
+        rows.push_str(&format!(
+            "<tr class=\"{status}\"><td>{}</td><td>{}</td><td>{}</td><td>{status}</td><td>{}/{}</td></tr>\n",
+            escape(&l.id), escape(&l.title), escape(&l.difficulty), l.points, l.max_points
+        ));
+        if !l.output.is_empty() {
+            rows.push_str(&format!("<tr><td colspan=\"5\"><pre>{}</pre></td></tr>\n", escape(&l.output)));
+        }
+    }
+    format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><title>Exam report</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; }}
+td, th {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}
+tr.passed td:nth-child(4) {{ color: #1a7f37; }}
+tr.failed td:nth-child(4), tr.late td:nth-child(4) {{ color: #cf222e; }}
+pre {{ margin: 0; font-size: 0.85em; white-space: pre-wrap; }}
+</style></head><body>
+<h1>Exam report</h1>
+<p>Score: <b>{}/{}</b> points, {}/{} lessons passed. Time used: {}m{:02}s of {}m.</p>
+<table>
+<tr><th>Lesson</th><th>Title</th><th>Difficulty</th><th>Status</th><th>Points</th></tr>
+{}</table>
+</body></html>
+"#,
+        r.points, r.max_points, r.passed, r.lessons.len(), r.elapsed_secs / 60, r.elapsed_secs % 60, r.time_limit_secs / 60, rows
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exercise::ExerciseMeta;
+
+    fn lesson(id: &str, difficulty: &str, tags: &[&str]) -> Exercise {
+        Exercise {
+            meta: ExerciseMeta {
+                id: id.into(),
+                title: id.into(),
+                difficulty: difficulty.into(),
+                hint: None,
+                timeout_secs: None,
+                hints: Vec::new(),
+                hint_cost: None,
+                contextual_hints: Vec::new(),
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                coverage: false,
+                kind: Default::default(),
+                miri: false,
+                order: None,
+            },
+            root: PathBuf::new(),
+            starter_rs: PathBuf::new(),
+            tests_rs: PathBuf::new(),
+            solution_rs: None,
+            explanation_md: None,
+        }
+    }
+
+    #[test]
+    fn selects_by_tag_count_and_list() {
+        let all = vec![
+            lesson("advanced/arc_mutex_counter", "advanced", &["concurrency"]),
+            lesson("intro/ownership", "beginner", &["ownership"]),
+            lesson("intro/variables", "beginner", &["basics"]),
+            lesson("intermediate/lifetimes_longest", "intermediate", &["ownership"]),
+        ];
+        let ids = |xs: Vec<&Exercise>| xs.iter().map(|e| e.meta.id.clone()).collect::<Vec<_>>();
+
+        let spec = ExamSpec { tag: Some("ownership".into()), ..Default::default() };
+        assert_eq!(ids(select(&all, &spec).unwrap()), vec!["intro/ownership", "intermediate/lifetimes_longest"]);
+
+        let spec = ExamSpec { count: Some(2), seed: Some(7), ..Default::default() };
+        let a = ids(select(&all, &spec).unwrap());
+        assert_eq!(a.len(), 2);
+        assert_eq!(a, ids(select(&all, &spec).unwrap()));
+
+        let spec = ExamSpec { lessons: vec!["advanced/arc_mutex_counter".into(), "intro/variables".into()], ..Default::default() };
+        assert_eq!(ids(select(&all, &spec).unwrap()), vec!["intro/variables", "advanced/arc_mutex_counter"]);
+        let spec = ExamSpec { lessons: vec!["intro/nope".into()], ..Default::default() };
+        assert!(select(&all, &spec).is_err());
+        let spec = ExamSpec { tag: Some("async".into()), ..Default::default() };
+        assert!(select(&all, &spec).is_err());
+    }
+
+    #[test]
+    fn html_escapes_output() {
+        let r = ExamReport {
+            started_at: 0, finished_at: 60, time_limit_secs: 600, elapsed_secs: 60, passed: 0, points: 0, max_points: 10,
+            lessons: vec![LessonResult { id: "intro/x".into(), title: "X".into(), difficulty: "beginner".into(), passed: false, late: false, points: 0, max_points: 10, output: "expected <i32>".into() }],
+        };
+        let html = to_html(&r);
+        assert!(html.contains("expected &lt;i32&gt;"));
+        assert!(html.contains("<b>0/10</b>"));
+    }
+}
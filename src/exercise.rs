@@ -12,6 +12,102 @@ pub struct ExerciseMeta {
     pub difficulty: String,
     pub hint: Option<String>,
     pub timeout_secs: Option<u64>,
+    /// Ordered from a gentle nudge to nearly the answer. A lone `hint` becomes a single tier.
+    #[serde(default)]
+    pub hints: Vec<String>,
+    /// Points deducted per revealed hint (default: a fifth of the lesson's points).
+    #[serde(default)]
+    pub hint_cost: Option<u32>,
+    #[serde(default)]
+    pub contextual_hints: Vec<ContextualHint>,
+    /// Topics such as `ownership` or `concurrency`, used to pick exam lessons.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Measure line coverage after every pass, for lessons where learners write tests.
+    #[serde(default)]
+    pub coverage: bool,
+    #[serde(default)]
+    pub kind: ExerciseKind,
+    /// After the tests pass, run them again under `cargo miri test` to catch undefined behavior.
+    #[serde(default)]
+    pub miri: bool,
+    /// Position in its pack's manifest; pack lessons follow that order instead of the built-in curriculum.
+    #[serde(skip)]
+    pub order: Option<u32>,
+}
+
+/// What the learner writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExerciseKind {
+    /// The implementation, checked by the lesson's `tests.rs`.
+    #[default]
+    Implement,
+    /// The tests, which must pass on `reference.rs` and catch every file in `mutants/`.
+    Tests,
+}
+
+/// Hint shown automatically after a failed check whose output mentions
+/// `error` (a compiler code like `E0382`) or a failing `test` name.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContextualHint {
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub test: Option<String>,
+    pub text: String,
+}
+
+impl ExerciseMeta {
+    /// Position in the curated order within the lesson's difficulty tier;
+    /// lessons missing from the list sort last.
+    pub fn curriculum_rank(&self) -> u32 {
+        if let Some(order) = self.order { return order; }
+        const BEGINNER: &[&str] = &[
+            "variables",
+            "control_flow_loops",
+            "arrays_slices_basics",
+            "functions",
+            "ownership",
+            "strings_utf8",
+            "pattern_matching_ergonomics",
+            "match_enums",
+            "hashmap",
+            "iterators",
+            "result_error",
+            "traits",
+        ];
+        const INTERMEDIATE: &[&str] = &[
+            "lifetimes_longest",
+            "generics_asref_maxlen",
+            "result_map_errors",
+            "iterators_combinators",
+            "iterators_ownership",
+            "rc_refcell",
+            "thiserror",
+        ];
+        const ADVANCED: &[&str] = &[
+            "arc_mutex_counter",
+            "arc_rwlock_counter",
+            "channels_mpsc",
+            "tokio_mpsc",
+            "tokio_timeout_race",
+            "traits_dyn_dispatch",
+        ];
+        let slug = self.id.rsplit('/').next().unwrap_or(&self.id);
+        let list = match self.difficulty.as_str() { "beginner" => BEGINNER, "intermediate" => INTERMEDIATE, _ => ADVANCED };
+        list.iter().position(|s| *s == slug).map(|i| i as u32).unwrap_or(999)
+    }
+
+    /// The pack a lesson came from: `async-extra` for `async-extra:advanced/select`.
+    pub fn pack(&self) -> Option<&str> {
+        self.id.split_once(':').map(|(pack, _)| pack)
+    }
+
+    /// 0 for beginner, 1 for intermediate, 2 for anything harder.
+    pub fn difficulty_rank(&self) -> u32 {
+        match self.difficulty.as_str() { "beginner" => 0, "intermediate" => 1, _ => 2 }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -32,14 +128,81 @@ impl Exercise {
     }
 
     pub fn working_file(&self) -> Result<PathBuf> {
-        Ok(Self::working_dir()?.join(self.meta.id.replace('/', "_")).join("lib.rs"))
+        Self::working_file_for(&self.meta.id)
+    }
+
+    /// Working file for a lesson id without loading the lesson itself.
+    pub fn working_file_for(id: &str) -> Result<PathBuf> {
+        Ok(Self::working_dir()?.join(dir_name(id)).join("lib.rs"))
+    }
+
+    /// Hash of what grades the lesson: `tests.rs`, or `reference.rs` and the
+    /// mutants. Edits to hints, titles or the starter leave it unchanged.
+    pub fn content_hash(&self) -> Result<String> {
+        let mut files = match self.meta.kind {
+            ExerciseKind::Implement => vec![self.tests_rs.clone()],
+            ExerciseKind::Tests => crate::mutants::load(self)?.into_iter().map(|m| m.path).collect(),
+        };
+        if self.meta.kind == ExerciseKind::Tests { files.insert(0, crate::mutants::reference(self)); }
+        // FNV-1a: stable across Rust releases, unlike `DefaultHasher`.
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for f in files {
+            let data = fs::read(&f).with_context(|| format!("read {}", f.display()))?;
+            let name = f.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            for b in name.bytes().chain([0]).chain(data.into_iter().filter(|b| *b != b'\r')) {
+                hash = (hash ^ b as u64).wrapping_mul(0x100_0000_01b3);
+            }
+        }
+        Ok(format!("{:016x}", hash))
+    }
+}
+
+/// Directory for a lesson's working copy or sandbox, relative to `work/` or
+/// `sandboxes/`: `intro_variables`, or `packs/<pack>/advanced_select` for a
+/// pack lesson so it can't collide with a built-in one.
+pub fn dir_name(id: &str) -> PathBuf {
+    match id.split_once(':') {
+        Some((pack, rest)) => Path::new("packs").join(pack).join(rest.replace('/', "_")),
+        None => PathBuf::from(id.replace('/', "_")),
     }
 }
 
+/// Whether `id` is open: the first lesson of its difficulty (within its pack)
+/// in curriculum order, or one whose predecessor is `completed`.
+pub fn unlocked(all: &[Exercise], id: &str, completed: impl Fn(&str) -> bool) -> bool {
+    let Some(ex) = all.iter().find(|e| e.meta.id == id) else { return false };
+    let mut same: Vec<&Exercise> = all.iter().filter(|e| e.meta.difficulty == ex.meta.difficulty && e.meta.pack() == ex.meta.pack()).collect();
+    same.sort_by(|a, b| a.meta.curriculum_rank().cmp(&b.meta.curriculum_rank()).then_with(|| a.meta.id.cmp(&b.meta.id)));
+    match same.iter().position(|e| e.meta.id == id) {
+        Some(0) | None => true,
+        Some(pos) => completed(&same[pos - 1].meta.id),
+    }
+}
+
+/// The catalog: lessons under `lessons_root`, then those of every installed
+/// pack. A broken pack is skipped with a warning rather than failing the rest.
 pub fn load_all(lessons_root: &str) -> Result<Vec<Exercise>> {
+    let mut out = load_dir(Path::new(lessons_root), None)?;
+    for pack in crate::packs::installed()? {
+        let name = &pack.manifest.name;
+        let mut xs = match load_dir(&pack.root, Some(name)) {
+            Ok(xs) => xs,
+            Err(e) => { crate::packs::warn_broken(&pack.root, &e); continue; }
+        };
+        for ex in &mut xs {
+            let rel = ex.meta.id.strip_prefix(&format!("{}:", name)).unwrap_or(&ex.meta.id);
+            ex.meta.order = pack.manifest.lessons.iter().position(|l| l.trim_matches('/').replace('\\', "/") == rel).map(|i| i as u32);
+        }
+        out.extend(xs);
+    }
+    Ok(out)
+}
+
+/// Lessons under `root`; with a `pack`, their ids are prefixed `<pack>:`.
+pub fn load_dir(root_dir: &Path, pack: Option<&str>) -> Result<Vec<Exercise>> {
     let mut out = Vec::new();
 
-    for entry in WalkDir::new(lessons_root)
+    for entry in WalkDir::new(root_dir)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_name() == "exercise.toml")
@@ -48,27 +211,36 @@ pub fn load_all(lessons_root: &str) -> Result<Vec<Exercise>> {
         let meta_toml = fs::read_to_string(entry.path())
             .with_context(|| format!("Reading {:?}", entry.path()))?;
         #[derive(Deserialize)]
-        struct PartialMeta { title: String, difficulty: String, hint: Option<String>, timeout_secs: Option<u64> }
+        struct PartialMeta { title: String, difficulty: String, hint: Option<String>, timeout_secs: Option<u64>, #[serde(default)] hints: Vec<String>, hint_cost: Option<u32>, #[serde(default)] contextual_hints: Vec<ContextualHint>, #[serde(default)] tags: Vec<String>, #[serde(default)] coverage: bool, #[serde(default)] kind: ExerciseKind, #[serde(default)] miri: bool }
         let pm: PartialMeta = toml::from_str(&meta_toml)
             .with_context(|| format!("Parsing {:?}", entry.path()))?;
 
-        let rel_root = root.strip_prefix(Path::new(lessons_root)).unwrap_or(&root);
-        let id = rel_root
+        let rel_root = root.strip_prefix(root_dir).unwrap_or(&root);
+        let rel_id = rel_root
             .components()
             .map(|c| c.as_os_str().to_string_lossy().to_string())
             .collect::<Vec<_>>()
             .join("/");
+        let id = match pack { Some(p) => format!("{}:{}", p, rel_id), None => rel_id };
 
         let starter_rs = root.join("starter.rs");
         let tests_rs = root.join("tests.rs");
         let solution_rs = root.join("solution.rs");
         let explanation_md = root.join("explanation.md");
-        if !starter_rs.exists() || !tests_rs.exists() {
-            anyhow::bail!("Exercise {:?} missing starter.rs or tests.rs", root);
+        if !starter_rs.exists() {
+            anyhow::bail!("Exercise {:?} missing starter.rs", root);
         }
+        match pm.kind {
+            ExerciseKind::Implement if !tests_rs.exists() => anyhow::bail!("Exercise {:?} missing tests.rs", root),
+            ExerciseKind::Tests if !root.join("reference.rs").exists() => anyhow::bail!("Exercise {:?} missing reference.rs", root),
+            _ => {}
+        }
+
+        let mut hints = pm.hints;
+        if hints.is_empty() { hints.extend(pm.hint.clone()); }
 
         out.push(Exercise {
-            meta: ExerciseMeta { id, title: pm.title, difficulty: pm.difficulty, hint: pm.hint, timeout_secs: pm.timeout_secs },
+            meta: ExerciseMeta { id, title: pm.title, difficulty: pm.difficulty, hint: pm.hint, timeout_secs: pm.timeout_secs, hints, hint_cost: pm.hint_cost, contextual_hints: pm.contextual_hints, tags: pm.tags, coverage: pm.coverage, kind: pm.kind, miri: pm.miri, order: None },
             root,
             starter_rs,
             tests_rs,
@@ -105,10 +277,20 @@ hint = "Use let mut"
 #[test] fn basic(){ assert_eq!(crate::answer(), 42); }
 "#).unwrap();
 
-        let xs = load_all(dir.path().join("lessons").to_str().unwrap()).unwrap();
+        // `load_dir`, not `load_all`, which would add the packs installed in the real data dir.
+        let xs = load_dir(&dir.path().join("lessons"), None).unwrap();
         assert_eq!(xs.len(), 1);
         assert!(xs[0].starter_rs.exists());
         assert!(xs[0].tests_rs.exists());
         assert!(xs[0].meta.id.ends_with("intro/vars"));
+        assert_eq!(xs[0].meta.hints, vec!["Use let mut".to_string()]);
+        assert_eq!(dir_name("intro/vars"), PathBuf::from("intro_vars"));
+        assert_eq!(dir_name("intro:vars"), Path::new("packs").join("intro").join("vars"));
+
+        let before = xs[0].content_hash().unwrap();
+        fs::write(root.join("starter.rs"), "pub fn answer()->i32{1}\n").unwrap();
+        assert_eq!(xs[0].content_hash().unwrap(), before);
+        fs::write(root.join("tests.rs"), "#[test] fn basic(){ assert_eq!(crate::answer(), 43); }\n").unwrap();
+        assert_ne!(xs[0].content_hash().unwrap(), before);
     }
 }
\ No newline at end of file
@@ -1,68 +1,284 @@
 //! Compile-and-test grader with timeout.
 
-use crate::exercise::Exercise;
-use crate::util;
+use crate::exercise::{self, Exercise, ExerciseKind};
+use crate::{miri::{self, UbReport}, mutants, util};
 use anyhow::{Context, Result};
 use std::{
+    fmt::{self, Write as _},
     fs,
-    path::Path,
-    process::{Command, Stdio},
-    time::Duration,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
 };
 use wait_timeout::ChildExt;
 
+#[derive(Debug, Default)]
 pub struct GradeOutcome {
     pub passed: bool,
     pub stdout: String,
     pub stderr: String,
+    /// Mutants the learner's tests did not catch, in test-writing lessons.
+    pub survivors: Vec<String>,
+    /// Undefined behavior Miri found; any fails the check.
+    pub ub: Vec<UbReport>,
+    /// Remarks that don't affect the result, such as a skipped Miri run.
+    pub notes: Vec<String>,
+    pub hang: Option<Hang>,
 }
 
-pub fn grade(ex: &Exercise, user_src: &Path, timeout_secs: u64) -> Result<GradeOutcome> {
-    let proj = sandbox_dir_for(ex)?;
+/// Why a check ran out of time, or found tasks waiting on each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hang {
+    /// The build alone took longer than the timeout.
+    Compile { secs: u64 },
+    /// Tests were still running at the timeout: an endless loop, a blocked
+    /// thread or a future nothing will wake. Lists tests without a result.
+    Tests { secs: u64, tests: Vec<String> },
+    /// `prelude::within` gave up while tokio's paused clock ran ahead,
+    /// which only happens when every task is waiting.
+    Deadlock { tests: Vec<String> },
+}
 
-    if !proj.exists() {
-        fs::create_dir_all(&proj)?;
-        write_cargo_toml(&proj)?;
+impl fmt::Display for Hang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hang::Compile { secs } => write!(f, "Compiling took longer than {}s. The first build of a lesson is slow; try again or raise the timeout", secs),
+            Hang::Tests { secs, tests } if tests.is_empty() => write!(f, "Tests were still running after {}s: look for an endless loop, a deadlock or a blocking call", secs),
+            Hang::Tests { secs, tests } => write!(f, "Tests were still running after {}s: {}. Look for an endless loop, a deadlock or a blocking call", secs, tests.join(", ")),
+            Hang::Deadlock { tests } => write!(f, "Deadlock: every task was waiting in {}", tests.join(", ")),
+        }
     }
+}
+
+pub fn grade(ex: &Exercise, user_src: &Path, timeout_secs: u64) -> Result<GradeOutcome> {
+    grade_in(&sandbox_dir(ex)?, ex, user_src, timeout_secs)
+}
+
+/// `grade` using the sandbox project at `proj`, created on first use.
+pub fn grade_in(proj: &Path, ex: &Exercise, user_src: &Path, timeout_secs: u64) -> Result<GradeOutcome> {
+    grade_cancellable(proj, ex, user_src, timeout_secs, &AtomicBool::new(false))
+}
 
-    let src_dir = proj.join("src");
-    fs::create_dir_all(&src_dir)?;
+/// `grade_in` that stops `cargo test` soon after `cancel` is set. Holds the
+/// sandbox lock throughout, so checks of one lesson from the CLI, GUI and
+/// scheduler workers take turns instead of overwriting each other's files.
+pub fn grade_cancellable(proj: &Path, ex: &Exercise, user_src: &Path, timeout_secs: u64, cancel: &AtomicBool) -> Result<GradeOutcome> {
+    let _lock = lock_sandbox(proj)?;
+    if cancel.load(Ordering::Relaxed) { return Ok(cancelled()); }
+    fs::create_dir_all(proj)?;
+    write_cargo_toml(proj)?;
+    fs::create_dir_all(proj.join("src"))?;
     fs::create_dir_all(proj.join("tests"))?;
 
-    fs::copy(user_src, src_dir.join("lib.rs")).with_context(|| "copy user code into sandbox")?;
+    match ex.meta.kind {
+        ExerciseKind::Implement => {
+            let out = run_tests(proj, user_src, &fs::read_to_string(&ex.tests_rs)?, timeout_secs, cancel)?;
+            if out.passed && ex.meta.miri { check_miri(proj, out, timeout_secs, cancel) } else { Ok(out) }
+        }
+        ExerciseKind::Tests => grade_tests(proj, ex, user_src, timeout_secs, cancel),
+    }
+}
+
+/// Test-writing lessons: the learner's tests must pass on `reference.rs`
+/// and fail on every mutant.
+fn grade_tests(proj: &Path, ex: &Exercise, user_tests: &Path, timeout_secs: u64, cancel: &AtomicBool) -> Result<GradeOutcome> {
+    let tests = fs::read_to_string(user_tests).context("read your tests")?;
+    let reference = run_tests(proj, &mutants::reference(ex), &tests, timeout_secs, cancel)?;
+    if !reference.passed {
+        let stdout = format!("Your tests must pass on the correct implementation first.\n\n{}", reference.stdout);
+        return Ok(GradeOutcome { stdout, ..reference });
+    }
+    let all = mutants::load(ex)?;
+    let mut lines = String::new();
+    let mut survivors = Vec::new();
+    for m in &all {
+        let out = run_tests(proj, &m.path, &tests, timeout_secs, cancel)?;
+        if cancel.load(Ordering::Relaxed) { return Ok(cancelled()); }
+        if out.passed { survivors.push(m.name.clone()); }
+        let about = m.description.as_deref().map(|d| format!(" - {d}")).unwrap_or_default();
+        let _ = writeln!(lines, "  {} {}{}", if out.passed { "survived" } else { "caught  " }, m.name, about);
+    }
+    // Leave the sandbox on the reference rather than the last mutant.
+    fs::copy(mutants::reference(ex), proj.join("src").join("lib.rs"))?;
+    let stdout = format!("Your tests pass on the correct implementation.\nMutants caught: {}/{}\n{}", all.len() - survivors.len(), all.len(), lines);
+    Ok(GradeOutcome { passed: survivors.is_empty(), stdout, survivors, ..Default::default() })
+}
+
+/// Re-runs tests that passed under Miri. Only undefined behavior fails the
+/// check; a missing Miri or one that can't run the tests adds a note instead.
+fn check_miri(proj: &Path, passed: GradeOutcome, timeout_secs: u64, cancel: &AtomicBool) -> Result<GradeOutcome> {
+    let Some(cmd) = miri::command() else {
+        return Ok(GradeOutcome { notes: vec![miri::UNAVAILABLE.to_string()], ..passed });
+    };
+    // Miri interprets every step, so it gets far longer than a normal run.
+    let secs = timeout_secs.saturating_mul(4).max(120);
+    let (success, stdout, stderr) = match run(proj, cmd, &["test"], secs, cancel)? {
+        Ran::Exited { success, stdout, stderr } => (success, stdout, stderr),
+        Ran::TimedOut { .. } => return Ok(GradeOutcome { notes: vec![format!("Miri did not finish within {secs}s, so undefined behavior was not checked")], ..passed }),
+        Ran::Cancelled => return Ok(cancelled()),
+    };
+    let ub = miri::parse(&stdout, &stderr);
+    if !ub.is_empty() { return Ok(GradeOutcome { passed: false, stdout, stderr: strip_cargo_status(&stderr), ub, ..Default::default() }); }
+    let note = if success {
+        "Miri found no undefined behavior".to_string()
+    } else {
+        let why = stderr.lines().find(|l| l.starts_with("error")).unwrap_or("it failed without a report");
+        format!("Miri could not check these tests: {why}")
+    };
+    Ok(GradeOutcome { notes: vec![note], ..passed })
+}
+
+/// Runs `tests` (with `crate::` paths) against `lib_src` in the sandbox.
+/// The build and the test run each get `timeout_secs`, so a slow first
+/// compile and a hanging test are told apart.
+fn run_tests(proj: &Path, lib_src: &Path, tests: &str, timeout_secs: u64, cancel: &AtomicBool) -> Result<GradeOutcome> {
+    fs::copy(lib_src, proj.join("src").join("lib.rs")).with_context(|| "copy user code into sandbox")?;
+    let rewritten = tests.replace("crate::", "exercise_sandbox::");
+    // Declared last so panics keep the line numbers of the lesson's file.
+    fs::write(proj.join("tests").join("exercise.rs"), format!("{}\nmod prelude;\n", rewritten.trim_end()))?;
+
+    let build = run(proj, &["cargo"], &["test", "--no-run", "--quiet"], timeout_secs, cancel)?;
+    match build {
+        Ran::Cancelled => return Ok(cancelled()),
+        Ran::TimedOut { stderr, .. } => return Ok(hung(Hang::Compile { secs: timeout_secs }, String::new(), stderr)),
+        Ran::Exited { success: false, stdout, stderr } => return Ok(GradeOutcome { stdout, stderr, ..Default::default() }),
+        Ran::Exited { .. } => {}
+    }
+    match run(proj, &["cargo"], &["test"], timeout_secs, cancel)? {
+        Ran::Cancelled => Ok(cancelled()),
+        Ran::TimedOut { stdout, stderr } => {
+            let listed = match run(proj, &["cargo"], &["test", "--quiet", "--", "--list"], 30, cancel)? {
+                Ran::Exited { stdout, .. } => stdout,
+                _ => String::new(),
+            };
+            Ok(hung(Hang::Tests { secs: timeout_secs, tests: unfinished_tests(&listed, &stdout) }, stdout, stderr))
+        }
+        Ran::Exited { success, stdout, stderr } => {
+            let deadlocked = deadlocked_tests(&stdout);
+            let hang = (!deadlocked.is_empty()).then_some(Hang::Deadlock { tests: deadlocked });
+            Ok(GradeOutcome { passed: success, stderr: strip_cargo_status(&stderr), stdout, hang, ..Default::default() })
+        }
+    }
+}
+
+/// The hang is reported through `hang`; `stderr` stays what the process wrote.
+fn hung(hang: Hang, stdout: String, stderr: String) -> GradeOutcome {
+    GradeOutcome { stdout, stderr, hang: Some(hang), ..Default::default() }
+}
 
-    let raw_tests = fs::read_to_string(&ex.tests_rs)?;
-    let rewritten = raw_tests.replace("crate::", "exercise_sandbox::");
-    fs::write(proj.join("tests").join("exercise.rs"), rewritten)?;
+enum Ran {
+    Exited { success: bool, stdout: String, stderr: String },
+    /// Output up to the moment it was killed.
+    TimedOut { stdout: String, stderr: String },
+    Cancelled,
+}
 
-    let mut cmd = Command::new("cargo");
-    cmd.arg("test")
-        .arg("--quiet")
+/// Runs `program` (a command and its leading arguments, such as `cargo +nightly miri`)
+/// with `args` in the sandbox. On timeout or cancellation its whole process
+/// group is killed, so a hung test binary doesn't outlive `cargo`.
+fn run(proj: &Path, program: &[&str], args: &[&str], timeout_secs: u64, cancel: &AtomicBool) -> Result<Ran> {
+    let mut cmd = Command::new(program[0]);
+    cmd.args(&program[1..])
+        .args(args)
         .current_dir(proj)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+    let mut child = cmd.spawn().with_context(|| format!("spawn {}", program.join(" ")))?;
+    // Drained on threads so a chatty test can't fill the pipe and stall.
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+    let waited = wait(&mut child, Duration::from_secs(timeout_secs), cancel)?;
+    let (stdout, stderr) = (stdout.join().unwrap_or_default(), stderr.join().unwrap_or_default());
+    Ok(match waited {
+        Wait::Exited(status) => Ran::Exited { success: status.success(), stdout, stderr },
+        Wait::TimedOut => Ran::TimedOut { stdout, stderr },
+        Wait::Cancelled => Ran::Cancelled,
+    })
+}
+
+pub(crate) fn drain(pipe: Option<impl Read + Send + 'static>) -> std::thread::JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut p) = pipe { let _ = p.read_to_end(&mut buf); }
+        String::from_utf8_lossy(&buf).to_string()
+    })
+}
+
+/// Names from `cargo test -- --list` that have no result line in `stdout`.
+fn unfinished_tests(listed: &str, stdout: &str) -> Vec<String> {
+    let finished: Vec<&str> = stdout.lines()
+        .filter_map(|l| l.strip_prefix("test ").and_then(|r| r.split_once(" ...")))
+        // With one test thread libtest prints the name before the result.
+        .filter(|(_, result)| !result.trim().is_empty())
+        .map(|(name, _)| name)
+        .collect();
+    listed.lines()
+        .filter_map(|l| l.strip_suffix(": test"))
+        .filter(|name| !finished.contains(name))
+        .map(str::to_string)
+        .collect()
+}
 
-    let mut child = cmd.spawn().context("spawn cargo test")?;
-    let status = match child
-        .wait_timeout(Duration::from_secs(timeout_secs))
-        .context("waiting for test with timeout")?
-    {
-        Some(status) => status,
-        None => {
-            child.kill().ok();
-            child.wait().ok();
-            return Ok(GradeOutcome { passed: false, stdout: String::new(), stderr: format!("Timed out after {}s", timeout_secs) });
+/// Tests that failed in `prelude::within`.
+fn deadlocked_tests(stdout: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut current: Option<&str> = None;
+    for line in stdout.lines() {
+        if let Some(name) = line.strip_prefix("---- ").and_then(|r| r.strip_suffix(" stdout ----")) {
+            current = Some(name);
+        } else if line.contains("async deadlock:") {
+            if let Some(name) = current.take() { out.push(name.to_string()); }
         }
-    };
+    }
+    out
+}
 
-    let output = child.wait_with_output().context("collect output")?;
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+/// Drops cargo's `Finished`/`Running` lines, leaving compiler and test output.
+fn strip_cargo_status(stderr: &str) -> String {
+    stderr.lines()
+        .filter(|l| !["Finished ", "Running ", "Doc-tests "].iter().any(|p| l.trim_start().starts_with(p)))
+        .map(|l| format!("{l}\n"))
+        .collect()
+}
+
+/// Runs `cargo clippy` on the sandbox from the last `grade` and counts the
+/// warnings in the learner's `lib.rs`. `None` if clippy is missing, fails or times out.
+pub fn lint(ex: &Exercise, timeout_secs: u64) -> Result<Option<u32>> {
+    let proj = sandbox_dir(ex)?;
+    let _lock = lock_sandbox(&proj)?;
+    if !proj.join("src").join("lib.rs").exists() { return Ok(None); }
+    let mut child = Command::new("cargo")
+        .args(["clippy", "--quiet", "--message-format", "short"])
+        .current_dir(&proj)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("spawn cargo clippy")?;
+    let stderr = drain(child.stderr.take());
+    let Some(status) = child.wait_timeout(Duration::from_secs(timeout_secs)).context("waiting for clippy")? else {
+        child.kill().ok();
+        child.wait().ok();
+        return Ok(None);
+    };
+    if !status.success() { return Ok(None); }
+    Ok(Some(count_warnings(&stderr.join().unwrap_or_default())))
+}
 
-    Ok(GradeOutcome { passed: status.success(), stdout, stderr })
+/// `src/lib.rs:3:9: warning: ...` lines from `--message-format short`.
+fn count_warnings(stderr: &str) -> u32 {
+    stderr.lines().filter(|l| l.starts_with("src/lib.rs:") && l.contains(": warning:")).count() as u32
 }
 
+/// `prelude::*` for lesson tests: deterministic-time helpers for async lessons.
+const PRELUDE: &str = include_str!("sandbox/prelude.rs");
+
 fn write_cargo_toml(root: &Path) -> Result<()> {
     let cargo = r#"[package]
 name = "exercise_sandbox"
@@ -71,21 +287,69 @@ edition = "2021"
 
 [dependencies]
 thiserror = "1"
-tokio = { version = "1", features = ["rt-multi-thread","macros","sync"] }
+tokio = { version = "1", features = ["rt-multi-thread","macros","sync","time","test-util"] }
 "#;
-    fs::write(root.join("Cargo.toml"), cargo)?;
+    // Rewritten when it changes so older sandboxes pick up new dependencies.
+    let path = root.join("Cargo.toml");
+    if fs::read_to_string(&path).ok().as_deref() != Some(cargo) { fs::write(&path, cargo)?; }
+    fs::create_dir_all(root.join("tests").join("prelude"))?;
+    let prelude = root.join("tests").join("prelude").join("mod.rs");
+    if fs::read_to_string(&prelude).ok().as_deref() != Some(PRELUDE) { fs::write(&prelude, PRELUDE)?; }
     Ok(())
 }
 
-fn sandbox_dir_for(ex: &Exercise) -> Result<std::path::PathBuf> {
+enum Wait {
+    Exited(ExitStatus),
+    TimedOut,
+    Cancelled,
+}
+
+/// Waits for `child`, killing it on timeout or once `cancel` is set.
+fn wait(child: &mut Child, timeout: Duration, cancel: &AtomicBool) -> Result<Wait> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let step = left.min(Duration::from_millis(100));
+        if let Some(status) = child.wait_timeout(step).context("waiting for test with timeout")? {
+            return Ok(Wait::Exited(status));
+        }
+        let stop = if cancel.load(Ordering::Relaxed) { Wait::Cancelled } else if left.is_zero() { Wait::TimedOut } else { continue };
+        #[cfg(unix)]
+        let _ = Command::new("kill").args(["-KILL", "--", &format!("-{}", child.id())]).stderr(Stdio::null()).status();
+        child.kill().ok();
+        child.wait().ok();
+        return Ok(stop);
+    }
+}
+
+fn cancelled() -> GradeOutcome {
+    GradeOutcome { stderr: "Cancelled".to_string(), ..Default::default() }
+}
+
+/// Exclusive lock on `<sandbox>.lock`, released when the file is dropped.
+/// Blocks while another process or thread grades the same sandbox.
+pub fn lock_sandbox(proj: &Path) -> Result<fs::File> {
+    let mut name = proj.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    let path = proj.with_file_name(name);
+    if let Some(parent) = path.parent() { fs::create_dir_all(parent)?; }
+    let file = fs::OpenOptions::new().create(true).truncate(false).write(true).open(&path)
+        .with_context(|| format!("open {}", path.display()))?;
+    file.lock().with_context(|| format!("lock {}", path.display()))?;
+    Ok(file)
+}
+
+/// The sandbox project `grade` uses for a lesson.
+pub fn sandbox_dir(ex: &Exercise) -> Result<PathBuf> {
     let base = util::data_dir()?.join("sandboxes");
     fs::create_dir_all(&base).ok();
-    Ok(base.join(ex.meta.id.replace('/', "_")))
+    Ok(base.join(exercise::dir_name(&ex.meta.id)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::tempdir;
 
     fn make_ex(tmp: &std::path::Path, code: &str, tests: &str) -> Exercise {
         let root = tmp.join("lesson");
@@ -131,4 +395,23 @@ difficulty = "beginner"
         let out = grade(&ex, &user, 10).unwrap();
         assert!(!out.passed);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn classifies_hangs() {
+        let listed = "fast: test\nstuck: test\nsrc/lib.rs - f (line 3): test\n\n3 tests, 0 benchmarks\n";
+        let stdout = "running 2 tests\ntest fast ... ok\ntest stuck ... ";
+        assert_eq!(unfinished_tests(listed, stdout), ["stuck", "src/lib.rs - f (line 3)"]);
+
+        let stdout = "failures:\n\n---- fan_in stdout ----\n\nthread 'fan_in' panicked at tests/prelude/mod.rs:15:19:\nasync deadlock: not finished after 5s\n\n---- sums stdout ----\nassertion failed\n";
+        assert_eq!(deadlocked_tests(stdout), ["fan_in"]);
+        assert_eq!(Hang::Deadlock { tests: vec!["fan_in".into()] }.to_string(), "Deadlock: every task was waiting in fan_in");
+        assert_eq!(strip_cargo_status("    Finished `test` profile\n     Running tests/exercise.rs (target/x)\nerror: test failed\n"), "error: test failed\n");
+    }
+
+    #[test]
+    fn counts_clippy_warnings() {
+        let stderr = "src/lib.rs:1:5: warning: unneeded `return` statement\nsrc/lib.rs:4:1: warning: this function has too many arguments\nwarning: `exercise_sandbox` (lib) generated 2 warnings\n";
+        assert_eq!(count_warnings(stderr), 2);
+        assert_eq!(count_warnings(""), 0);
+    }
+}
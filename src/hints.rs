@@ -0,0 +1,129 @@
+//! Tiered hints: reveal order and per-lesson reveal tracking. Their point
+//! cost is `ScoringConfig::hint_cost`.
+
+use crate::{
+    exercise::ExerciseMeta,
+    persistence::{self, Progress},
+};
+use anyhow::Result;
+
+pub fn revealed_count(meta: &ExerciseMeta, prog: &Progress) -> usize {
+    let n = prog.exercises.get(&meta.id).map(|e| e.hints_revealed).unwrap_or(0) as usize;
+    n.min(meta.hints.len())
+}
+
+/// Hints the learner has already paid for.
+pub fn revealed<'a>(meta: &'a ExerciseMeta, prog: &Progress) -> &'a [String] {
+    &meta.hints[..revealed_count(meta, prog)]
+}
+
+/// Reveals the next tier (if any remain) and records it. Returns how many
+/// tiers are now revealed.
+pub fn reveal_next(meta: &ExerciseMeta) -> Result<usize> {
+    crate::exam::ensure_unlocked("Hints")?;
+    let total = meta.hints.len() as u32;
+    persistence::update(|prog| {
+        let entry = prog.exercises.entry(meta.id.clone()).or_default();
+        if entry.hints_revealed < total { entry.hints_revealed += 1; }
+        entry.hints_revealed.min(total) as usize
+    })
+}
+
+/// Hints from `[[contextual_hints]]` whose compiler error code or test name
+/// appears in the grader output, in lesson order.
+pub fn contextual<'a>(meta: &'a ExerciseMeta, stdout: &str, stderr: &str) -> Vec<&'a str> {
+    let codes = error_codes(stderr);
+    let failing = failing_tests(stdout, stderr);
+    meta.contextual_hints
+        .iter()
+        .filter(|h| {
+            h.error.as_ref().is_some_and(|c| codes.contains(c))
+                || h.test.as_ref().is_some_and(|t| failing.iter().any(|f| f == t || f.ends_with(&format!("::{t}"))))
+        })
+        .map(|h| h.text.as_str())
+        .collect()
+}
+
+/// Distinct `E0000`-style error codes in compiler output.
+pub fn error_codes(stderr: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let bytes = stderr.as_bytes();
+    let mut i = 0;
+    while i + 5 <= bytes.len() {
+        if bytes[i] == b'E' && bytes[i + 1].is_ascii_digit() && bytes[i + 2].is_ascii_digit()
+            && bytes[i + 3].is_ascii_digit() && bytes[i + 4].is_ascii_digit()
+        {
+            let code = &stderr[i..i + 5];
+            if !out.contains(&code.to_string()) {
+                out.push(code.to_string());
+            }
+            i += 5;
+            continue;
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Names of failing tests reported by `cargo test` (`---- name stdout ----`,
+/// `test name ... FAILED`, the `failures:` list and panic lines).
+pub fn failing_tests(stdout: &str, stderr: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut push = |name: &str| {
+        let name = name.trim();
+        if !name.is_empty() && !name.contains(' ') && !out.iter().any(|n| n == name) { out.push(name.to_string()); }
+    };
+    let mut in_failures = false;
+    for line in stdout.lines() {
+        if let Some(rest) = line.strip_prefix("---- ").and_then(|r| r.strip_suffix(" stdout ----")) { push(rest); }
+        if let Some(rest) = line.strip_prefix("test ").and_then(|r| r.strip_suffix(" ... FAILED")) { push(rest); }
+        if line.trim_end() == "failures:" { in_failures = true; continue; }
+        if in_failures {
+            if line.starts_with("    ") { push(line); } else if !line.trim().is_empty() { in_failures = false; }
+        }
+    }
+    for line in stderr.lines() {
+        if let Some(rest) = line.strip_prefix("thread '") {
+            if line.contains("panicked at") {
+                if let Some(end) = rest.find('\'') { push(&rest[..end]); }
+            }
+        }
+    }
+    out.retain(|n| n != "main");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_revealed_hints() {
+        let meta: ExerciseMeta = serde_json::from_value(serde_json::json!({
+            "id": "intro/x", "title": "x", "difficulty": "intermediate", "hint": null, "timeout_secs": null,
+            "hints": ["a", "b", "c"]
+        })).unwrap();
+        let mut prog = Progress::default();
+        assert!(revealed(&meta, &prog).is_empty());
+        prog.exercises.entry("intro/x".into()).or_default().hints_revealed = 7;
+        assert_eq!(revealed(&meta, &prog).len(), 3);
+    }
+
+    #[test]
+    fn matches_failures_to_hints() {
+        let meta: ExerciseMeta = serde_json::from_value(serde_json::json!({
+            "id": "intro/x", "title": "x", "difficulty": "beginner", "hint": null, "timeout_secs": null,
+            "contextual_hints": [
+                { "error": "E0382", "text": "moved" },
+                { "test": "handles_empty", "text": "empty" },
+                { "test": "other", "text": "never" }
+            ]
+        })).unwrap();
+        let stdout = "\nfailures:\n    handles_empty\n\ntest result: FAILED. 1 passed; 1 failed\n";
+        assert_eq!(failing_tests(stdout, ""), vec!["handles_empty".to_string()]);
+        assert_eq!(contextual(&meta, stdout, ""), vec!["empty"]);
+        let stderr = "error[E0382]: borrow of moved value: `s`";
+        assert_eq!(contextual(&meta, "", stderr), vec!["moved"]);
+        assert_eq!(failing_tests("", "thread 'ok' (13362) panicked at tests/exercise.rs:2:18:"), vec!["ok".to_string()]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,112 @@
+//! Per-lesson solution history: snapshots of the working file, diffs and restore.
+
+use crate::storage::{Snapshot, Storage};
+use anyhow::{Context, Result};
+use std::{fs, path::Path};
+
+/// Saves the current contents of `file` as a snapshot. Returns `None` when
+/// there is no working file to save.
+pub fn record(storage: &Storage, user_id: Option<i64>, lesson_id: &str, attempt_id: Option<i64>, passed: Option<bool>, timestamp: i64, file: &Path) -> Result<Option<i64>> {
+    let Ok(source) = fs::read_to_string(file) else { return Ok(None) };
+    Ok(Some(storage.record_snapshot(user_id, lesson_id, attempt_id, passed, timestamp, &source)?))
+}
+
+pub fn list(storage: &Storage, user_id: Option<i64>, lesson_id: &str) -> Result<Vec<Snapshot>> {
+    storage.snapshots_for_lesson(user_id, lesson_id)
+}
+
+fn find(storage: &Storage, user_id: Option<i64>, lesson_id: &str, id: i64) -> Result<Snapshot> {
+    storage
+        .snapshot(id)?
+        .filter(|s| s.lesson_id == lesson_id && s.user_id == user_id)
+        .with_context(|| format!("Snapshot #{} not found for {}", id, lesson_id))
+}
+
+/// Diff between snapshot `a` and snapshot `b`, or the current working file when `b` is `None`.
+pub fn diff(storage: &Storage, user_id: Option<i64>, lesson_id: &str, a: i64, b: Option<i64>, file: &Path) -> Result<String> {
+    let old = find(storage, user_id, lesson_id, a)?;
+    let (new_label, new_src) = match b {
+        Some(b) => (format!("#{}", b), find(storage, user_id, lesson_id, b)?.source),
+        None => ("working copy".to_string(), fs::read_to_string(file).unwrap_or_default()),
+    };
+    Ok(format!("--- #{}\n+++ {}\n{}", a, new_label, render_diff(&old.source, &new_src)))
+}
+
+/// Replaces the working file with snapshot `id`, snapshotting the current file first so the restore can be undone.
+pub fn restore(storage: &Storage, user_id: Option<i64>, lesson_id: &str, id: i64, file: &Path, now: i64) -> Result<()> {
+    let snap = find(storage, user_id, lesson_id, id)?;
+    record(storage, user_id, lesson_id, None, None, now, file)?;
+    crate::util::write_atomic(file, snap.source.as_bytes())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Line diff based on the longest common subsequence.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] { out.push(DiffLine::Same(a[i])); i += 1; j += 1; }
+        else if lcs[i + 1][j] >= lcs[i][j + 1] { out.push(DiffLine::Removed(a[i])); i += 1; }
+        else { out.push(DiffLine::Added(b[j])); j += 1; }
+    }
+    out.extend(a[i..].iter().map(|l| DiffLine::Removed(l)));
+    out.extend(b[j..].iter().map(|l| DiffLine::Added(l)));
+    out
+}
+
+pub fn render_diff(old: &str, new: &str) -> String {
+    let mut s = String::new();
+    for l in diff_lines(old, new) {
+        match l {
+            DiffLine::Same(t) => { s.push_str("  "); s.push_str(t); }
+            DiffLine::Removed(t) => { s.push_str("- "); s.push_str(t); }
+            DiffLine::Added(t) => { s.push_str("+ "); s.push_str(t); }
+        }
+        s.push('\n');
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn diffs_lines() {
+        let d = diff_lines("a\nb\nc\n", "a\nc\nd\n");
+        assert_eq!(d, vec![DiffLine::Same("a"), DiffLine::Removed("b"), DiffLine::Same("c"), DiffLine::Added("d")]);
+    }
+
+    #[test]
+    fn restore_keeps_current_copy() {
+        let db = Storage::open_in_memory().unwrap();
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("lib.rs");
+        fs::write(&file, "fn v1() {}\n").unwrap();
+        let first = record(&db, None, "intro/x", None, Some(false), 1, &file).unwrap().unwrap();
+        fs::write(&file, "fn v2() {}\n").unwrap();
+
+        restore(&db, None, "intro/x", first, &file, 2).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "fn v1() {}\n");
+        let snaps = list(&db, None, "intro/x").unwrap();
+        assert_eq!(snaps.len(), 2);
+        assert_eq!(snaps[1].source, "fn v2() {}\n");
+        assert!(snaps[1].passed.is_none());
+        assert!(restore(&db, Some(7), "intro/x", first, &file, 3).is_err());
+    }
+}
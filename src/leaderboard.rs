@@ -0,0 +1,64 @@
+//! Leaderboard over the SQLite attempts table: standings per player and the
+//! fastest time per lesson, all-time or within a window.
+
+use crate::storage::{LessonRecord, Standing, Storage};
+use anyhow::Result;
+
+const DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone)]
+pub struct Board {
+    /// Start of the window; 0 for all time.
+    pub since: i64,
+    pub standings: Vec<Standing>,
+    pub fastest: Vec<LessonRecord>,
+}
+
+/// Start of a window: `all`, `week` (since Monday, UTC), `today`, `<n>d`
+/// for the last n days, or a `YYYY-MM-DD` date.
+pub fn parse_since(s: &str, now: i64) -> Result<i64> {
+    let today = now.div_euclid(DAY);
+    let day = match s.trim() {
+        "all" => return Ok(0),
+        "today" => today,
+        // 1970-01-01 was a Thursday.
+        "week" => today - (today + 3).rem_euclid(7),
+        other => match other.strip_suffix('d').and_then(|n| n.parse::<i64>().ok()) {
+            Some(n) => return Ok(now - n * DAY),
+            None => return crate::util::parse_date(other)
+                .ok_or_else(|| anyhow::anyhow!("Unknown window '{}': use all, week, today, <n>d or YYYY-MM-DD", other)),
+        },
+    };
+    Ok(day * DAY)
+}
+
+pub fn load(storage: &Storage, lesson_id: Option<&str>, since: i64) -> Result<Board> {
+    Ok(Board { since, standings: storage.standings(since, lesson_id)?, fastest: storage.fastest_times(since, lesson_id)? })
+}
+
+/// `1m05s` / `2h07m`-style duration for tables.
+pub fn format_secs(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows() {
+        let now = 1_792_368_000 + 3600; // Monday 2026-10-19, 01:00 UTC
+        assert_eq!(parse_since("all", now).unwrap(), 0);
+        assert_eq!(parse_since("week", now).unwrap(), 1_792_368_000);
+        assert_eq!(parse_since("week", now + 6 * DAY).unwrap(), 1_792_368_000);
+        assert_eq!(parse_since("7d", now).unwrap(), now - 7 * DAY);
+        assert_eq!(parse_since("2026-10-01", now).unwrap(), 1_792_368_000 - 18 * DAY);
+        assert!(parse_since("soon", now).is_err());
+        assert_eq!(format_secs(65), "1m05s");
+        assert_eq!(format_secs(7620), "2h07m");
+    }
+}
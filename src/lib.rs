@@ -1,9 +1,26 @@
 //! Library crate exposing the learning engine, models, grader, and persistence.
 
+pub mod achievements;
+pub mod bundle;
+pub mod classroom;
+pub mod coverage;
+pub mod daily;
 pub mod engine;
+pub mod exam;
 pub mod exercise;
 pub mod grader;
+pub mod hints;
+pub mod history;
+pub mod leaderboard;
+pub mod miri;
+pub mod mutants;
+pub mod packs;
 pub mod persistence;
 pub mod util;
 pub mod quiz;
+pub mod reverify;
+pub mod review;
+pub mod scheduler;
+pub mod scoring;
+pub mod server;
 pub mod storage;
\ No newline at end of file
@@ -1,14 +1,11 @@
 //! CLI entry for the Rust learning game.
 
-mod engine;
-mod exercise;
-mod grader;
-mod persistence;
-mod util;
-
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use engine::Command as EngineCommand;
+use rust_game::engine::{self, Command as EngineCommand, ExamCommand, InstructorOptions, PackCommand};
+use rust_game::exam::ExamSpec;
+use rust_game::storage::Storage;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "rust-game")]
@@ -17,6 +14,14 @@ struct Cli {
     #[arg(long)]
     lessons: Option<String>,
 
+    /// Base data dir (default: $RUSTLEARN_HOME, ~/.rustlearn or $XDG_DATA_HOME/rustlearn)
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
+
+    /// Named profile with its own progress, work copies and sandboxes
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -26,29 +31,227 @@ pub enum Commands {
     List,
     Start { id: String },
     Open { id: String },
-    Check { id: String, #[arg(long)] timeout: Option<u64> },
+    Check {
+        id: String,
+        #[arg(long)]
+        timeout: Option<u64>,
+        /// After a pass, report line coverage of lib.rs (needs llvm-tools)
+        #[arg(long)]
+        coverage: bool,
+    },
     Hint { id: String },
     Progress,
     Reset { id: String },
-    CheckAll,
+    /// Grade every lesson; Ctrl-C cancels the remaining checks
+    CheckAll {
+        /// Lessons graded at the same time
+        #[arg(long, short, default_value_t = 1)]
+        jobs: usize,
+        /// Cancel the remaining checks after the first failure
+        #[arg(long)]
+        fail_fast: bool,
+        #[arg(long)]
+        timeout: Option<u64>,
+    },
     Validate,
     Solution { id: String },
+    Profiles,
+    /// Write progress, working copies and attempts to a bundle file
+    Export { file: PathBuf },
+    /// Merge a bundle produced by `export` into this profile
+    Import { file: PathBuf },
+    /// List saved snapshots of a lesson's working file
+    History { id: String },
+    /// Diff snapshot A against snapshot B (default: the current working file)
+    Diff { id: String, a: i64, b: Option<i64> },
+    /// Replace the working file with an earlier snapshot
+    Restore { id: String, snapshot: i64 },
+    /// Take a lesson's quiz in the terminal
+    Quiz {
+        id: String,
+        /// Answer every question without prompting: choices and lines are 1-based, `1,3` for multi-select, `\n` for line breaks
+        #[arg(long, num_args = 1..)]
+        answers: Option<Vec<String>>,
+    },
+    /// Review what is due (spaced repetition), or re-solve a completed lesson from its starter
+    Review { id: Option<String> },
+    /// Today's challenge and your streak
+    Daily,
+    /// Achievements, unlocked and still locked
+    Achievements,
+    /// Recompute lesson points under the current scoring formula
+    Rescore {
+        /// Show what would change without saving
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Players ranked by points from recorded attempts
+    Leaderboard {
+        /// Only attempts at this lesson
+        #[arg(long)]
+        lesson: Option<String>,
+        /// Window: all, week (since Monday, UTC), today, <n>d or YYYY-MM-DD
+        #[arg(long, default_value = "all")]
+        since: String,
+    },
+    /// Web frontend and JSON API for many learners on one machine
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+        /// Use 0.0.0.0 to accept connections from other machines
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Checks graded at the same time
+        #[arg(long, default_value_t = 2)]
+        workers: usize,
+        /// Checks that may wait for a worker before new ones are refused
+        #[arg(long, default_value_t = 16)]
+        queue: usize,
+        /// Test timeout in seconds for every check (default: the lesson's, or 15)
+        #[arg(long)]
+        timeout: Option<u64>,
+    },
+    /// Cohort overview: learners × lessons from exported bundles or a shared database
+    Instructor {
+        /// Progress bundles exported by learners (`rust-game export`)
+        bundles: Vec<PathBuf>,
+        /// Shared SQLite file (`app.db`); every user in it is a learner
+        #[arg(long)]
+        db: Option<PathBuf>,
+        /// Write one row per learner and lesson to this CSV file
+        #[arg(long)]
+        csv: Option<PathBuf>,
+        /// Checks without a pass before a learner counts as stuck
+        #[arg(long, default_value_t = rust_game::classroom::STUCK_ATTEMPTS)]
+        stuck_attempts: u32,
+        /// Percent of learners who started a lesson that must be stuck to flag it
+        #[arg(long, default_value_t = 30)]
+        stuck_share: u32,
+    },
+    /// Timed exam over a set of lessons, graded at the end
+    Exam {
+        #[command(subcommand)]
+        action: ExamAction,
+    },
+    /// Check saved solutions against lessons whose tests changed after you solved them
+    Reverify {
+        /// Only this lesson, even if it hasn't changed
+        id: Option<String>,
+        #[arg(long)]
+        timeout: Option<u64>,
+    },
+    /// Install, list and remove lesson packs
+    Pack {
+        #[command(subcommand)]
+        action: PackAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PackAction {
+    /// Install or upgrade a pack from a directory or .tar/.tar.gz archive with a pack.toml
+    Install {
+        source: PathBuf,
+        /// Install even if the pack needs a newer Rust than rustc
+        #[arg(long)]
+        force: bool,
+    },
+    /// Installed packs with version, author and lessons done
+    List,
+    /// Uninstall a pack; progress on its lessons is kept
+    Remove { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum ExamAction {
+    /// Start an exam; without --lessons, picks by --tag/--difficulty
+    Start {
+        #[arg(long)]
+        count: Option<usize>,
+        #[arg(long)]
+        tag: Option<String>,
+        #[arg(long)]
+        difficulty: Option<String>,
+        /// Comma-separated lesson ids
+        #[arg(long, value_delimiter = ',')]
+        lessons: Vec<String>,
+        #[arg(long, default_value_t = 60)]
+        minutes: u64,
+        /// Seed for picking --count lessons (default: the start time)
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Time left and exam files; grades the exam once time is up
+    Status,
+    /// Grade the exam now
+    Submit {
+        #[arg(long)]
+        json: Option<PathBuf>,
+        #[arg(long)]
+        html: Option<PathBuf>,
+    },
+    /// Show or export the last exam report
+    Report {
+        #[arg(long)]
+        json: Option<PathBuf>,
+        #[arg(long)]
+        html: Option<PathBuf>,
+    },
+    /// Stop the exam without grading
+    Abort,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    rust_game::util::set_data_dir(cli.data_dir);
+    rust_game::util::set_profile(cli.profile)?;
     let lessons_root = cli.lessons.unwrap_or_else(|| "lessons".to_string());
     let cmd = match cli.command {
         Commands::List => EngineCommand::List,
         Commands::Start { id } => EngineCommand::Start { id },
         Commands::Open { id } => EngineCommand::Open { id },
-        Commands::Check { id, timeout } => EngineCommand::Check { id, timeout },
+        Commands::Check { id, timeout, coverage } => EngineCommand::Check { id, timeout, coverage },
         Commands::Hint { id } => EngineCommand::Hint { id },
         Commands::Progress => EngineCommand::Progress,
         Commands::Reset { id } => EngineCommand::Reset { id },
-        Commands::CheckAll => EngineCommand::CheckAll,
+        Commands::CheckAll { jobs, fail_fast, timeout } => EngineCommand::CheckAll { jobs, fail_fast, timeout },
         Commands::Validate => EngineCommand::Validate,
         Commands::Solution { id } => EngineCommand::Solution { id },
+        Commands::Profiles => EngineCommand::Profiles,
+        Commands::Export { file } => EngineCommand::Export { file },
+        Commands::Import { file } => EngineCommand::Import { file },
+        Commands::History { id } => EngineCommand::History { id },
+        Commands::Diff { id, a, b } => EngineCommand::Diff { id, a, b },
+        Commands::Restore { id, snapshot } => EngineCommand::Restore { id, snapshot },
+        Commands::Quiz { id, answers } => EngineCommand::Quiz { id, answers },
+        Commands::Review { id } => EngineCommand::Review { id },
+        Commands::Daily => EngineCommand::Daily,
+        Commands::Achievements => EngineCommand::Achievements,
+        Commands::Rescore { dry_run } => EngineCommand::Rescore { dry_run },
+        Commands::Leaderboard { lesson, since } => EngineCommand::Leaderboard { lesson, since },
+        Commands::Serve { port, host, workers, queue, timeout } => EngineCommand::Serve { host, port, workers, queue, timeout },
+        Commands::Instructor { bundles, db, csv, stuck_attempts, stuck_share } => {
+            EngineCommand::Instructor(InstructorOptions { bundles, db, csv, stuck_attempts, stuck_share })
+        }
+        Commands::Exam { action } => EngineCommand::Exam(match action {
+            ExamAction::Start { count, tag, difficulty, lessons, minutes, seed } => {
+                let seed = seed.or_else(|| std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_secs()));
+                ExamCommand::Start(ExamSpec { lessons, tag, difficulty, count, minutes, seed })
+            }
+            ExamAction::Status => ExamCommand::Status,
+            ExamAction::Submit { json, html } => ExamCommand::Submit { json, html },
+            ExamAction::Report { json, html } => ExamCommand::Report { json, html },
+            ExamAction::Abort => ExamCommand::Abort,
+        }),
+        Commands::Reverify { id, timeout } => EngineCommand::Reverify { id, timeout },
+        Commands::Pack { action } => EngineCommand::Pack(match action {
+            PackAction::Install { source, force } => PackCommand::Install { source, force },
+            PackAction::List => PackCommand::List,
+            PackAction::Remove { name } => PackCommand::Remove { name },
+        }),
     };
-    engine::run(lessons_root, cmd)
+    let storage = Storage::open_default()?;
+    let res = engine::run(&storage, lessons_root, cmd);
+    for r in rust_game::persistence::take_recoveries() { eprintln!("warning: {r}"); }
+    res
 }
\ No newline at end of file
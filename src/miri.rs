@@ -0,0 +1,92 @@
+//! Optional `cargo miri test` for lessons on `unsafe` code: finding Miri,
+//! and turning its undefined-behavior errors into structured reports.
+
+use serde::Serialize;
+use std::{fmt, process::{Command, Stdio}, sync::OnceLock};
+
+/// Shown when a lesson asks for Miri and it is not installed.
+pub const UNAVAILABLE: &str = "Miri is not installed, so undefined behavior was not checked \
+    (rustup +nightly component add miri)";
+
+/// `cargo miri` on the default toolchain, else on nightly. `None` without Miri.
+pub fn command() -> Option<&'static [&'static str]> {
+    static CMD: OnceLock<Option<&'static [&'static str]>> = OnceLock::new();
+    *CMD.get_or_init(|| {
+        [&["cargo", "miri"][..], &["cargo", "+nightly", "miri"][..]].into_iter().find(|c| {
+            Command::new(c[0]).args(&c[1..]).arg("--version")
+                .stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null())
+                .status().is_ok_and(|s| s.success())
+        })
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UbReport {
+    /// `undefined behavior`, `data race` or `memory leak`.
+    pub kind: String,
+    pub message: String,
+    /// `src/lib.rs:12:9` in the learner's file, when Miri points at it.
+    pub location: Option<String>,
+    /// The test that was running.
+    pub test: Option<String>,
+}
+
+impl fmt::Display for UbReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(t) = &self.test { write!(f, " in test `{}`", t)?; }
+        if let Some(l) = &self.location { write!(f, " at {}", l)?; }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Errors from `cargo miri test` output. The test is the last one started,
+/// since Miri stops the run at the first error.
+pub fn parse(stdout: &str, stderr: &str) -> Vec<UbReport> {
+    let test = stdout.lines().rev()
+        .find_map(|l| l.strip_prefix("test ").and_then(|r| r.trim_end().strip_suffix(" ...")))
+        .map(str::to_string);
+    let mut out: Vec<UbReport> = Vec::new();
+    let mut awaiting_location = false;
+    for line in stderr.lines() {
+        let (kind, message) = if let Some(m) = line.strip_prefix("error: Undefined Behavior: ") {
+            (if m.starts_with("Data race") { "data race" } else { "undefined behavior" }, m)
+        } else if let Some(m) = line.strip_prefix("error: memory leaked: ") {
+            ("memory leak", m)
+        } else {
+            if let Some(loc) = line.trim_start().strip_prefix("--> ").filter(|_| awaiting_location) {
+                if let Some(r) = out.last_mut() { r.location = Some(loc.trim().to_string()); }
+                awaiting_location = false;
+            }
+            continue;
+        };
+        out.push(UbReport { kind: kind.to_string(), message: message.trim().to_string(), location: None, test: test.clone() });
+        awaiting_location = true;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_miri_errors() {
+        let stdout = "running 2 tests\ntest splits_in_half ... ok\ntest reads_after_free ... \n";
+        let stderr = "\
+error: Undefined Behavior: memory access failed: alloc1234 has been freed, so this pointer is dangling
+  --> src/lib.rs:10:14
+   |
+10 |     unsafe { *p }
+   |              ^^ memory access failed
+   = help: this indicates a bug in the program
+error: memory leaked: alloc99 (Rust heap, size: 4, align: 4), allocated here:
+  --> src/lib.rs:3:5
+";
+        let r = parse(stdout, stderr);
+        assert_eq!(r.len(), 2);
+        assert_eq!(r[0].to_string(), "undefined behavior in test `reads_after_free` at src/lib.rs:10:14: memory access failed: alloc1234 has been freed, so this pointer is dangling");
+        assert_eq!((r[1].kind.as_str(), r[1].location.as_deref()), ("memory leak", Some("src/lib.rs:3:5")));
+        assert!(parse("", "error: could not compile").is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+//! Test-writing lessons ship a correct `reference.rs` and buggy copies of it
+//! under `mutants/`. The learner's tests pass the lesson when they pass on
+//! the reference and fail on every mutant.
+
+use crate::exercise::Exercise;
+use anyhow::{Context, Result};
+use std::{fs, path::PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct Mutant {
+    /// File stem, such as `no_century_rule`.
+    pub name: String,
+    /// The mutant's first line when it is a `//` comment.
+    pub description: Option<String>,
+    pub path: PathBuf,
+}
+
+pub fn reference(ex: &Exercise) -> PathBuf {
+    ex.root.join("reference.rs")
+}
+
+/// `mutants/*.rs`, sorted by name.
+pub fn load(ex: &Exercise) -> Result<Vec<Mutant>> {
+    let dir = ex.root.join("mutants");
+    if !dir.exists() { return Ok(Vec::new()); }
+    let mut out = Vec::new();
+    for entry in fs::read_dir(&dir).with_context(|| format!("read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|e| e != "rs") { continue; }
+        let src = fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        out.push(Mutant { name, description: description(&src), path });
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(out)
+}
+
+fn description(src: &str) -> Option<String> {
+    let first = src.lines().next()?.trim();
+    let text = first.strip_prefix("//!").or_else(|| first.strip_prefix("//"))?.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_mutants_with_descriptions() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("lesson");
+        fs::create_dir_all(root.join("mutants")).unwrap();
+        fs::write(root.join("mutants").join("b_flipped.rs"), "// Flips the comparison\npub fn f() {}\n").unwrap();
+        fs::write(root.join("mutants").join("a_plain.rs"), "pub fn f() {}\n").unwrap();
+        fs::write(root.join("mutants").join("notes.md"), "ignored").unwrap();
+        let ex = Exercise {
+            meta: serde_json::from_value(serde_json::json!({ "id": "x", "title": "x", "difficulty": "beginner", "hint": null, "timeout_secs": null })).unwrap(),
+            root: root.clone(), starter_rs: PathBuf::new(), tests_rs: PathBuf::new(), solution_rs: None, explanation_md: None,
+        };
+        let ms = load(&ex).unwrap();
+        assert_eq!(ms.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["a_plain", "b_flipped"]);
+        assert_eq!((ms[0].description.as_deref(), ms[1].description.as_deref()), (None, Some("Flips the comparison")));
+        assert_eq!(reference(&ex), root.join("reference.rs"));
+    }
+}
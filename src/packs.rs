@@ -0,0 +1,255 @@
+//! Lesson packs: external courses installed into `<data dir>/packs/<name>/`
+//! from a directory or tar archive with a `pack.toml` manifest. Their lessons
+//! join the catalog as `<name>:<path>`, so progress stays apart from the
+//! built-in lessons and from other packs.
+
+use crate::{exercise, util};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+use walkdir::WalkDir;
+
+pub const MANIFEST: &str = "pack.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The pack id: prefixes its lesson ids and names its directory.
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// Oldest `rustc` the lessons build with, such as `1.75`.
+    #[serde(default)]
+    pub rust_version: Option<String>,
+    /// Lesson directories relative to the manifest, in curriculum order.
+    pub lessons: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pack {
+    pub manifest: Manifest,
+    pub root: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct Installed {
+    pub manifest: Manifest,
+    /// The version this install replaced.
+    pub replaced: Option<String>,
+}
+
+/// Shared by every profile.
+pub fn packs_dir() -> Result<PathBuf> {
+    Ok(util::base_data_dir()?.join("packs"))
+}
+
+/// Installed packs, by name. A pack whose manifest can't be read is skipped with a warning.
+pub fn installed() -> Result<Vec<Pack>> {
+    installed_in(&packs_dir()?)
+}
+
+fn installed_in(dir: &Path) -> Result<Vec<Pack>> {
+    if !dir.exists() { return Ok(Vec::new()); }
+    let mut out = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))? {
+        let root = entry?.path();
+        // Skips installs still being staged in `.tmp*` directories.
+        if root.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.')) || !root.join(MANIFEST).is_file() { continue; }
+        match read_manifest(&root) {
+            Ok(manifest) => out.push(Pack { manifest, root }),
+            Err(e) => warn_broken(&root, &e),
+        }
+    }
+    out.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+    Ok(out)
+}
+
+/// Prints a skipped pack once per process; the GUI loads the catalog every frame.
+pub fn warn_broken(root: &Path, err: &anyhow::Error) {
+    static WARNED: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+    if WARNED.lock().unwrap().get_or_insert_with(HashSet::new).insert(root.to_path_buf()) {
+        eprintln!("warning: skipping pack {} ({err:#}); reinstall or `rust-game pack remove` it", root.display());
+    }
+}
+
+pub fn read_manifest(root: &Path) -> Result<Manifest> {
+    let path = root.join(MANIFEST);
+    let src = fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+    let m: Manifest = toml::from_str(&src).with_context(|| format!("parse {}", path.display()))?;
+    validate_name(&m.name)?;
+    if m.lessons.is_empty() { bail!("{} lists no lessons", path.display()); }
+    for l in &m.lessons {
+        if Path::new(l).is_absolute() || l.split(['/', '\\']).any(|p| p == "..") { bail!("Lesson path '{}' must stay inside the pack", l); }
+    }
+    Ok(m)
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    let ok = name.chars().next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if !ok { bail!("Invalid pack name '{}': use letters, digits, '-' and '_'", name); }
+    Ok(())
+}
+
+/// Installs or upgrades a pack from a directory or a `.tar`/`.tar.gz`
+/// archive. Refuses packs that need a newer Rust unless `force`.
+pub fn install(source: &Path, force: bool) -> Result<Installed> {
+    install_into(&packs_dir()?, source, force)
+}
+
+fn install_into(dir: &Path, source: &Path, force: bool) -> Result<Installed> {
+    let extracted = tempfile::tempdir()?;
+    let root = if source.is_dir() {
+        source.to_path_buf()
+    } else {
+        let status = Command::new("tar").arg("-xf").arg(source).arg("-C").arg(extracted.path()).status()
+            .context("run tar to unpack the pack")?;
+        if !status.success() { bail!("could not unpack {}", source.display()); }
+        manifest_root(extracted.path())?
+    };
+    let manifest = read_manifest(&root)?;
+    if let Some(need) = manifest.rust_version.as_deref().filter(|_| !force) {
+        let have = rustc_version()?;
+        if !version_at_least(&have, need) {
+            bail!("{} {} needs Rust {} but rustc is {}; update with `rustup update` or pass --force", manifest.name, manifest.version, need, have);
+        }
+    }
+
+    fs::create_dir_all(dir)?;
+    // Copy next to the final place, check the lessons load, then swap it in.
+    let staging = tempfile::tempdir_in(dir)?;
+    fs::copy(root.join(MANIFEST), staging.path().join(MANIFEST))?;
+    for lesson in &manifest.lessons {
+        let from = root.join(lesson);
+        if !from.join("exercise.toml").is_file() { bail!("{} lists '{}', which has no exercise.toml", manifest.name, lesson); }
+        copy_dir(&from, &staging.path().join(lesson))?;
+    }
+    exercise::load_dir(staging.path(), Some(&manifest.name)).with_context(|| format!("load the lessons of {}", manifest.name))?;
+
+    let dest = dir.join(&manifest.name);
+    let replaced = dest.is_dir().then(|| read_manifest(&dest).ok().map(|m| m.version)).flatten();
+    if dest.exists() { fs::remove_dir_all(&dest).with_context(|| format!("remove {}", dest.display()))?; }
+    fs::rename(staging.keep(), &dest).with_context(|| format!("install into {}", dest.display()))?;
+    Ok(Installed { manifest, replaced })
+}
+
+/// Deletes an installed pack, even one whose manifest is broken (then `None`).
+/// Progress on its lessons stays, ready for a reinstall.
+pub fn remove(name: &str) -> Result<Option<Manifest>> {
+    remove_from(&packs_dir()?, name)
+}
+
+fn remove_from(dir: &Path, name: &str) -> Result<Option<Manifest>> {
+    validate_name(name)?;
+    let dest = dir.join(name);
+    if !dest.is_dir() { bail!("Pack '{}' is not installed", name); }
+    let manifest = read_manifest(&dest).ok();
+    fs::remove_dir_all(&dest).with_context(|| format!("remove {}", dest.display()))?;
+    Ok(manifest)
+}
+
+/// An archive holds the manifest at its top, or inside one top-level directory.
+fn manifest_root(dir: &Path) -> Result<PathBuf> {
+    if dir.join(MANIFEST).is_file() { return Ok(dir.to_path_buf()); }
+    let subdirs: Vec<PathBuf> = fs::read_dir(dir)?.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()).collect();
+    match subdirs.as_slice() {
+        [one] if one.join(MANIFEST).is_file() => Ok(one.clone()),
+        _ => bail!("no {} in the archive", MANIFEST),
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in WalkDir::new(from).into_iter().filter_map(|e| e.ok()) {
+        let rel = entry.path().strip_prefix(from).unwrap_or(entry.path());
+        let target = to.join(rel);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_file() {
+            fs::copy(entry.path(), &target).with_context(|| format!("copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// `1.75.0` from `rustc --version`.
+pub fn rustc_version() -> Result<String> {
+    let out = Command::new("rustc").arg("--version").output().context("run rustc --version")?;
+    let text = String::from_utf8_lossy(&out.stdout);
+    text.split_whitespace().nth(1).map(str::to_string).context("unexpected rustc --version output")
+}
+
+/// Compares dotted numbers, ignoring suffixes such as `-nightly`; missing parts count as 0.
+pub fn version_at_least(have: &str, need: &str) -> bool {
+    let parts = |v: &str| -> Vec<u32> {
+        v.split(['-', '+']).next().unwrap_or("").split('.').map(|p| p.parse().unwrap_or(0)).collect()
+    };
+    let (mut h, mut n) = (parts(have), parts(need));
+    let len = h.len().max(n.len());
+    h.resize(len, 0);
+    n.resize(len, 0);
+    h >= n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_manifests_and_compares_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(MANIFEST), "name = \"async-extra\"\nversion = \"1.2.0\"\nauthor = \"Ferris\"\nrust_version = \"1.75\"\nlessons = [\"advanced/select\"]\n").unwrap();
+        let m = read_manifest(dir.path()).unwrap();
+        assert_eq!((m.name.as_str(), m.rust_version.as_deref(), m.lessons.len()), ("async-extra", Some("1.75"), 1));
+
+        fs::write(dir.path().join(MANIFEST), "name = \"../evil\"\nversion = \"1\"\nlessons = [\"a\"]\n").unwrap();
+        assert!(read_manifest(dir.path()).is_err());
+        fs::write(dir.path().join(MANIFEST), "name = \"ok\"\nversion = \"1\"\nlessons = [\"../../outside\"]\n").unwrap();
+        assert!(read_manifest(dir.path()).is_err());
+
+        assert!(version_at_least("1.80.1", "1.75"));
+        assert!(version_at_least("1.75.0-nightly", "1.75"));
+        assert!(!version_at_least("1.74.1", "1.75.0"));
+        assert!(!version_at_least("1.9.0", "1.10"));
+    }
+
+    #[test]
+    fn installs_lists_upgrades_and_removes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (src, packs) = (tmp.path().join("src"), tmp.path().join("packs"));
+        let lesson = src.join("basics").join("double");
+        fs::create_dir_all(&lesson).unwrap();
+        fs::write(lesson.join("exercise.toml"), "title = \"Double\"\ndifficulty = \"beginner\"\n").unwrap();
+        fs::write(lesson.join("starter.rs"), "pub fn f(x: i32) -> i32 { x }\n").unwrap();
+        fs::write(lesson.join("tests.rs"), "#[test] fn t() { assert_eq!(crate::f(2), 4); }\n").unwrap();
+        fs::write(src.join("notes.md"), "not listed, not copied").unwrap();
+        let manifest = |v: &str| format!("name = \"arith\"\nversion = \"{v}\"\nlessons = [\"basics/double\"]\n");
+        fs::write(src.join(MANIFEST), manifest("1.0.0")).unwrap();
+
+        let i = install_into(&packs, &src, false).unwrap();
+        assert_eq!((i.manifest.version.as_str(), i.replaced), ("1.0.0", None));
+        let listed = installed_in(&packs).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(!listed[0].root.join("notes.md").exists());
+        let xs = exercise::load_dir(&listed[0].root, Some("arith")).unwrap();
+        assert_eq!(xs[0].meta.id, "arith:basics/double");
+
+        fs::write(src.join(MANIFEST), manifest("1.1.0")).unwrap();
+        assert_eq!(install_into(&packs, &src, false).unwrap().replaced.as_deref(), Some("1.0.0"));
+
+        // A broken pack is skipped, and can still be removed.
+        fs::create_dir_all(packs.join("broken")).unwrap();
+        fs::write(packs.join("broken").join(MANIFEST), "name = ").unwrap();
+        assert_eq!(installed_in(&packs).unwrap().len(), 1);
+        assert!(remove_from(&packs, "broken").unwrap().is_none());
+
+        assert_eq!(remove_from(&packs, "arith").unwrap().map(|m| m.version).as_deref(), Some("1.1.0"));
+        assert!(installed_in(&packs).unwrap().is_empty());
+        assert!(remove_from(&packs, "arith").is_err());
+    }
+}
@@ -1,8 +1,15 @@
-//! Progress tracking in ~/.rustlearn/progress.json
+//! Progress and the local leaderboard, as JSON files in the active profile's
+//! data dir (`util::data_dir`), with rotated backups and a lock per file.
 
 use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, path::PathBuf};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use crate::daily::StreakProgress;
+use crate::quiz::Answer;
+use crate::review::{ReviewCard, ReviewSession};
+use std::{collections::{BTreeMap, HashMap}, fmt, fs, path::{Path, PathBuf}, sync::Mutex};
+
+/// Number of rotated backups kept beside each JSON file (`<file>.bak1` is newest).
+const BACKUPS: usize = 3;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Progress {
@@ -17,12 +24,18 @@ pub struct Progress {
     pub display_name: Option<String>,
     #[serde(default)]
     pub theme: Option<String>,
+    /// Badges from before achievements were data-driven; matched by name.
     #[serde(default)]
     pub badges: Vec<String>,
+    /// Unlocked achievement id -> unlock timestamp.
+    #[serde(default)]
+    pub achievements: BTreeMap<String, i64>,
     #[serde(default)]
     pub current_user_id: Option<i64>,
     #[serde(default)]
     pub current_username: Option<String>,
+    #[serde(default)]
+    pub streak: StreakProgress,
 }
 
 impl Default for Progress {
@@ -35,13 +48,15 @@ impl Default for Progress {
             display_name: Some("Player".to_string()),
             theme: Some("Dark".to_string()),
             badges: Vec::new(),
+            achievements: BTreeMap::new(),
             current_user_id: None,
             current_username: None,
+            streak: StreakProgress::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ExerciseProgress {
     pub attempts: u32,
     pub completed: bool,
@@ -51,6 +66,7 @@ pub struct ExerciseProgress {
     pub first_started_at: Option<i64>,
     #[serde(default)]
     pub last_started_at: Option<i64>,
+    /// When the lesson first passed; later passes keep it.
     #[serde(default)]
     pub completed_at: Option<i64>,
     #[serde(default)]
@@ -61,10 +77,58 @@ pub struct ExerciseProgress {
     pub points_earned: u32,
     #[serde(default)]
     pub feedback_helpful: Option<bool>,
+    #[serde(default)]
+    pub hints_revealed: u32,
+    /// Value of `attempts` when the lesson first passed.
+    #[serde(default)]
+    pub attempts_at_pass: Option<u32>,
+    #[serde(default)]
+    pub solution_viewed: bool,
+    /// `hints_revealed`, `solution_viewed` and the solve time when the lesson
+    /// first passed; later reveals, views and faster re-solves don't change its points.
+    #[serde(default)]
+    pub hints_at_pass: Option<u32>,
+    #[serde(default)]
+    pub solution_viewed_at_pass: Option<bool>,
+    #[serde(default)]
+    pub duration_at_pass: Option<u64>,
+    /// Clippy warnings in the first passing solution, if clippy ran.
+    #[serde(default)]
+    pub lint_warnings: Option<u32>,
+    #[serde(default)]
+    pub quiz: QuizProgress,
+    /// Spaced-repetition schedule, set after the first review.
+    #[serde(default)]
+    pub review: Option<ReviewCard>,
+    #[serde(default)]
+    pub review_session: Option<ReviewSession>,
+    /// `Exercise::content_hash` at the last pass; a different current hash
+    /// means the lesson changed since it was solved.
+    #[serde(default)]
+    pub content_hash: Option<String>,
 }
 
-impl Default for ExerciseProgress {
-    fn default() -> Self { Self { attempts: 0, completed: false, quiz_completed: false, first_started_at: None, last_started_at: None, completed_at: None, best_duration_secs: None, last_duration_secs: None, points_earned: 0, feedback_helpful: None } }
+/// Quiz answers and scores for one lesson. `answers` holds the in-progress
+/// (or last submitted) answer per question index.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuizProgress {
+    #[serde(default)]
+    pub answers: BTreeMap<usize, Answer>,
+    #[serde(default)]
+    pub submitted: bool,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub total: u32,
+    #[serde(default)]
+    pub last_score: Option<u32>,
+    #[serde(default)]
+    pub best_score: Option<u32>,
+    #[serde(default)]
+    pub last_submitted_at: Option<i64>,
+    /// Review schedule per question index.
+    #[serde(default)]
+    pub review: BTreeMap<usize, ReviewCard>,
 }
 
 pub fn progress_path() -> Result<PathBuf> {
@@ -75,17 +139,118 @@ pub fn progress_path() -> Result<PathBuf> {
 
 pub fn load() -> Result<Progress> {
     let p = progress_path()?;
-    if !p.exists() { return Ok(Progress::default()); }
-    let data = fs::read_to_string(p)?;
-    let prog: Progress = serde_json::from_str(&data).context("parse progress")?;
-    Ok(prog)
+    Ok(load_json(&p, "progress")?.unwrap_or_default())
 }
 
 pub fn save(progress: &Progress) -> Result<()> {
     let p = progress_path()?;
-    let data = serde_json::to_string_pretty(progress)?;
-    fs::write(p, data)?;
-    Ok(())
+    let _lock = crate::util::lock_file(&p)?;
+    save_json(&p, progress)
+}
+
+/// Loads, mutates and saves progress while holding the file lock, so a
+/// concurrent CLI and GUI cannot drop each other's changes.
+pub fn update<T>(f: impl FnOnce(&mut Progress) -> T) -> Result<T> {
+    update_at(&progress_path()?, f)
+}
+
+/// `load` for a progress file elsewhere, e.g. one of the server's per-user files.
+pub fn load_at(path: &Path) -> Result<Progress> {
+    Ok(load_json(path, "progress")?.unwrap_or_default())
+}
+
+/// `update` for a progress file elsewhere.
+pub fn update_at<T>(path: &Path, f: impl FnOnce(&mut Progress) -> T) -> Result<T> {
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+    let _lock = crate::util::lock_file(path)?;
+    let mut prog: Progress = load_json_locked(path, "progress")?.unwrap_or_default();
+    let out = f(&mut prog);
+    save_json(path, &prog)?;
+    Ok(out)
+}
+
+/// A damaged file that was replaced by its newest backup that still parses.
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    pub path: PathBuf,
+    pub backup: PathBuf,
+    pub error: String,
+}
+
+impl fmt::Display for Recovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is damaged ({}); restored from {}", self.path.display(), self.error, self.backup.display())
+    }
+}
+
+static RECOVERED: Mutex<Vec<Recovery>> = Mutex::new(Vec::new());
+
+/// Files restored from a backup since the last call, for the CLI, GUI or
+/// server to report in their own way.
+pub fn take_recoveries() -> Vec<Recovery> {
+    std::mem::take(&mut *RECOVERED.lock().unwrap())
+}
+
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".bak{n}"));
+    PathBuf::from(name)
+}
+
+fn keep_corrupt(path: &Path) {
+    let mut corrupt = path.as_os_str().to_owned();
+    corrupt.push(".corrupt");
+    let _ = fs::copy(path, PathBuf::from(corrupt));
+}
+
+/// Reads `path` without holding its lock. Only a damaged file takes the
+/// lock, to recover it with `load_json_locked`.
+fn load_json<T: DeserializeOwned>(path: &Path, what: &str) -> Result<Option<T>> {
+    if !path.exists() { return Ok(None); }
+    let data = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    if let Ok(v) = serde_json::from_str(&data) { return Ok(Some(v)); }
+    let _lock = crate::util::lock_file(path)?;
+    load_json_locked(path, what)
+}
+
+/// Reads `path`, falling back to the newest backup that still parses. A
+/// recovered backup is written back in place and the damaged file is kept
+/// as `<file>.corrupt` for inspection. Callers hold the lock.
+fn load_json_locked<T: DeserializeOwned>(path: &Path, what: &str) -> Result<Option<T>> {
+    if !path.exists() { return Ok(None); }
+    let data = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    let err = match serde_json::from_str(&data) {
+        Ok(v) => return Ok(Some(v)),
+        Err(e) => e,
+    };
+    for n in 1..=BACKUPS {
+        let bak = backup_path(path, n);
+        let Ok(data) = fs::read_to_string(&bak) else { continue };
+        if let Ok(v) = serde_json::from_str::<T>(&data) {
+            keep_corrupt(path);
+            crate::util::write_atomic(path, data.as_bytes())?;
+            RECOVERED.lock().unwrap().push(Recovery { path: path.to_path_buf(), backup: bak, error: err.to_string() });
+            return Ok(Some(v));
+        }
+    }
+    Err(err).with_context(|| format!("parse {what} at {} (no usable backup)", path.display()))
+}
+
+/// Rotates backups, then atomically replaces `path`. Callers hold the lock.
+fn save_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+    let data = serde_json::to_string_pretty(value)?;
+    if path.exists() {
+        if serde_json::from_str::<serde_json::Value>(&fs::read_to_string(path).unwrap_or_default()).is_ok() {
+            for n in (1..BACKUPS).rev() {
+                let from = backup_path(path, n);
+                if from.exists() { let _ = fs::rename(&from, backup_path(path, n + 1)); }
+            }
+            let _ = fs::copy(path, backup_path(path, 1));
+        } else {
+            keep_corrupt(path);
+        }
+    }
+    crate::util::write_atomic(path, data.as_bytes())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -106,22 +271,53 @@ pub fn leaderboard_path() -> Result<PathBuf> {
 
 pub fn load_leaderboard() -> Result<Vec<LeaderboardEntry>> {
     let p = leaderboard_path()?;
-    if !p.exists() { return Ok(Vec::new()); }
-    let data = fs::read_to_string(p)?;
-    let entries: Vec<LeaderboardEntry> = serde_json::from_str(&data).context("parse leaderboard")?;
-    Ok(entries)
+    Ok(load_json(&p, "leaderboard")?.unwrap_or_default())
 }
 
 pub fn save_leaderboard(entries: &[LeaderboardEntry]) -> Result<()> {
     let p = leaderboard_path()?;
-    let data = serde_json::to_string_pretty(entries)?;
-    fs::write(p, data)?;
-    Ok(())
+    let _lock = crate::util::lock_file(&p)?;
+    save_json(&p, entries)
 }
 
 pub fn add_leaderboard_entry(entry: LeaderboardEntry) -> Result<()> {
-    let mut entries = load_leaderboard().unwrap_or_default();
+    let p = leaderboard_path()?;
+    let _lock = crate::util::lock_file(&p)?;
+    let mut entries: Vec<LeaderboardEntry> = load_json_locked(&p, "leaderboard").ok().flatten().unwrap_or_default();
     entries.push(entry);
     if entries.len() > 2000 { let drop_n = entries.len() - 2000; entries.drain(0..drop_n); }
-    save_leaderboard(&entries)
+    save_json(&p, &entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn rotates_backups_and_recovers() {
+        let dir = tempdir().unwrap();
+        let p = dir.path().join("progress.json");
+        for pts in [1, 2, 3] {
+            let prog = Progress { total_points: pts, ..Progress::default() };
+            save_json(&p, &prog).unwrap();
+        }
+        assert!(backup_path(&p, 2).exists());
+
+        fs::write(&p, "{ \"exercises\": {").unwrap();
+        let back: Progress = load_json(&p, "progress").unwrap().unwrap();
+        assert_eq!(back.total_points, 2);
+        let again: Progress = serde_json::from_str(&fs::read_to_string(&p).unwrap()).unwrap();
+        assert_eq!(again.total_points, 2);
+        assert!(dir.path().join("progress.json.corrupt").exists());
+        assert!(take_recoveries().iter().any(|r| r.path == p && r.backup == backup_path(&p, 1)));
+    }
+
+    #[test]
+    fn errors_without_usable_backup() {
+        let dir = tempdir().unwrap();
+        let p = dir.path().join("leaderboard.json");
+        fs::write(&p, "not json").unwrap();
+        assert!(load_json::<Vec<LeaderboardEntry>>(&p, "leaderboard").is_err());
+    }
 }
\ No newline at end of file
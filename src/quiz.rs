@@ -1,27 +1,364 @@
-//! Simple lesson quiz loader
+//! Lesson quiz loader and grading
 
-use anyhow::{Context, Result};
-use serde::Deserialize;
-use std::{fs, path::Path};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
 
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct QuizConfig {
     pub title: String,
     #[serde(default)]
     pub questions: Vec<Question>,
 }
 
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionKind {
+    /// Pick one of `options`; answer in `answer_index`.
+    #[default]
+    Single,
+    /// Pick every correct option; answers in `answer_indices`.
+    Multi,
+    /// Answer in `answer`.
+    TrueFalse,
+    /// Type the missing word; any of `accepted` is right.
+    FillBlank,
+    /// Type what `code` prints; any of `accepted` is right.
+    Output,
+    /// Pick the line of `code` that fails to compile; 1-based `answer_line`.
+    CompileError,
+}
+
+/// One question. Only the answer field matching `kind` is used.
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Question {
+    #[serde(default)]
+    pub kind: QuestionKind,
     pub prompt: String,
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
     pub options: Vec<String>,
-    pub answer_index: usize,
+    #[serde(default)]
+    pub answer_index: Option<usize>,
+    #[serde(default)]
+    pub answer_indices: Vec<usize>,
+    #[serde(default)]
+    pub answer: Option<bool>,
+    #[serde(default)]
+    pub accepted: Vec<String>,
+    #[serde(default)]
+    pub answer_line: Option<usize>,
+    /// Shown after the question is graded, right or wrong.
+    #[serde(default)]
+    pub explanation: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Answer {
+    Choice(usize),
+    Choices(Vec<usize>),
+    Bool(bool),
+    Text(String),
+    Line(usize),
+}
+
+impl Question {
+    pub fn is_correct(&self, answer: &Answer) -> bool {
+        match (self.kind, answer) {
+            (QuestionKind::Single, Answer::Choice(i)) => self.answer_index == Some(*i),
+            (QuestionKind::Multi, Answer::Choices(xs)) => {
+                let mut got = xs.clone();
+                got.sort_unstable();
+                got.dedup();
+                let mut want = self.answer_indices.clone();
+                want.sort_unstable();
+                want.dedup();
+                got == want
+            }
+            (QuestionKind::TrueFalse, Answer::Bool(b)) => self.answer == Some(*b),
+            (QuestionKind::FillBlank | QuestionKind::Output, Answer::Text(t)) => {
+                let t = normalize(t);
+                self.accepted.iter().any(|a| normalize(a) == t)
+            }
+            (QuestionKind::CompileError, Answer::Line(l)) => self.answer_line == Some(*l),
+            _ => false,
+        }
+    }
+
+    /// Parses a typed answer. Choices and lines are 1-based, `multi` choices are
+    /// comma-separated, and a literal `\n` in text stands for a line break.
+    pub fn parse_answer(&self, input: &str) -> Result<Answer> {
+        let input = input.trim();
+        let pick = |s: &str, max: usize| -> Result<usize> {
+            let n: usize = s.trim().parse().with_context(|| format!("'{}' is not a number", s.trim()))?;
+            if n == 0 || n > max { bail!("Pick a number from 1 to {}", max); }
+            Ok(n)
+        };
+        Ok(match self.kind {
+            QuestionKind::Single => Answer::Choice(pick(input, self.options.len())? - 1),
+            QuestionKind::Multi => Answer::Choices(
+                input.split(',').filter(|s| !s.trim().is_empty()).map(|s| pick(s, self.options.len()).map(|n| n - 1)).collect::<Result<_>>()?,
+            ),
+            QuestionKind::TrueFalse => match input.to_ascii_lowercase().as_str() {
+                "t" | "true" | "y" | "yes" => Answer::Bool(true),
+                "f" | "false" | "n" | "no" => Answer::Bool(false),
+                _ => bail!("Answer true or false"),
+            },
+            QuestionKind::FillBlank | QuestionKind::Output => Answer::Text(input.replace("\\n", "\n")),
+            QuestionKind::CompileError => Answer::Line(pick(input, self.code_lines().len())?),
+        })
+    }
+
+    /// The correct answer in the same form `parse_answer` accepts, for feedback.
+    pub fn expected(&self) -> String {
+        match self.kind {
+            QuestionKind::Single => self.answer_index.and_then(|i| self.options.get(i)).cloned().unwrap_or_default(),
+            QuestionKind::Multi => self.answer_indices.iter().filter_map(|i| self.options.get(*i).cloned()).collect::<Vec<_>>().join(", "),
+            QuestionKind::TrueFalse => self.answer.map(|b| b.to_string()).unwrap_or_default(),
+            QuestionKind::FillBlank | QuestionKind::Output => self.accepted.first().cloned().unwrap_or_default(),
+            QuestionKind::CompileError => self.answer_line.map(|l| format!("line {}", l)).unwrap_or_default(),
+        }
+    }
+
+    /// Lines of `code`, for rendering and for `compile_error` choices.
+    pub fn code_lines(&self) -> Vec<&str> {
+        self.code.as_deref().map(|c| c.trim_end().lines().collect()).unwrap_or_default()
+    }
+}
+
+/// Trims every line and drops trailing blank lines, so `"1\n2 \n"` matches `"1\n2"`.
+fn normalize(s: &str) -> String {
+    s.trim().lines().map(str::trim).collect::<Vec<_>>().join("\n")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuizResult {
+    pub correct: u32,
+    pub total: u32,
+    /// Whether each question (by index) was answered correctly.
+    pub per_question: Vec<bool>,
+}
+
+impl QuizResult {
+    pub fn perfect(&self) -> bool { self.total > 0 && self.correct == self.total }
+}
+
+/// Grades `answers`, keyed by question index. Missing answers count as wrong.
+pub fn grade(quiz: &QuizConfig, answers: &BTreeMap<usize, Answer>) -> QuizResult {
+    let per_question: Vec<bool> = quiz.questions.iter().enumerate()
+        .map(|(i, q)| answers.get(&i).is_some_and(|a| q.is_correct(a)))
+        .collect();
+    QuizResult { correct: per_question.iter().filter(|c| **c).count() as u32, total: quiz.questions.len() as u32, per_question }
+}
+
+/// Grades `answers` and stores them with the score in the lesson's progress.
+/// `quiz_completed` is set once every question has been answered correctly.
+pub fn submit(lesson_id: &str, quiz: &QuizConfig, answers: BTreeMap<usize, Answer>) -> Result<QuizResult> {
+    let result = grade(quiz, &answers);
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+    crate::persistence::update(|prog| {
+        let entry = prog.exercises.entry(lesson_id.to_string()).or_default();
+        let qp = &mut entry.quiz;
+        qp.answers = answers;
+        qp.submitted = true;
+        qp.attempts += 1;
+        qp.total = result.total;
+        qp.last_score = Some(result.correct);
+        qp.best_score = Some(qp.best_score.unwrap_or(0).max(result.correct));
+        qp.last_submitted_at = Some(now);
+        if result.perfect() { entry.quiz_completed = true; }
+    })?;
+    Ok(result)
 }
 
 pub fn load_quiz(dir: &Path) -> Result<Option<QuizConfig>> {
     let p = dir.join("quiz.toml");
     if !p.exists() { return Ok(None); }
     let data = fs::read_to_string(&p).with_context(|| format!("read quiz at {}", p.display()))?;
-    let q: QuizConfig = toml::from_str(&data).context("parse quiz toml")?;
+    let q: QuizConfig = toml::from_str(&data).with_context(|| format!("parse quiz {}", p.display()))?;
     Ok(Some(q))
-}
\ No newline at end of file
+}
+
+/// An authoring problem in a `quiz.toml`, with a 1-based position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuizIssue {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// Parses `src` and checks every question for mistakes that would make it
+/// unanswerable or ambiguous. A parse error is returned as the only issue.
+pub fn check_source(src: &str) -> Vec<QuizIssue> {
+    let quiz: QuizConfig = match toml::from_str(src) {
+        Ok(q) => q,
+        Err(e) => {
+            let (line, column) = e.span().map(|s| line_col(src, s.start)).unwrap_or((1, 1));
+            return vec![QuizIssue { line, column, message: e.message().trim().to_string() }];
+        }
+    };
+    let mut issues = Vec::new();
+    if quiz.title.trim().is_empty() { issues.push(QuizIssue { line: key_line(src, None, "title"), column: 1, message: "empty title".into() }); }
+    if quiz.questions.is_empty() { issues.push(QuizIssue { line: 1, column: 1, message: "quiz has no [[questions]]".into() }); }
+    for (qi, q) in quiz.questions.iter().enumerate() {
+        let mut issue = |key: &str, message: String| {
+            issues.push(QuizIssue { line: key_line(src, Some(qi), key), column: 1, message: format!("Q{}: {}", qi + 1, message) });
+        };
+        if q.prompt.trim().is_empty() { issue("prompt", "empty prompt".into()); }
+        let n = q.options.len();
+        match q.kind {
+            QuestionKind::Single | QuestionKind::Multi => {
+                if n < 2 { issue("options", format!("needs at least 2 options, has {}", n)); }
+                for (i, opt) in q.options.iter().enumerate() {
+                    if opt.trim().is_empty() { issue("options", format!("option {} is empty", i + 1)); }
+                    if let Some(j) = q.options[..i].iter().position(|o| o.trim().eq_ignore_ascii_case(opt.trim())) {
+                        issue("options", format!("option {} duplicates option {} ({:?})", i + 1, j + 1, opt));
+                    }
+                }
+            }
+            _ if n > 0 => issue("options", format!("`options` is not used by {:?} questions", q.kind)),
+            _ => {}
+        }
+        match q.kind {
+            QuestionKind::Single => match q.answer_index {
+                None => issue("prompt", "missing answer_index".into()),
+                Some(i) if i >= n => issue("answer_index", format!("answer_index {} is out of range (0..{})", i, n)),
+                Some(_) => {}
+            },
+            QuestionKind::Multi => {
+                if q.answer_indices.is_empty() { issue("prompt", "missing answer_indices".into()); }
+                for (k, i) in q.answer_indices.iter().enumerate() {
+                    if *i >= n { issue("answer_indices", format!("answer index {} is out of range (0..{})", i, n)); }
+                    if q.answer_indices[..k].contains(i) { issue("answer_indices", format!("answer index {} is listed twice", i)); }
+                }
+            }
+            QuestionKind::TrueFalse => if q.answer.is_none() { issue("prompt", "missing answer = true/false".into()); },
+            QuestionKind::FillBlank | QuestionKind::Output => {
+                if q.accepted.iter().all(|a| a.trim().is_empty()) { issue("prompt", "missing accepted answers".into()); }
+                if q.kind == QuestionKind::Output && q.code.is_none() { issue("prompt", "output question has no code".into()); }
+            }
+            QuestionKind::CompileError => {
+                let lines = q.code_lines().len();
+                if lines == 0 { issue("prompt", "compile_error question has no code".into()); }
+                match q.answer_line {
+                    None => issue("prompt", "missing answer_line".into()),
+                    Some(l) if l == 0 || l > lines => issue("answer_line", format!("answer_line {} is out of range (1..={})", l, lines)),
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Line of `key` inside the `index`-th `[[questions]]` table (or before the
+/// first table when `index` is `None`), falling back to the table header.
+fn key_line(src: &str, index: Option<usize>, key: &str) -> usize {
+    let mut table: Option<usize> = None;
+    let mut header = 1;
+    let mut in_string = false;
+    for (n, line) in src.lines().enumerate() {
+        let t = line.trim();
+        let was_in_string = in_string;
+        // Skip the body of multi-line strings such as `code = """ ... """`.
+        if (t.matches("\"\"\"").count() + t.matches("'''").count()) % 2 == 1 { in_string = !in_string; }
+        if was_in_string { continue; }
+        if t.starts_with('[') {
+            table = Some(table.map_or(0, |i| i + 1));
+            if table == index { header = n + 1; }
+            continue;
+        }
+        let is_key = t.strip_prefix(key).is_some_and(|rest| rest.trim_start().starts_with('='));
+        if table == index && is_key { return n + 1; }
+    }
+    header
+}
+
+fn line_col(src: &str, offset: usize) -> (usize, usize) {
+    let before = &src[..offset.min(src.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grades_every_kind() {
+        let quiz: QuizConfig = toml::from_str(r#"
+title = "All kinds"
+[[questions]]
+prompt = "single"
+options = ["a", "b"]
+answer_index = 1
+[[questions]]
+kind = "multi"
+prompt = "multi"
+options = ["a", "b", "c"]
+answer_indices = [0, 2]
+[[questions]]
+kind = "true_false"
+prompt = "tf"
+answer = false
+[[questions]]
+kind = "fill_blank"
+prompt = "let ___ x = 1;"
+accepted = ["mut"]
+[[questions]]
+kind = "output"
+prompt = "prints?"
+code = "fn main() { println!(\"1\"); println!(\"2\"); }"
+accepted = ["1\n2"]
+[[questions]]
+kind = "compile_error"
+prompt = "which line?"
+code = "let s = String::new();\nlet t = s;\nprintln!(\"{s}\");\n"
+answer_line = 3
+explanation = "s was moved into t"
+"#).unwrap();
+        let mut answers = BTreeMap::new();
+        answers.insert(0, Answer::Choice(1));
+        answers.insert(1, Answer::Choices(vec![2, 0]));
+        answers.insert(2, Answer::Bool(false));
+        answers.insert(3, Answer::Text(" mut ".into()));
+        answers.insert(4, Answer::Text("1 \n2\n".into()));
+        answers.insert(5, Answer::Line(3));
+        assert!(grade(&quiz, &answers).perfect());
+        assert_eq!(quiz.questions[5].code_lines().len(), 3);
+
+        answers.insert(1, Answer::Choices(vec![0]));
+        answers.remove(&5);
+        let r = grade(&quiz, &answers);
+        assert_eq!((r.correct, r.total), (4, 6));
+        assert_eq!(r.per_question, vec![true, false, true, true, true, false]);
+
+        let q = &quiz.questions;
+        assert_eq!(q[0].parse_answer("2").unwrap(), Answer::Choice(1));
+        assert!(q[0].parse_answer("3").is_err());
+        assert_eq!(q[1].parse_answer("1, 3").unwrap(), Answer::Choices(vec![0, 2]));
+        assert_eq!(q[2].parse_answer("F").unwrap(), Answer::Bool(false));
+        assert!(q[4].is_correct(&q[4].parse_answer("1\\n2").unwrap()));
+        assert!(q[5].parse_answer("4").is_err());
+        assert_eq!(q[1].expected(), "a, c");
+    }
+
+    #[test]
+    fn reports_authoring_mistakes() {
+        let src = "title = \"Q\"\n\n[[questions]]\nprompt = \"ok\"\noptions = [\"a\", \"b\"]\nanswer_index = 1\n\n[[questions]]\nprompt = \" \"\noptions = [\"x\", \"X\"]\nanswer_index = 2\n";
+        let issues = check_source(src);
+        let got: Vec<(usize, &str)> = issues.iter().map(|i| (i.line, i.message.as_str())).collect();
+        assert_eq!(got, vec![
+            (9, "Q2: empty prompt"),
+            (10, "Q2: option 2 duplicates option 1 (\"X\")"),
+            (11, "Q2: answer_index 2 is out of range (0..2)"),
+        ]);
+
+        let bad = check_source("title = \"Q\"\n[[questions]]\nprompt = \"p\"\nanswr = 1\n");
+        assert_eq!((bad.len(), bad[0].line, bad[0].column), (1, 4, 1));
+    }
+}
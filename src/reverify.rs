@@ -0,0 +1,80 @@
+//! Lessons updated since they were solved. Each pass records the lesson's
+//! `Exercise::content_hash`; when the tests change afterwards, the saved
+//! solution can be graded again against the new ones.
+
+use crate::{
+    exercise::Exercise,
+    grader::{self, GradeOutcome},
+    persistence::{self, ExerciseProgress, Progress},
+    storage::Storage,
+};
+use anyhow::Result;
+use std::fs;
+
+/// Completed with a recorded hash that no longer matches the lesson. Lessons
+/// solved before hashes were recorded are never flagged.
+pub fn is_updated(ex: &Exercise, entry: Option<&ExerciseProgress>) -> bool {
+    let Some(saved) = entry.filter(|e| e.completed).and_then(|e| e.content_hash.as_deref()) else { return false };
+    ex.content_hash().is_ok_and(|now| now != saved)
+}
+
+pub fn updated<'a>(lessons: &'a [Exercise], prog: &Progress) -> Vec<&'a Exercise> {
+    lessons.iter().filter(|ex| is_updated(ex, prog.exercises.get(&ex.meta.id))).collect()
+}
+
+/// The source that last passed: the newest passing snapshot, else the working copy.
+pub fn saved_solution(storage: &Storage, user_id: Option<i64>, ex: &Exercise) -> Result<Option<String>> {
+    let passing = storage.snapshots_for_lesson(user_id, &ex.meta.id)?.into_iter().rev().find(|s| s.passed == Some(true));
+    if let Some(s) = passing { return Ok(Some(s.source)); }
+    Ok(fs::read_to_string(ex.working_file()?).ok())
+}
+
+#[derive(Debug)]
+pub enum Reverified {
+    /// Still passes; the new hash is recorded and the flag cleared.
+    Passed,
+    /// Fails the new tests. The lesson stays completed and flagged.
+    Failed(GradeOutcome),
+    NoSolution,
+}
+
+/// Grades the saved solution against the lesson as it is now. Neither the
+/// working copy nor points and attempts change.
+pub fn reverify(storage: &Storage, ex: &Exercise, timeout_secs: u64) -> Result<Reverified> {
+    let uid = persistence::load()?.current_user_id;
+    let Some(source) = saved_solution(storage, uid, ex)? else { return Ok(Reverified::NoSolution) };
+    let dir = tempfile::tempdir()?;
+    let file = dir.path().join("lib.rs");
+    fs::write(&file, source)?;
+    let outcome = grader::grade(ex, &file, timeout_secs)?;
+    if !outcome.passed { return Ok(Reverified::Failed(outcome)); }
+    let hash = ex.content_hash()?;
+    persistence::update(|prog| {
+        if let Some(e) = prog.exercises.get_mut(&ex.meta.id) { e.content_hash = Some(hash); }
+    })?;
+    Ok(Reverified::Passed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_lessons_whose_tests_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("intro").join("vars");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("exercise.toml"), "title = \"Vars\"\ndifficulty = \"beginner\"\n").unwrap();
+        fs::write(root.join("starter.rs"), "pub fn answer() -> i32 { 0 }\n").unwrap();
+        fs::write(root.join("tests.rs"), "#[test] fn t() { assert_eq!(crate::answer(), 42); }\n").unwrap();
+        let ex = crate::exercise::load_dir(dir.path(), None).unwrap().remove(0);
+
+        let solved = ExerciseProgress { completed: true, content_hash: Some(ex.content_hash().unwrap()), ..Default::default() };
+        assert!(!is_updated(&ex, Some(&solved)));
+        fs::write(root.join("tests.rs"), "#[test] fn t() { assert_eq!(crate::answer(), 43); }\n").unwrap();
+        assert!(is_updated(&ex, Some(&solved)));
+        assert!(!is_updated(&ex, Some(&ExerciseProgress { completed: true, ..Default::default() })));
+        assert!(!is_updated(&ex, Some(&ExerciseProgress { completed: false, ..solved })));
+        assert!(!is_updated(&ex, None));
+    }
+}
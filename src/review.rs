@@ -0,0 +1,185 @@
+//! Spaced-repetition review (SM-2) over completed lessons and quiz questions.
+
+use crate::{
+    exercise::{self, Exercise},
+    persistence::{self, ExerciseProgress, Progress},
+    quiz,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// SM-2 state for one review item.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewCard {
+    pub ease: f32,
+    pub interval_days: u32,
+    pub repetitions: u32,
+    pub due_at: i64,
+    #[serde(default)]
+    pub last_reviewed_at: Option<i64>,
+}
+
+impl ReviewCard {
+    pub fn new(due_at: i64) -> Self {
+        Self { ease: 2.5, interval_days: 0, repetitions: 0, due_at, last_reviewed_at: None }
+    }
+
+    /// Applies a review graded `quality` (0 = blackout .. 5 = perfect recall).
+    pub fn schedule(&mut self, quality: u8, now: i64) {
+        let q = quality.min(5) as f32;
+        if quality < 3 {
+            self.repetitions = 0;
+            self.interval_days = 1;
+        } else {
+            self.repetitions += 1;
+            self.interval_days = match self.repetitions {
+                1 => 1,
+                2 => 6,
+                _ => (self.interval_days as f32 * self.ease).round() as u32,
+            };
+        }
+        self.ease = (self.ease + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(1.3);
+        self.due_at = now + self.interval_days as i64 * DAY;
+        self.last_reviewed_at = Some(now);
+    }
+}
+
+/// A lesson re-solve that has been started but not yet passed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReviewSession {
+    pub started_at: i64,
+    pub failed_checks: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReviewItem {
+    Lesson { id: String },
+    Question { lesson_id: String, index: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct DueItem {
+    pub item: ReviewItem,
+    pub due_at: i64,
+}
+
+/// The lesson's card, or a first review one day after completion.
+pub fn lesson_card(ep: &ExerciseProgress) -> Option<ReviewCard> {
+    if !ep.completed { return None; }
+    ep.review.clone().or_else(|| Some(ReviewCard::new(ep.completed_at.unwrap_or(0) + DAY)))
+}
+
+/// The question's card. Without one, questions from a submitted quiz are due
+/// a day after submission, or right away if they were answered wrong.
+pub fn question_card(ep: &ExerciseProgress, q: &quiz::Question, index: usize) -> Option<ReviewCard> {
+    if let Some(c) = ep.quiz.review.get(&index) { return Some(c.clone()); }
+    if !ep.quiz.submitted { return None; }
+    let at = ep.quiz.last_submitted_at.unwrap_or(0);
+    let right = ep.quiz.answers.get(&index).is_some_and(|a| q.is_correct(a));
+    Some(ReviewCard::new(if right { at + DAY } else { at }))
+}
+
+/// Items due at `now`, most overdue first. Quizzes that fail to load are skipped.
+pub fn due(lessons: &[Exercise], prog: &Progress, now: i64) -> Vec<DueItem> {
+    let mut out = Vec::new();
+    for ex in lessons {
+        let Some(ep) = prog.exercises.get(&ex.meta.id) else { continue };
+        if let Some(c) = lesson_card(ep) {
+            if c.due_at <= now { out.push(DueItem { item: ReviewItem::Lesson { id: ex.meta.id.clone() }, due_at: c.due_at }); }
+        }
+        let Ok(Some(qz)) = quiz::load_quiz(&ex.root) else { continue };
+        for (i, q) in qz.questions.iter().enumerate() {
+            if let Some(c) = question_card(ep, q, i).filter(|c| c.due_at <= now) {
+                out.push(DueItem { item: ReviewItem::Question { lesson_id: ex.meta.id.clone(), index: i }, due_at: c.due_at });
+            }
+        }
+    }
+    out.sort_by_key(|d| d.due_at);
+    out
+}
+
+/// Records a quiz question review and returns the updated card.
+pub fn answer_question(lesson_id: &str, q: &quiz::Question, index: usize, correct: bool, now: i64) -> Result<ReviewCard> {
+    persistence::update(|prog| {
+        let ep = prog.exercises.entry(lesson_id.to_string()).or_default();
+        let mut card = question_card(ep, q, index).unwrap_or_else(|| ReviewCard::new(now));
+        card.schedule(if correct { 4 } else { 1 }, now);
+        ep.quiz.review.insert(index, card.clone());
+        card
+    })
+}
+
+/// Starts re-solving a completed lesson: the working file is replaced by the
+/// starter (callers snapshot it first) and the next passing check is graded.
+pub fn start_lesson(ex: &Exercise, now: i64) -> Result<()> {
+    let file = ex.working_file()?;
+    if let Some(dir) = file.parent() { std::fs::create_dir_all(dir)?; }
+    let starter = std::fs::read(&ex.starter_rs).with_context(|| format!("read {}", ex.starter_rs.display()))?;
+    crate::util::write_atomic(&file, &starter)?;
+    persistence::update(|prog| {
+        prog.exercises.entry(ex.meta.id.clone()).or_default().review_session = Some(ReviewSession { started_at: now, failed_checks: 0 });
+    })
+}
+
+/// Feeds a check result into an open re-solve session. Returns the new card
+/// once the lesson passes; failures are counted against the quality.
+pub fn on_check(lesson_id: &str, passed: bool, now: i64) -> Result<Option<ReviewCard>> {
+    persistence::update(|prog| {
+        let ep = prog.exercises.get_mut(lesson_id)?;
+        let session = ep.review_session.as_mut()?;
+        if !passed {
+            session.failed_checks += 1;
+            return None;
+        }
+        let quality = match session.failed_checks { 0 => 5, 1 => 4, 2 => 3, _ => 2 };
+        ep.review_session = None;
+        let mut card = lesson_card(ep).unwrap_or_else(|| ReviewCard::new(now));
+        card.schedule(quality, now);
+        ep.review = Some(card.clone());
+        Some(card)
+    })
+}
+
+/// Loads the lessons and progress and returns what is due now.
+pub fn due_now(lessons_root: &str, now: i64) -> Result<(Vec<Exercise>, Vec<DueItem>)> {
+    let lessons = exercise::load_all(lessons_root)?;
+    let prog = persistence::load()?;
+    let items = due(&lessons, &prog, now);
+    Ok((lessons, items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sm2_intervals() {
+        let mut c = ReviewCard::new(0);
+        c.schedule(5, 0);
+        assert_eq!((c.interval_days, c.due_at), (1, DAY));
+        c.schedule(4, DAY);
+        assert_eq!(c.interval_days, 6);
+        c.schedule(4, 7 * DAY);
+        assert_eq!(c.interval_days, 16);
+        c.schedule(1, 23 * DAY);
+        assert_eq!((c.repetitions, c.interval_days), (0, 1));
+        assert!(c.ease < 2.6 && c.ease >= 1.3);
+    }
+
+    #[test]
+    fn completed_lessons_and_missed_questions_come_due() {
+        let q: quiz::QuizConfig = toml::from_str("title = \"t\"\n[[questions]]\nprompt = \"p\"\noptions = [\"a\", \"b\"]\nanswer_index = 0\n").unwrap();
+        let mut ep = ExerciseProgress { completed: true, completed_at: Some(100), ..Default::default() };
+        assert_eq!(lesson_card(&ep).unwrap().due_at, 100 + DAY);
+        assert!(question_card(&ep, &q.questions[0], 0).is_none());
+
+        ep.quiz.submitted = true;
+        ep.quiz.last_submitted_at = Some(200);
+        ep.quiz.answers.insert(0, quiz::Answer::Choice(1));
+        assert_eq!(question_card(&ep, &q.questions[0], 0).unwrap().due_at, 200);
+        ep.quiz.answers.insert(0, quiz::Answer::Choice(0));
+        assert_eq!(question_card(&ep, &q.questions[0], 0).unwrap().due_at, 200 + DAY);
+    }
+}
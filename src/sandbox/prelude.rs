@@ -0,0 +1,24 @@
+//! Helpers for lesson tests, copied into every sandbox. `use prelude::*;`
+//! in a lesson's `tests.rs` brings them in.
+#![allow(dead_code, unused_imports)]
+
+pub use std::time::Duration;
+pub use tokio::time::{advance, sleep, Instant};
+
+/// Awaits `fut`, failing the test as a deadlock if it isn't done within
+/// `limit` of tokio time. Under `#[tokio::test(start_paused = true)]` the
+/// clock only moves once every task is waiting, so a deadlock fails here
+/// at once, and a slow machine can't make a correct solution time out.
+pub async fn within<F: std::future::Future>(limit: Duration, fut: F) -> F::Output {
+    match tokio::time::timeout(limit, fut).await {
+        Ok(out) => out,
+        Err(_) => panic!("async deadlock: not finished after {:?} of tokio time, and every task was waiting", limit),
+    }
+}
+
+/// Yields until spawned tasks have had a chance to run.
+pub async fn settle() {
+    for _ in 0..64 {
+        tokio::task::yield_now().await;
+    }
+}
@@ -0,0 +1,239 @@
+//! Grading scheduler: a fixed pool of workers taking checks from a priority
+//! queue. Queued checks can be cancelled, and so can running ones, which
+//! kills their `cargo test`. `check-all --jobs N` and `serve` grade through it.
+
+use crate::{exercise::Exercise, grader::{self, GradeOutcome}};
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::{BinaryHeap, HashMap},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc, Condvar, Mutex,
+    },
+    thread::JoinHandle,
+};
+
+/// Higher priorities run first; equal ones in submission order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Bulk runs such as `check-all`.
+    Batch,
+    /// A learner waiting on one check.
+    Interactive,
+}
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub ex: Exercise,
+    /// The learner's `lib.rs`.
+    pub source: PathBuf,
+    pub sandbox: PathBuf,
+    pub timeout_secs: u64,
+    pub priority: Priority,
+}
+
+pub enum Outcome {
+    Graded(GradeOutcome),
+    Cancelled,
+    Failed(anyhow::Error),
+}
+
+/// Sent once per submitted job, when it finishes or is cancelled.
+pub struct Done {
+    pub id: JobId,
+    pub lesson_id: String,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("the grading queue is full")]
+pub struct QueueFull;
+
+struct Queued {
+    id: JobId,
+    job: Job,
+    done: Sender<Done>,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool { self.id == other.id }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> { Some(self.cmp(other)) }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.job.priority.cmp(&other.job.priority).then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+#[derive(Default)]
+struct State {
+    queue: BinaryHeap<Queued>,
+    /// Cancel flags of the jobs being graded.
+    running: HashMap<JobId, Arc<AtomicBool>>,
+    next_id: JobId,
+    shutdown: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    ready: Condvar,
+}
+
+/// Cloneable handle for cancelling from another thread, such as a Ctrl-C handler.
+#[derive(Clone)]
+pub struct Canceller(Arc<Shared>);
+
+impl Canceller {
+    pub fn cancel_all(&self) {
+        let mut st = self.0.state.lock().unwrap();
+        for q in std::mem::take(&mut st.queue) {
+            let _ = q.done.send(Done { id: q.id, lesson_id: q.job.ex.meta.id, outcome: Outcome::Cancelled });
+        }
+        for flag in st.running.values() { flag.store(true, Ordering::Relaxed); }
+    }
+}
+
+pub struct Scheduler {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+    max_queued: Option<usize>,
+}
+
+impl Scheduler {
+    pub fn new(workers: usize) -> Self {
+        let shared = Arc::new(Shared::default());
+        let workers = (0..workers).map(|_| {
+            let shared = Arc::clone(&shared);
+            std::thread::spawn(move || work(&shared))
+        }).collect();
+        Self { shared, workers, max_queued: None }
+    }
+
+    /// Refuses submissions while `n` jobs are already waiting for a worker.
+    pub fn max_queued(mut self, n: usize) -> Self {
+        self.max_queued = Some(n);
+        self
+    }
+
+    /// Queues `job`; `done` receives its result.
+    pub fn submit(&self, job: Job, done: Sender<Done>) -> Result<JobId, QueueFull> {
+        let mut st = self.shared.state.lock().unwrap();
+        if self.max_queued.is_some_and(|n| st.queue.len() >= n) { return Err(QueueFull); }
+        let id = st.next_id;
+        st.next_id += 1;
+        st.queue.push(Queued { id, job, done });
+        self.shared.ready.notify_one();
+        Ok(id)
+    }
+
+    /// Drops a queued job or stops a running one. False if it already finished.
+    pub fn cancel(&self, id: JobId) -> bool {
+        let mut st = self.shared.state.lock().unwrap();
+        if let Some(flag) = st.running.get(&id) {
+            flag.store(true, Ordering::Relaxed);
+            return true;
+        }
+        let (hit, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut st.queue).into_iter().partition(|q| q.id == id);
+        st.queue = rest.into();
+        for q in &hit {
+            let _ = q.done.send(Done { id, lesson_id: q.job.ex.meta.id.clone(), outcome: Outcome::Cancelled });
+        }
+        !hit.is_empty()
+    }
+
+    pub fn canceller(&self) -> Canceller {
+        Canceller(Arc::clone(&self.shared))
+    }
+
+    /// Lesson ids waiting for a worker, next first.
+    pub fn pending(&self) -> Vec<(JobId, String)> {
+        let st = self.shared.state.lock().unwrap();
+        let mut q: Vec<&Queued> = st.queue.iter().collect();
+        q.sort_by(|a, b| b.cmp(a));
+        q.into_iter().map(|q| (q.id, q.job.ex.meta.id.clone())).collect()
+    }
+}
+
+impl Drop for Scheduler {
+    /// Cancels whatever is left and waits for the workers to stop.
+    fn drop(&mut self) {
+        self.canceller().cancel_all();
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.ready.notify_all();
+        for w in self.workers.drain(..) { let _ = w.join(); }
+    }
+}
+
+fn work(shared: &Shared) {
+    loop {
+        let (q, cancel) = {
+            let mut st = shared.state.lock().unwrap();
+            loop {
+                if st.shutdown { return; }
+                if let Some(q) = st.queue.pop() {
+                    let cancel = Arc::new(AtomicBool::new(false));
+                    st.running.insert(q.id, Arc::clone(&cancel));
+                    break (q, cancel);
+                }
+                st = shared.ready.wait(st).unwrap();
+            }
+        };
+        let Job { ex, source, sandbox, timeout_secs, .. } = q.job;
+        let result = grader::grade_cancellable(&sandbox, &ex, &source, timeout_secs, &cancel);
+        shared.state.lock().unwrap().running.remove(&q.id);
+        let outcome = match result {
+            _ if cancel.load(Ordering::Relaxed) => Outcome::Cancelled,
+            Ok(o) => Outcome::Graded(o),
+            Err(e) => Outcome::Failed(e),
+        };
+        let _ = q.done.send(Done { id: q.id, lesson_id: ex.meta.id, outcome });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn job(id: &str, priority: Priority) -> Job {
+        let meta = serde_json::from_value(serde_json::json!({
+            "id": id, "title": id, "difficulty": "beginner", "hint": null, "timeout_secs": null
+        })).unwrap();
+        let ex = Exercise { meta, root: PathBuf::new(), starter_rs: PathBuf::new(), tests_rs: PathBuf::new(), solution_rs: None, explanation_md: None };
+        Job { ex, source: PathBuf::new(), sandbox: PathBuf::new(), timeout_secs: 1, priority }
+    }
+
+    #[test]
+    fn orders_by_priority_and_cancels() {
+        // No workers, so jobs stay queued and we can inspect them.
+        let s = Scheduler::new(0).max_queued(3);
+        let (tx, rx) = mpsc::channel();
+        let a = s.submit(job("a", Priority::Batch), tx.clone()).unwrap();
+        s.submit(job("b", Priority::Batch), tx.clone()).unwrap();
+        s.submit(job("c", Priority::Interactive), tx.clone()).unwrap();
+        assert!(s.submit(job("d", Priority::Interactive), tx.clone()).is_err());
+        let order: Vec<String> = s.pending().into_iter().map(|(_, id)| id).collect();
+        assert_eq!(order, ["c", "a", "b"]);
+
+        assert!(s.cancel(a));
+        assert!(!s.cancel(a));
+        let done = rx.try_recv().unwrap();
+        assert!(done.id == a && matches!(done.outcome, Outcome::Cancelled));
+        assert_eq!(s.pending().len(), 2);
+
+        s.canceller().cancel_all();
+        assert_eq!(rx.try_iter().count(), 2);
+        assert!(s.pending().is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+//! Points for a first solve: a formula over difficulty, failed attempts,
+//! solve time, hints, solution views and lint warnings, configured in
+//! `<lessons_root>/scoring.toml`.
+
+use crate::{
+    exercise::{self, ExerciseMeta},
+    persistence::{self, ExerciseProgress, Progress},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoringConfig {
+    /// Base points per difficulty; other difficulties use `advanced`.
+    pub base: BTreeMap<String, u32>,
+    /// Cost of each revealed hint as a percent of base, unless the lesson sets `hint_cost`.
+    pub hint_percent: u32,
+    /// Percent of base lost per failed check before the first pass.
+    pub attempt_penalty_percent: u32,
+    pub max_attempt_penalty_percent: u32,
+    /// Percent of base added when solved within `fast_secs` of `start`.
+    pub fast_bonus_percent: u32,
+    pub fast_secs: u64,
+    /// Percent of base lost when the solution was viewed before passing.
+    pub solution_penalty_percent: u32,
+    /// Percent of base added when `cargo clippy` is clean. 0 skips the lint run.
+    pub lint_bonus_percent: u32,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            base: [("beginner", 10), ("intermediate", 25), ("advanced", 50)].into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+            hint_percent: 20,
+            attempt_penalty_percent: 10,
+            max_attempt_penalty_percent: 50,
+            fast_bonus_percent: 20,
+            fast_secs: 300,
+            solution_penalty_percent: 50,
+            lint_bonus_percent: 10,
+        }
+    }
+}
+
+impl ScoringConfig {
+    pub fn base_points(&self, meta: &ExerciseMeta) -> u32 {
+        self.base.get(&meta.difficulty).or_else(|| self.base.get("advanced")).copied().unwrap_or(50)
+    }
+
+    pub fn hint_cost(&self, meta: &ExerciseMeta) -> u32 {
+        meta.hint_cost.unwrap_or(self.base_points(meta) * self.hint_percent / 100)
+    }
+}
+
+/// `scoring.toml` under the lessons root, or the defaults when it is absent.
+pub fn load(lessons_root: &str) -> Result<ScoringConfig> {
+    let p = Path::new(lessons_root).join("scoring.toml");
+    if !p.exists() { return Ok(ScoringConfig::default()); }
+    let data = fs::read_to_string(&p).with_context(|| format!("read {}", p.display()))?;
+    toml::from_str(&data).with_context(|| format!("parse {}", p.display()))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScoreInputs {
+    pub failed_attempts: u32,
+    pub duration_secs: Option<u64>,
+    pub hints: u32,
+    pub solution_viewed: bool,
+    /// `None` when clippy was not run or is not installed.
+    pub lint_warnings: Option<u32>,
+}
+
+impl ScoreInputs {
+    /// Inputs recorded for a lesson, as they were at the first pass when
+    /// known. Lessons scored before that was recorded use the best solve time.
+    pub fn from_progress(ep: &ExerciseProgress) -> Self {
+        Self {
+            failed_attempts: ep.attempts_at_pass.unwrap_or(ep.attempts).saturating_sub(1),
+            duration_secs: if ep.attempts_at_pass.is_some() { ep.duration_at_pass } else { ep.best_duration_secs },
+            hints: ep.hints_at_pass.unwrap_or(ep.hints_revealed),
+            solution_viewed: ep.solution_viewed_at_pass.unwrap_or(ep.solution_viewed),
+            lint_warnings: ep.lint_warnings,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakdown {
+    pub items: Vec<(String, i64)>,
+    pub total: u32,
+}
+
+impl fmt::Display for Breakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (label, pts) in &self.items {
+            writeln!(f, "  {:<28} {:>+5}", label, pts)?;
+        }
+        write!(f, "  {:<28} {:>5}", "total", self.total)
+    }
+}
+
+pub fn score(cfg: &ScoringConfig, meta: &ExerciseMeta, inp: &ScoreInputs) -> Breakdown {
+    let base = cfg.base_points(meta) as i64;
+    let pct = |p: u32| base * p as i64 / 100;
+    let mut items = vec![(format!("base ({})", meta.difficulty), base)];
+    if inp.failed_attempts > 0 {
+        let p = (inp.failed_attempts * cfg.attempt_penalty_percent).min(cfg.max_attempt_penalty_percent);
+        items.push((format!("{} failed attempt(s)", inp.failed_attempts), -pct(p)));
+    }
+    if inp.hints > 0 {
+        items.push((format!("{} hint(s)", inp.hints), -(cfg.hint_cost(meta) as i64 * inp.hints as i64)));
+    }
+    if inp.solution_viewed && cfg.solution_penalty_percent > 0 {
+        items.push(("solution viewed".to_string(), -pct(cfg.solution_penalty_percent)));
+    }
+    if cfg.fast_bonus_percent > 0 && inp.duration_secs.is_some_and(|d| d <= cfg.fast_secs) {
+        items.push((format!("solved within {}s", cfg.fast_secs), pct(cfg.fast_bonus_percent)));
+    }
+    if cfg.lint_bonus_percent > 0 && inp.lint_warnings == Some(0) {
+        items.push(("clippy clean".to_string(), pct(cfg.lint_bonus_percent)));
+    }
+    let total = items.iter().map(|(_, p)| p).sum::<i64>().max(0) as u32;
+    Breakdown { items, total }
+}
+
+/// Scores a lesson that has just passed, once: records the inputs, adds the
+/// points to the total and returns the breakdown. `None` if it was scored before.
+pub fn award(prog: &mut Progress, meta: &ExerciseMeta, cfg: &ScoringConfig, lint_warnings: Option<u32>) -> Option<Breakdown> {
+    let entry = prog.exercises.entry(meta.id.clone()).or_default();
+    if entry.points_earned > 0 || entry.attempts_at_pass.is_some() { return None; }
+    entry.attempts_at_pass = Some(entry.attempts);
+    entry.hints_at_pass = Some(entry.hints_revealed);
+    entry.solution_viewed_at_pass = Some(entry.solution_viewed);
+    entry.duration_at_pass = entry.last_duration_secs;
+    entry.lint_warnings = lint_warnings;
+    let b = score(cfg, meta, &ScoreInputs::from_progress(entry));
+    entry.points_earned = b.total;
+    prog.total_points = prog.total_points.saturating_add(b.total);
+    Some(b)
+}
+
+/// Whether `award` would score this lesson, so callers can skip the lint run otherwise.
+pub fn needs_award(prog: &Progress, id: &str) -> bool {
+    prog.exercises.get(id).is_none_or(|e| e.points_earned == 0 && e.attempts_at_pass.is_none())
+}
+
+/// A lesson whose points change under the current formula.
+#[derive(Debug, Clone)]
+pub struct Rescored {
+    pub id: String,
+    pub old: u32,
+    pub new: u32,
+}
+
+/// Recomputes the points of every completed lesson from its recorded inputs.
+/// Bonuses outside lessons (streaks, daily) are kept. Writes only if `apply`.
+pub fn rescore(lessons_root: &str, apply: bool) -> Result<Vec<Rescored>> {
+    let cfg = load(lessons_root)?;
+    let lessons: Vec<ExerciseMeta> = exercise::load_all(lessons_root)?.into_iter().map(|e| e.meta).collect();
+    let compute = |prog: &mut Progress| rescore_progress(prog, &lessons, &cfg);
+    if apply { persistence::update(compute) } else { Ok(compute(&mut persistence::load()?)) }
+}
+
+fn rescore_progress(prog: &mut Progress, lessons: &[ExerciseMeta], cfg: &ScoringConfig) -> Vec<Rescored> {
+    let mut out = Vec::new();
+    for meta in lessons {
+        let Some(ep) = prog.exercises.get_mut(&meta.id).filter(|e| e.completed) else { continue };
+        let new = score(cfg, meta, &ScoreInputs::from_progress(ep)).total;
+        let old = ep.points_earned;
+        if new == old { continue; }
+        ep.points_earned = new;
+        prog.total_points = prog.total_points.saturating_sub(old).saturating_add(new);
+        out.push(Rescored { id: meta.id.clone(), old, new });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formula_breakdown() {
+        let meta: ExerciseMeta = serde_json::from_value(serde_json::json!({
+            "id": "intro/x", "title": "x", "difficulty": "advanced", "hint": null, "timeout_secs": null
+        })).unwrap();
+        let cfg = ScoringConfig::default();
+        let quick = ScoreInputs { duration_secs: Some(120), lint_warnings: Some(0), ..Default::default() };
+        let b = score(&cfg, &meta, &quick);
+        assert_eq!(b.total, 50 + 10 + 5);
+        assert_eq!(b.items.len(), 3);
+
+        let slow = ScoreInputs { failed_attempts: 40, duration_secs: Some(3600), hints: 1, solution_viewed: true, lint_warnings: Some(2) };
+        let b = score(&cfg, &meta, &slow);
+        assert_eq!(b.items.iter().map(|(_, p)| *p).collect::<Vec<_>>(), vec![50, -25, -10, -25]);
+        assert_eq!(b.total, 0);
+
+        let cfg: ScoringConfig = toml::from_str("attempt_penalty_percent = 0\n[base]\nadvanced = 100\n").unwrap();
+        assert_eq!(score(&cfg, &meta, &slow).total, 100 - 20 - 50);
+    }
+
+    #[test]
+    fn changes_after_passing_keep_points() {
+        let meta: ExerciseMeta = serde_json::from_value(serde_json::json!({
+            "id": "intro/x", "title": "x", "difficulty": "beginner", "hint": null, "timeout_secs": null
+        })).unwrap();
+        let cfg = ScoringConfig::default();
+        let mut prog = Progress::default();
+        let ep = prog.exercises.entry(meta.id.clone()).or_default();
+        (ep.attempts, ep.hints_revealed, ep.completed, ep.last_duration_secs) = (1, 1, true, Some(900));
+        let total = award(&mut prog, &meta, &cfg, None).unwrap().total;
+        assert_eq!(total, 10 - 2);
+
+        let ep = prog.exercises.get_mut(&meta.id).unwrap();
+        ep.hints_revealed += 1;
+        ep.solution_viewed = true;
+        ep.best_duration_secs = Some(5);
+        assert!(rescore_progress(&mut prog, std::slice::from_ref(&meta), &cfg).is_empty());
+        assert_eq!((prog.total_points, prog.exercises[&meta.id].points_earned), (total, total));
+    }
+}
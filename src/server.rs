@@ -0,0 +1,468 @@
+//! `rust-game serve`: a small HTTP/1.1 JSON API over the engine plus a web
+//! frontend, so one machine can host many learners. Each user gets their own
+//! working files, sandboxes and progress under `<root>/users/<id>/`; checks
+//! go through the grading scheduler with a bounded queue.
+
+use crate::{
+    exercise::{self, Exercise},
+    grader::GradeOutcome,
+    hints, persistence, reverify,
+    scheduler::{self, Priority, Scheduler},
+    scoring,
+    storage::Storage,
+    util,
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const INDEX_HTML: &str = include_str!("web/index.html");
+
+/// Largest request body accepted (sources are well under this).
+const MAX_BODY: usize = 1 << 20;
+
+#[derive(Debug, Clone)]
+pub struct ServeConfig {
+    pub lessons_root: String,
+    /// Per-user data lives under `<root>/users/<id>/`.
+    pub root: PathBuf,
+    /// Grading workers running `cargo test` at the same time.
+    pub workers: usize,
+    /// Checks that may wait for a worker; more are refused with 503.
+    pub queue: usize,
+    /// Overrides each lesson's test timeout; a first compile in a fresh sandbox is slow.
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// From `Authorization: Bearer <token>`.
+    pub token: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn new(method: &str, path: &str, token: Option<&str>, body: Value) -> Self {
+        let body = if body.is_null() { Vec::new() } else { body.to_string().into_bytes() };
+        Self { method: method.to_string(), path: path.to_string(), token: token.map(str::to_string), body }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, v: Value) -> Self {
+        Self { status, content_type: "application/json", body: v.to_string().into_bytes() }
+    }
+
+    fn error(status: u16, msg: impl Into<String>) -> Self {
+        Self::json(status, json!({ "error": msg.into() }))
+    }
+
+    /// The body parsed as JSON (`null` if it is not JSON).
+    pub fn value(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Session {
+    user_id: i64,
+    username: String,
+}
+
+pub struct App {
+    cfg: ServeConfig,
+    storage: Mutex<Storage>,
+    sessions: Mutex<HashMap<String, Session>>,
+    scheduler: Scheduler,
+    /// (user, lesson) checks queued or running; a second one is refused.
+    grading: Mutex<HashSet<(i64, String)>>,
+}
+
+/// Removes a (user, lesson) pair from `App::grading` when the check ends.
+struct GradingSlot<'a>(&'a App, (i64, String));
+
+impl Drop for GradingSlot<'_> {
+    fn drop(&mut self) {
+        self.0.grading.lock().unwrap().remove(&self.1);
+    }
+}
+
+#[derive(Deserialize)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct SourceBody {
+    source: String,
+}
+
+impl App {
+    /// Starts the grading workers. `storage` holds the accounts and attempts.
+    pub fn new(cfg: ServeConfig, storage: Storage) -> Arc<Self> {
+        let scheduler = Scheduler::new(cfg.workers).max_queued(cfg.queue);
+        Arc::new(Self { cfg, storage: Mutex::new(storage), sessions: Mutex::new(HashMap::new()), scheduler, grading: Mutex::new(HashSet::new()) })
+    }
+
+    /// Routes one request. This is the whole API; `serve` only adds HTTP framing,
+    /// so tests can drive it in-process.
+    pub fn handle(&self, req: &Request) -> Response {
+        let res = match (req.method.as_str(), req.path.as_str()) {
+            ("GET", "/") | ("GET", "/index.html") => {
+                return Response { status: 200, content_type: "text/html; charset=utf-8", body: INDEX_HTML.as_bytes().to_vec() };
+            }
+            ("POST", "/api/register") => self.register(req),
+            ("POST", "/api/login") => self.login(req),
+            ("POST", "/api/logout") => {
+                if let Some(t) = &req.token { self.sessions.lock().unwrap().remove(t); }
+                Ok(Response::json(200, json!({ "ok": true })))
+            }
+            ("GET", "/api/lessons") => self.session(req).and_then(|s| self.lessons(&s)),
+            ("GET", "/api/progress") => self.session(req).and_then(|s| self.progress(&s)),
+            (method, path) => match path.strip_prefix("/api/lessons/") {
+                Some(rest) => self.session(req).and_then(|s| self.lesson_route(&s, method, rest, req)),
+                None => Ok(Response::error(404, "Not found")),
+            },
+        };
+        res.unwrap_or_else(|e| match e.downcast::<HttpError>() {
+            Ok(HttpError(status, msg)) => Response::error(status, msg),
+            Err(e) => Response::error(500, format!("{e:#}")),
+        })
+    }
+
+    fn lesson_route(&self, s: &Session, method: &str, rest: &str, req: &Request) -> Result<Response> {
+        let (id, action) = match rest.rsplit_once('/') {
+            Some((id, a @ ("start" | "source" | "check"))) => (id, a),
+            _ => (rest, ""),
+        };
+        let all = exercise::load_all(&self.cfg.lessons_root)?;
+        let ex = all.iter().find(|e| e.meta.id == id).ok_or_else(|| HttpError(404, format!("Exercise '{id}' not found")))?;
+        match (method, action) {
+            ("GET", "") => self.lesson(s, ex),
+            ("POST", "start") => self.start(s, &all, ex),
+            ("PUT", "source") => self.save_source(s, ex, req),
+            ("POST", "check") => self.check(s, ex),
+            _ => Ok(Response::error(405, "Method not allowed")),
+        }
+    }
+
+    fn register(&self, req: &Request) -> Result<Response> {
+        let c: Credentials = parse_body(req)?;
+        if c.username.trim().is_empty() || c.password.len() < 4 {
+            return Ok(Response::error(400, "Pick a username and a password of at least 4 characters"));
+        }
+        let user = match self.storage.lock().unwrap().register_user(c.username.trim(), &c.password) {
+            Ok(u) => u,
+            Err(_) => return Ok(Response::error(409, "That username is taken")),
+        };
+        self.open_session(user.id, user.username)
+    }
+
+    fn login(&self, req: &Request) -> Result<Response> {
+        let c: Credentials = parse_body(req)?;
+        match self.storage.lock().unwrap().authenticate(c.username.trim(), &c.password)? {
+            Some(u) => self.open_session(u.id, u.username),
+            None => Ok(Response::error(401, "Wrong username or password")),
+        }
+    }
+
+    fn open_session(&self, user_id: i64, username: String) -> Result<Response> {
+        let token = new_token()?;
+        self.sessions.lock().unwrap().insert(token.clone(), Session { user_id, username: username.clone() });
+        Ok(Response::json(200, json!({ "token": token, "username": username })))
+    }
+
+    fn session(&self, req: &Request) -> Result<Session> {
+        req.token.as_ref()
+            .and_then(|t| self.sessions.lock().unwrap().get(t).cloned())
+            .ok_or_else(|| HttpError(401, "Log in first".into()).into())
+    }
+
+    fn user_dir(&self, s: &Session) -> PathBuf {
+        self.cfg.root.join("users").join(s.user_id.to_string())
+    }
+
+    fn progress_path(&self, s: &Session) -> PathBuf {
+        self.user_dir(s).join("progress.json")
+    }
+
+    fn working_file(&self, s: &Session, id: &str) -> PathBuf {
+        self.user_dir(s).join("work").join(exercise::dir_name(id)).join("lib.rs")
+    }
+
+    fn lessons(&self, s: &Session) -> Result<Response> {
+        let mut all = exercise::load_all(&self.cfg.lessons_root)?;
+        all.sort_by_key(|e| (e.meta.difficulty_rank(), e.meta.curriculum_rank(), e.meta.id.clone()));
+        let prog = persistence::load_at(&self.progress_path(s))?;
+        let done = |id: &str| prog.exercises.get(id).is_some_and(|e| e.completed);
+        let list: Vec<Value> = all.iter().map(|e| {
+            let ep = prog.exercises.get(&e.meta.id);
+            json!({
+                "id": e.meta.id, "title": e.meta.title, "difficulty": e.meta.difficulty, "tags": e.meta.tags,
+                "completed": done(&e.meta.id), "attempts": ep.map(|p| p.attempts).unwrap_or(0),
+                "unlocked": exercise::unlocked(&all, &e.meta.id, done),
+                "updated": reverify::is_updated(e, ep),
+            })
+        }).collect();
+        Ok(Response::json(200, json!(list)))
+    }
+
+    fn lesson(&self, s: &Session, ex: &Exercise) -> Result<Response> {
+        let file = self.working_file(s, &ex.meta.id);
+        let source = fs::read_to_string(&file).ok();
+        let starter = fs::read_to_string(&ex.starter_rs).with_context(|| format!("read {}", ex.starter_rs.display()))?;
+        Ok(Response::json(200, json!({
+            "id": ex.meta.id, "title": ex.meta.title, "difficulty": ex.meta.difficulty, "tags": ex.meta.tags,
+            "starter": starter, "source": source, "started": source.is_some(),
+        })))
+    }
+
+    fn start(&self, s: &Session, all: &[Exercise], ex: &Exercise) -> Result<Response> {
+        let path = self.progress_path(s);
+        let prog = persistence::load_at(&path)?;
+        if !exercise::unlocked(all, &ex.meta.id, |id| prog.exercises.get(id).is_some_and(|e| e.completed)) {
+            return Ok(Response::error(403, "Locked: complete the previous lesson first"));
+        }
+        let file = self.working_file(s, &ex.meta.id);
+        if !file.exists() {
+            fs::create_dir_all(file.parent().unwrap())?;
+            fs::copy(&ex.starter_rs, &file).with_context(|| format!("copy starter for {}", ex.meta.id))?;
+        }
+        let now = now_secs();
+        persistence::update_at(&path, |prog| {
+            let e = prog.exercises.entry(ex.meta.id.clone()).or_default();
+            e.first_started_at.get_or_insert(now);
+            e.last_started_at = Some(now);
+        })?;
+        Ok(Response::json(200, json!({ "source": fs::read_to_string(&file)? })))
+    }
+
+    fn save_source(&self, s: &Session, ex: &Exercise, req: &Request) -> Result<Response> {
+        let body: SourceBody = parse_body(req)?;
+        let file = self.working_file(s, &ex.meta.id);
+        if !file.exists() { return Ok(Response::error(409, "Start the lesson first")); }
+        util::write_atomic(&file, body.source.as_bytes())?;
+        Ok(Response::json(200, json!({ "ok": true })))
+    }
+
+    fn check(&self, s: &Session, ex: &Exercise) -> Result<Response> {
+        let file = self.working_file(s, &ex.meta.id);
+        if !file.exists() { return Ok(Response::error(409, "Start the lesson first")); }
+        let key = (s.user_id, ex.meta.id.clone());
+        if !self.grading.lock().unwrap().insert(key.clone()) {
+            return Ok(Response::error(409, "This lesson is already being checked"));
+        }
+        let _slot = GradingSlot(self, key);
+        let (reply, rx) = mpsc::channel();
+        let sandbox = self.user_dir(s).join("sandboxes").join(exercise::dir_name(&ex.meta.id));
+        let timeout_secs = self.cfg.timeout_secs.or(ex.meta.timeout_secs).unwrap_or(15);
+        let job = scheduler::Job { ex: ex.clone(), source: file, sandbox, timeout_secs, priority: Priority::Interactive };
+        if self.scheduler.submit(job, reply).is_err() {
+            return Ok(Response::error(503, "The grading queue is full; try again in a moment"));
+        }
+        match rx.recv().context("grading worker stopped")?.outcome {
+            scheduler::Outcome::Graded(outcome) => self.record(s, ex, outcome),
+            scheduler::Outcome::Cancelled => Ok(Response::error(503, "The check was cancelled")),
+            scheduler::Outcome::Failed(e) => Err(e),
+        }
+    }
+
+    /// Updates the user's progress and attempts after a check, like `rust-game check`.
+    fn record(&self, s: &Session, ex: &Exercise, outcome: GradeOutcome) -> Result<Response> {
+        let now = now_secs();
+        let cfg = scoring::load(&self.cfg.lessons_root)?;
+        let content_hash = ex.content_hash().ok();
+        let (duration, breakdown) = persistence::update_at(&self.progress_path(s), |prog| {
+            let e = prog.exercises.entry(ex.meta.id.clone()).or_default();
+            e.attempts += 1;
+            if !outcome.passed { return (None, None); }
+            e.completed = true;
+            e.content_hash = content_hash.clone();
+            e.completed_at.get_or_insert(now);
+            let duration = e.last_started_at.map(|start| (now - start).max(0) as u64);
+            if let Some(d) = duration {
+                e.last_duration_secs = Some(d);
+                e.best_duration_secs = Some(e.best_duration_secs.map_or(d, |b| b.min(d)));
+            }
+            (duration, scoring::award(prog, &ex.meta, &cfg, None))
+        })?;
+        {
+            let storage = self.storage.lock().unwrap();
+            let id = storage.record_attempt(s.user_id, &ex.meta.id, outcome.passed, duration, now, content_hash.as_deref())?;
+            if let Some(b) = &breakdown { storage.set_attempt_points(id, b.total)?; }
+        }
+        let items: Vec<Value> = breakdown.iter().flat_map(|b| &b.items).map(|(label, pts)| json!({ "label": label, "points": pts })).collect();
+        Ok(Response::json(200, json!({
+            "passed": outcome.passed,
+            "stdout": outcome.stdout,
+            "stderr": outcome.stderr,
+            "points": breakdown.as_ref().map(|b| b.total),
+            "breakdown": items,
+            "survivors": outcome.survivors,
+            "ub": outcome.ub,
+            "notes": outcome.notes,
+            "hang": outcome.hang.as_ref().map(|h| h.to_string()),
+            "hints": if outcome.passed { Vec::new() } else { hints::contextual(&ex.meta, &outcome.stdout, &outcome.stderr) },
+        })))
+    }
+
+    fn progress(&self, s: &Session) -> Result<Response> {
+        let prog = persistence::load_at(&self.progress_path(s))?;
+        let lessons: serde_json::Map<String, Value> = prog.exercises.iter().map(|(id, e)| {
+            (id.clone(), json!({
+                "completed": e.completed, "attempts": e.attempts, "points": e.points_earned,
+                "best_duration_secs": e.best_duration_secs,
+            }))
+        }).collect();
+        Ok(Response::json(200, json!({
+            "username": s.username,
+            "total_points": prog.total_points,
+            "completed": prog.exercises.values().filter(|e| e.completed).count(),
+            "lessons": lessons,
+        })))
+    }
+}
+
+/// An error that maps to a specific HTTP status.
+#[derive(Debug, thiserror::Error)]
+#[error("{1}")]
+struct HttpError(u16, String);
+
+fn parse_body<T: serde::de::DeserializeOwned>(req: &Request) -> Result<T> {
+    serde_json::from_slice(&req.body).map_err(|e| HttpError(400, format!("Bad request body: {e}")).into())
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
+}
+
+/// 256 bits from the OS random number generator, hex-encoded.
+fn new_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow::anyhow!("read OS randomness: {e}"))?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Accepts connections on `listener` until the process exits, one thread per connection.
+pub fn serve(app: Arc<App>, listener: TcpListener) -> Result<()> {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let app = Arc::clone(&app);
+        std::thread::spawn(move || {
+            let _ = handle_connection(&app, stream);
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(app: &App, mut stream: TcpStream) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let res = match read_request(&mut BufReader::new(&stream)) {
+        Ok(req) => app.handle(&req),
+        Err(e) => Response::error(400, format!("{e:#}")),
+    };
+    for r in persistence::take_recoveries() { eprintln!("warning: {r}"); }
+    let reason = match res.status {
+        200 => "OK", 400 => "Bad Request", 401 => "Unauthorized", 403 => "Forbidden", 404 => "Not Found",
+        405 => "Method Not Allowed", 409 => "Conflict", 503 => "Service Unavailable", _ => "Internal Server Error",
+    };
+    write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", res.status, reason, res.content_type, res.body.len())?;
+    stream.write_all(&res.body)?;
+    Ok(())
+}
+
+fn read_request(r: &mut impl BufRead) -> Result<Request> {
+    let mut line = String::new();
+    r.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else { anyhow::bail!("malformed request line") };
+    let path = target.split('?').next().unwrap_or(target).to_string();
+    let (mut len, mut token) = (0usize, None);
+    loop {
+        let mut h = String::new();
+        if r.read_line(&mut h)? == 0 || h.trim().is_empty() { break; }
+        let Some((name, value)) = h.split_once(':') else { continue };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => len = value.trim().parse().context("bad Content-Length")?,
+            "authorization" => token = value.trim().strip_prefix("Bearer ").map(str::to_string),
+            _ => {}
+        }
+    }
+    if len > MAX_BODY { anyhow::bail!("request body too large"); }
+    let mut body = vec![0; len];
+    r.read_exact(&mut body)?;
+    Ok(Request { method: method.to_string(), path, token, body })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn lesson(root: &std::path::Path, slug: &str) {
+        let dir = root.join("intro").join(slug);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("exercise.toml"), "title = \"T\"\ndifficulty = \"beginner\"\n").unwrap();
+        fs::write(dir.join("starter.rs"), "pub fn answer() -> i32 { 0 }\n").unwrap();
+        fs::write(dir.join("tests.rs"), "#[test] fn t() { assert_eq!(crate::answer(), 42); }\n").unwrap();
+    }
+
+    #[test]
+    fn api_round_trip_in_process() {
+        let tmp = tempdir().unwrap();
+        let lessons = tmp.path().join("lessons");
+        lesson(&lessons, "variables");
+        lesson(&lessons, "functions");
+        // No workers and no queue: checks are refused, which is what we assert.
+        let cfg = ServeConfig { lessons_root: lessons.display().to_string(), root: tmp.path().join("serve"), workers: 0, queue: 0, timeout_secs: None };
+        let app = App::new(cfg, Storage::open_in_memory().unwrap());
+        let call = |m: &str, p: &str, t: Option<&str>, b: Value| app.handle(&Request::new(m, p, t, b));
+
+        assert_eq!(call("GET", "/api/lessons", None, Value::Null).status, 401);
+        let r = call("POST", "/api/register", None, json!({ "username": "ana", "password": "crab" }));
+        assert_eq!(r.status, 200);
+        let token = r.value()["token"].as_str().unwrap().to_string();
+        let t = Some(token.as_str());
+        assert_eq!(call("POST", "/api/login", None, json!({ "username": "ana", "password": "nope" })).status, 401);
+
+        let list = call("GET", "/api/lessons", t, Value::Null).value();
+        assert_eq!(list[0]["id"], "intro/variables");
+        assert_eq!((list[0]["unlocked"].as_bool(), list[1]["unlocked"].as_bool()), (Some(true), Some(false)));
+        assert_eq!(call("POST", "/api/lessons/intro/functions/start", t, Value::Null).status, 403);
+
+        assert_eq!(call("POST", "/api/lessons/intro/variables/check", t, Value::Null).status, 409);
+        let started = call("POST", "/api/lessons/intro/variables/start", t, Value::Null).value();
+        assert!(started["source"].as_str().unwrap().contains("answer"));
+        let src = "pub fn answer() -> i32 { 42 }\n";
+        assert_eq!(call("PUT", "/api/lessons/intro/variables/source", t, json!({ "source": src })).status, 200);
+        assert_eq!(call("GET", "/api/lessons/intro/variables", t, Value::Null).value()["source"], src);
+        assert_eq!(call("POST", "/api/lessons/intro/variables/check", t, Value::Null).status, 503);
+
+        let prog = call("GET", "/api/progress", t, Value::Null).value();
+        assert_eq!((prog["username"].as_str(), prog["completed"].as_u64()), (Some("ana"), Some(0)));
+        assert_eq!(call("GET", "/api/lessons/intro/nope", t, Value::Null).status, 404);
+
+        let raw = b"PUT /api/x?y=1 HTTP/1.1\r\nAuthorization: Bearer abc\r\nContent-Length: 2\r\n\r\n{}";
+        let req = read_request(&mut &raw[..]).unwrap();
+        assert_eq!((req.method.as_str(), req.path.as_str(), req.token.as_deref(), req.body.len()), ("PUT", "/api/x", Some("abc"), 2));
+    }
+}
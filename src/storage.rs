@@ -1,8 +1,9 @@
 //! SQLite-backed storage for users and attempts
 
-use anyhow::{Result};
+use anyhow::{Context, Result};
 use rusqlite::{params, Connection, OptionalExtension};
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct User {
@@ -13,7 +14,7 @@ pub struct User {
     pub avatar: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Attempt {
     pub id: i64,
     pub user_id: i64,
@@ -21,61 +22,142 @@ pub struct Attempt {
     pub passed: bool,
     pub duration_secs: Option<u64>,
     pub timestamp: i64,
+    /// Points the attempt scored: set on the pass that first solved the lesson.
+    #[serde(default)]
+    pub points: Option<u32>,
+    /// The lesson's `Exercise::content_hash` when it was graded.
+    #[serde(default)]
+    pub content_hash: Option<String>,
+}
+
+/// One player's row on the leaderboard.
+#[derive(Debug, Clone, Serialize)]
+pub struct Standing {
+    pub user_id: i64,
+    pub name: String,
+    pub avatar: Option<String>,
+    pub points: u32,
+    /// Distinct lessons passed.
+    pub completed: u32,
+    pub fastest_secs: Option<u64>,
+    pub last_active: i64,
+}
+
+/// Fastest passing attempt for a lesson.
+#[derive(Debug, Clone, Serialize)]
+pub struct LessonRecord {
+    pub lesson_id: String,
+    pub name: String,
+    pub duration_secs: u64,
+    pub timestamp: i64,
+}
+
+/// Copy of a learner's `lib.rs` taken on Check (`passed` is set) or before it
+/// was reset or overwritten by a restore (`passed` is `None`).
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub lesson_id: String,
+    pub attempt_id: Option<i64>,
+    pub passed: Option<bool>,
+    pub timestamp: i64,
+    pub source: String,
 }
 
-fn db_path() -> Result<PathBuf> {
+const SCHEMA: &str = r#"
+    CREATE TABLE IF NOT EXISTS users (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        username TEXT UNIQUE NOT NULL,
+        password_hash TEXT NOT NULL,
+        display_name TEXT,
+        avatar TEXT
+    );
+    CREATE TABLE IF NOT EXISTS attempts (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id INTEGER NOT NULL,
+        lesson_id TEXT NOT NULL,
+        passed INTEGER NOT NULL,
+        duration_secs INTEGER,
+        timestamp INTEGER NOT NULL,
+        FOREIGN KEY(user_id) REFERENCES users(id)
+    );
+    CREATE TABLE IF NOT EXISTS snapshots (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id INTEGER,
+        lesson_id TEXT NOT NULL,
+        attempt_id INTEGER,
+        passed INTEGER,
+        timestamp INTEGER NOT NULL,
+        source TEXT NOT NULL,
+        FOREIGN KEY(attempt_id) REFERENCES attempts(id) ON DELETE SET NULL
+    );
+    CREATE INDEX IF NOT EXISTS snapshots_lesson ON snapshots(lesson_id, timestamp);
+"#;
+
+pub fn db_path() -> Result<PathBuf> {
     let mut p = crate::persistence::progress_path()?;
     p.pop();
     p.push("app.db");
     Ok(p)
 }
 
-pub fn open() -> Result<Connection> {
-    let path = db_path()?;
-    if let Some(dir) = path.parent() { std::fs::create_dir_all(dir)?; }
-    let conn = Connection::open(path)?;
-    conn.execute_batch(
-        r#"
-        PRAGMA journal_mode = WAL;
-        CREATE TABLE IF NOT EXISTS users (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            username TEXT UNIQUE NOT NULL,
-            password_hash TEXT NOT NULL,
-            display_name TEXT,
-            avatar TEXT
-        );
-        CREATE TABLE IF NOT EXISTS attempts (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            user_id INTEGER NOT NULL,
-            lesson_id TEXT NOT NULL,
-            passed INTEGER NOT NULL,
-            duration_secs INTEGER,
-            timestamp INTEGER NOT NULL,
-            FOREIGN KEY(user_id) REFERENCES users(id)
-        );
-        "#,
-    )?;
-    Ok(conn)
+/// Handle owning a single SQLite connection. Open it once and pass it around
+/// instead of reconnecting for every query.
+pub struct Storage {
+    conn: Connection,
 }
 
-pub fn register_user(username: &str, password: &str) -> Result<User> {
-    let hash = bcrypt::hash(password, bcrypt::DEFAULT_COST)?;
-    let conn = open()?;
-    conn.execute(
-        "INSERT INTO users (username, password_hash) VALUES (?1, ?2)",
-        params![username, hash],
-    )?;
-    let id = conn.last_insert_rowid();
-    Ok(User { id, username: username.to_string(), password_hash: String::from("<redacted>"), display_name: None, avatar: None })
-}
+impl Storage {
+    /// Opens the database in the data dir (`app.db`).
+    pub fn open_default() -> Result<Self> {
+        Self::open(&db_path()?)
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() { std::fs::create_dir_all(dir)?; }
+        let conn = Connection::open(path).with_context(|| format!("open database at {}", path.display()))?;
+        conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+        Self::init(conn)
+    }
+
+    /// Fresh database that lives only as long as the handle; used by tests.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
 
-pub fn authenticate(username: &str, password: &str) -> Result<Option<User>> {
-    let conn = open()?;
-    let row = conn
-        .query_row(
-            "SELECT id, username, password_hash, display_name, avatar FROM users WHERE username = ?1",
-            params![username],
-            |r| {
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA).context("create schema")?;
+        let has_points = conn.prepare("SELECT 1 FROM pragma_table_info('attempts') WHERE name = 'points'")?.exists([])?;
+        if !has_points { conn.execute_batch("ALTER TABLE attempts ADD COLUMN points INTEGER").context("add attempts.points")?; }
+        let has_hash = conn.prepare("SELECT 1 FROM pragma_table_info('attempts') WHERE name = 'content_hash'")?.exists([])?;
+        if !has_hash { conn.execute_batch("ALTER TABLE attempts ADD COLUMN content_hash TEXT").context("add attempts.content_hash")?; }
+        conn.set_prepared_statement_cache_capacity(32);
+        Ok(Self { conn })
+    }
+
+    /// Runs `f` inside a transaction. Every call made on the handle within `f`
+    /// is committed together, or rolled back if `f` returns an error.
+    pub fn transaction<T>(&self, f: impl FnOnce(&Self) -> Result<T>) -> Result<T> {
+        let tx = self.conn.unchecked_transaction()?;
+        let out = f(self)?;
+        tx.commit()?;
+        Ok(out)
+    }
+
+    pub fn register_user(&self, username: &str, password: &str) -> Result<User> {
+        let hash = bcrypt::hash(password, bcrypt::DEFAULT_COST)?;
+        self.conn
+            .prepare_cached("INSERT INTO users (username, password_hash) VALUES (?1, ?2)")?
+            .execute(params![username, hash])?;
+        let id = self.conn.last_insert_rowid();
+        Ok(User { id, username: username.to_string(), password_hash: String::from("<redacted>"), display_name: None, avatar: None })
+    }
+
+    pub fn authenticate(&self, username: &str, password: &str) -> Result<Option<User>> {
+        let row = self.conn
+            .prepare_cached("SELECT id, username, password_hash, display_name, avatar FROM users WHERE username = ?1")?
+            .query_row(params![username], |r| {
                 Ok(User {
                     id: r.get(0)?,
                     username: r.get(1)?,
@@ -83,57 +165,212 @@ pub fn authenticate(username: &str, password: &str) -> Result<Option<User>> {
                     display_name: r.get(3).ok(),
                     avatar: r.get(4).ok(),
                 })
-            },
-        )
-        .optional()?;
-    if let Some(mut u) = row {
-        if bcrypt::verify(password, &u.password_hash).unwrap_or(false) {
-            u.password_hash = String::from("<redacted>");
-            return Ok(Some(u));
+            })
+            .optional()?;
+        if let Some(mut u) = row {
+            if bcrypt::verify(password, &u.password_hash).unwrap_or(false) {
+                u.password_hash = String::from("<redacted>");
+                return Ok(Some(u));
+            }
         }
+        Ok(None)
     }
-    Ok(None)
-}
 
-pub fn upsert_profile(user_id: i64, display_name: Option<String>, avatar: Option<String>) -> Result<()> {
-    let conn = open()?;
-    conn.execute(
-        "UPDATE users SET display_name = ?1, avatar = ?2 WHERE id = ?3",
-        params![display_name, avatar, user_id],
-    )?;
-    Ok(())
-}
+    /// Every registered user, by id. Password hashes are not read.
+    pub fn users(&self) -> Result<Vec<User>> {
+        let mut stmt = self.conn.prepare_cached("SELECT id, username, display_name, avatar FROM users ORDER BY id")?;
+        let rows = stmt.query_map([], |r| {
+            Ok(User { id: r.get(0)?, username: r.get(1)?, password_hash: String::from("<redacted>"), display_name: r.get(2)?, avatar: r.get(3)? })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn upsert_profile(&self, user_id: i64, display_name: Option<String>, avatar: Option<String>) -> Result<()> {
+        self.conn
+            .prepare_cached("UPDATE users SET display_name = ?1, avatar = ?2 WHERE id = ?3")?
+            .execute(params![display_name, avatar, user_id])?;
+        Ok(())
+    }
+
+    /// Returns the new attempt id.
+    pub fn record_attempt(&self, user_id: i64, lesson_id: &str, passed: bool, duration_secs: Option<u64>, timestamp: i64, content_hash: Option<&str>) -> Result<i64> {
+        self.conn
+            .prepare_cached("INSERT INTO attempts (user_id, lesson_id, passed, duration_secs, timestamp, content_hash) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?
+            .execute(params![user_id, lesson_id, if passed {1} else {0}, duration_secs.map(|v| v as i64), timestamp, content_hash])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn set_attempt_points(&self, attempt_id: i64, points: u32) -> Result<()> {
+        self.conn
+            .prepare_cached("UPDATE attempts SET points = ?1 WHERE id = ?2")?
+            .execute(params![points, attempt_id])?;
+        Ok(())
+    }
+
+    pub fn attempts_for_user(&self, user_id: i64) -> Result<Vec<Attempt>> {
+        let mut stmt = self.conn.prepare_cached("SELECT id, user_id, lesson_id, passed, duration_secs, timestamp, points, content_hash FROM attempts WHERE user_id = ?1 ORDER BY timestamp DESC")?;
+        let rows = stmt.query_map(params![user_id], |r| {
+            Ok(Attempt {
+                id: r.get(0)?,
+                user_id: r.get(1)?,
+                lesson_id: r.get(2)?,
+                passed: {
+                    let v: i64 = r.get(3)?;
+                    v != 0
+                },
+                duration_secs: r.get::<_, Option<i64>>(4)?.map(|v| v as u64),
+                timestamp: r.get(5)?,
+                points: r.get(6)?,
+                content_hash: r.get(7)?,
+            })
+        })?;
+        Ok(rows.filter_map(Result::ok).collect())
+    }
+
+    /// Players ranked by points, then lessons passed, then fastest time, over
+    /// attempts at or after `since`, optionally for one lesson only.
+    pub fn standings(&self, since: i64, lesson_id: Option<&str>) -> Result<Vec<Standing>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT u.id, COALESCE(u.display_name, u.username), u.avatar,
+                    COALESCE(SUM(a.points), 0),
+                    COUNT(DISTINCT CASE WHEN a.passed = 1 THEN a.lesson_id END),
+                    MIN(CASE WHEN a.passed = 1 THEN a.duration_secs END),
+                    MAX(a.timestamp)
+             FROM attempts a JOIN users u ON u.id = a.user_id
+             WHERE a.timestamp >= ?1 AND (?2 IS NULL OR a.lesson_id = ?2)
+             GROUP BY u.id
+             ORDER BY 4 DESC, 5 DESC, 6 IS NULL, 6, 2")?;
+        let rows = stmt.query_map(params![since, lesson_id], |r| {
+            Ok(Standing {
+                user_id: r.get(0)?,
+                name: r.get(1)?,
+                avatar: r.get(2)?,
+                points: r.get(3)?,
+                completed: r.get(4)?,
+                fastest_secs: r.get::<_, Option<i64>>(5)?.map(|v| v as u64),
+                last_active: r.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The fastest timed pass of each lesson at or after `since`, ties going to the earlier one.
+    pub fn fastest_times(&self, since: i64, lesson_id: Option<&str>) -> Result<Vec<LessonRecord>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT lesson_id, name, duration_secs, timestamp FROM (
+                SELECT a.lesson_id, COALESCE(u.display_name, u.username) AS name, a.duration_secs, a.timestamp,
+                       ROW_NUMBER() OVER (PARTITION BY a.lesson_id ORDER BY a.duration_secs, a.timestamp) AS rn
+                FROM attempts a JOIN users u ON u.id = a.user_id
+                WHERE a.passed = 1 AND a.duration_secs IS NOT NULL AND a.timestamp >= ?1 AND (?2 IS NULL OR a.lesson_id = ?2))
+             WHERE rn = 1 ORDER BY lesson_id")?;
+        let rows = stmt.query_map(params![since, lesson_id], |r| {
+            Ok(LessonRecord { lesson_id: r.get(0)?, name: r.get(1)?, duration_secs: r.get::<_, i64>(2)? as u64, timestamp: r.get(3)? })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn clear_attempts_for_user(&self, user_id: i64) -> Result<()> {
+        self.conn
+            .prepare_cached("UPDATE snapshots SET attempt_id = NULL WHERE attempt_id IN (SELECT id FROM attempts WHERE user_id = ?1)")?
+            .execute(params![user_id])?;
+        self.conn
+            .prepare_cached("DELETE FROM attempts WHERE user_id = ?1")?
+            .execute(params![user_id])?;
+        Ok(())
+    }
 
-pub fn record_attempt(user_id: i64, lesson_id: &str, passed: bool, duration_secs: Option<u64>, timestamp: i64) -> Result<()> {
-    let conn = open()?;
-    conn.execute(
-        "INSERT INTO attempts (user_id, lesson_id, passed, duration_secs, timestamp) VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![user_id, lesson_id, if passed {1} else {0}, duration_secs.map(|v| v as i64), timestamp],
-    )?;
-    Ok(())
+    pub fn record_snapshot(&self, user_id: Option<i64>, lesson_id: &str, attempt_id: Option<i64>, passed: Option<bool>, timestamp: i64, source: &str) -> Result<i64> {
+        self.conn
+            .prepare_cached("INSERT INTO snapshots (user_id, lesson_id, attempt_id, passed, timestamp, source) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?
+            .execute(params![user_id, lesson_id, attempt_id, passed, timestamp, source])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Snapshots of one lesson for `user_id` (or taken while logged out), oldest first.
+    pub fn snapshots_for_lesson(&self, user_id: Option<i64>, lesson_id: &str) -> Result<Vec<Snapshot>> {
+        let mut stmt = self.conn.prepare_cached("SELECT id, user_id, lesson_id, attempt_id, passed, timestamp, source FROM snapshots WHERE lesson_id = ?1 AND user_id IS ?2 ORDER BY timestamp, id")?;
+        let rows = stmt.query_map(params![lesson_id, user_id], snapshot_row)?;
+        Ok(rows.filter_map(Result::ok).collect())
+    }
+
+    pub fn snapshot(&self, id: i64) -> Result<Option<Snapshot>> {
+        Ok(self.conn
+            .prepare_cached("SELECT id, user_id, lesson_id, attempt_id, passed, timestamp, source FROM snapshots WHERE id = ?1")?
+            .query_row(params![id], snapshot_row)
+            .optional()?)
+    }
 }
 
-pub fn attempts_for_user(user_id: i64) -> Result<Vec<Attempt>> {
-    let conn = open()?;
-    let mut stmt = conn.prepare("SELECT id, user_id, lesson_id, passed, duration_secs, timestamp FROM attempts WHERE user_id = ?1 ORDER BY timestamp DESC")?;
-    let rows = stmt.query_map(params![user_id], |r| {
-        Ok(Attempt {
-            id: r.get(0)?,
-            user_id: r.get(1)?,
-            lesson_id: r.get(2)?,
-            passed: {
-                let v: i64 = r.get(3)?;
-                v != 0
-            },
-            duration_secs: r.get::<_, Option<i64>>(4)?.map(|v| v as u64),
-            timestamp: r.get(5)?,
-        })
-    })?;
-    Ok(rows.filter_map(Result::ok).collect())
+fn snapshot_row(r: &rusqlite::Row<'_>) -> rusqlite::Result<Snapshot> {
+    Ok(Snapshot {
+        id: r.get(0)?,
+        user_id: r.get(1)?,
+        lesson_id: r.get(2)?,
+        attempt_id: r.get(3)?,
+        passed: r.get(4)?,
+        timestamp: r.get(5)?,
+        source: r.get(6)?,
+    })
 }
 
-pub fn clear_attempts_for_user(user_id: i64) -> Result<()> {
-    let conn = open()?;
-    conn.execute("DELETE FROM attempts WHERE user_id = ?1", params![user_id])?;
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_and_authenticate() {
+        let db = Storage::open_in_memory().unwrap();
+        let u = db.register_user("ferris", "crab").unwrap();
+        assert!(db.authenticate("ferris", "wrong").unwrap().is_none());
+        let back = db.authenticate("ferris", "crab").unwrap().unwrap();
+        assert_eq!(back.id, u.id);
+        assert!(db.register_user("ferris", "again").is_err());
+    }
+
+    #[test]
+    fn attempts_roll_back_with_transaction() {
+        let db = Storage::open_in_memory().unwrap();
+        let uid = db.register_user("ferris", "crab").unwrap().id;
+        db.record_attempt(uid, "intro/variables", false, None, 10, None).unwrap();
+        let res: Result<()> = db.transaction(|db| {
+            db.record_attempt(uid, "intro/variables", true, Some(30), 20, None)?;
+            anyhow::bail!("abort")
+        });
+        assert!(res.is_err());
+        db.transaction(|db| db.record_attempt(uid, "intro/functions", true, Some(5), 30, Some("3f2a"))).unwrap();
+
+        let xs = db.attempts_for_user(uid).unwrap();
+        assert_eq!(xs.len(), 2);
+        assert_eq!((xs[0].lesson_id.as_str(), xs[0].content_hash.as_deref()), ("intro/functions", Some("3f2a")));
+        assert!(!xs[1].passed);
+        db.clear_attempts_for_user(uid).unwrap();
+        assert!(db.attempts_for_user(uid).unwrap().is_empty());
+    }
+
+    #[test]
+    fn ranks_players_by_window_and_lesson() {
+        let db = Storage::open_in_memory().unwrap();
+        let a = db.register_user("ferris", "crab").unwrap().id;
+        let b = db.register_user("corro", "crab").unwrap().id;
+        db.record_attempt(a, "intro/variables", false, None, 10, None).unwrap();
+        let id = db.record_attempt(a, "intro/variables", true, Some(90), 20, None).unwrap();
+        db.set_attempt_points(id, 10).unwrap();
+        let id = db.record_attempt(b, "intro/variables", true, Some(40), 100, None).unwrap();
+        db.set_attempt_points(id, 12).unwrap();
+        let id = db.record_attempt(a, "intro/functions", true, Some(30), 110, None).unwrap();
+        db.set_attempt_points(id, 8).unwrap();
+
+        let all = db.standings(0, None).unwrap();
+        assert_eq!(all.iter().map(|s| (s.name.as_str(), s.points, s.completed)).collect::<Vec<_>>(), vec![("ferris", 18, 2), ("corro", 12, 1)]);
+        assert_eq!(all[0].fastest_secs, Some(30));
+        let recent = db.standings(50, None).unwrap();
+        assert_eq!(recent.iter().map(|s| s.points).collect::<Vec<_>>(), vec![12, 8]);
+        let lesson = db.standings(0, Some("intro/variables")).unwrap();
+        assert_eq!(lesson[0].name, "corro");
+
+        let fastest = db.fastest_times(0, None).unwrap();
+        assert_eq!(fastest.iter().map(|r| (r.lesson_id.as_str(), r.name.as_str(), r.duration_secs)).collect::<Vec<_>>(),
+            vec![("intro/functions", "ferris", 30), ("intro/variables", "corro", 40)]);
+        assert_eq!(db.attempts_for_user(b).unwrap()[0].points, Some(12));
+    }
 }
\ No newline at end of file
@@ -1,11 +1,107 @@
 //! Utilities: data dir resolution, editor launching, formatting.
 
 use anyhow::{Context, Result};
-use std::{env, path::{Path, PathBuf}, process::Command};
+use std::{env, fs, path::{Path, PathBuf}, process::Command, sync::RwLock};
 
+static DATA_DIR_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+static PROFILE_OVERRIDE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Overrides the base data dir for this process (the CLI's `--data-dir`).
+pub fn set_data_dir(dir: Option<PathBuf>) {
+    *DATA_DIR_OVERRIDE.write().unwrap() = dir;
+}
+
+/// Selects a named profile for this process (the CLI's `--profile`).
+pub fn set_profile(name: Option<String>) -> Result<()> {
+    if let Some(n) = &name { validate_profile_name(n)?; }
+    *PROFILE_OVERRIDE.write().unwrap() = name;
+    Ok(())
+}
+
+/// Active profile: `--profile`, then `RUSTLEARN_PROFILE`. `None` is the default profile.
+pub fn profile() -> Option<String> {
+    if let Some(p) = PROFILE_OVERRIDE.read().unwrap().clone() { return Some(p); }
+    env::var("RUSTLEARN_PROFILE").ok().filter(|p| !p.is_empty() && validate_profile_name(p).is_ok())
+}
+
+pub fn validate_profile_name(name: &str) -> Result<()> {
+    let ok = !name.is_empty()
+        && name != "." && name != ".."
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !ok { anyhow::bail!("Invalid profile name '{}': use letters, digits, '-', '_' or '.'", name); }
+    Ok(())
+}
+
+/// Root holding every profile. Resolved from `--data-dir`, then `RUSTLEARN_HOME`,
+/// then an existing `~/.rustlearn`, then `$XDG_DATA_HOME/rustlearn` on Linux.
+pub fn base_data_dir() -> Result<PathBuf> {
+    if let Some(dir) = DATA_DIR_OVERRIDE.read().unwrap().clone() { return Ok(dir); }
+    if let Some(dir) = env::var_os("RUSTLEARN_HOME").filter(|v| !v.is_empty()) { return Ok(PathBuf::from(dir)); }
+    let home = dirs::home_dir().context("home dir not found")?;
+    let legacy = home.join(".rustlearn");
+    if legacy.exists() || !cfg!(target_os = "linux") { return Ok(legacy); }
+    Ok(dirs::data_dir().unwrap_or_else(|| home.join(".local").join("share")).join("rustlearn"))
+}
+
+/// Data dir of the active profile; progress, work copies and sandboxes live here.
 pub fn data_dir() -> Result<PathBuf> {
-    let base = dirs::home_dir().context("home dir not found")?;
-    Ok(base.join(".rustlearn"))
+    let base = base_data_dir()?;
+    Ok(match profile() { Some(name) => profile_dir(&base, &name), None => base })
+}
+
+fn profile_dir(base: &Path, name: &str) -> PathBuf {
+    base.join("profiles").join(name)
+}
+
+/// Named profiles that exist under the base data dir.
+pub fn list_profiles() -> Result<Vec<String>> {
+    let dir = base_data_dir()?.join("profiles");
+    if !dir.exists() { return Ok(Vec::new()); }
+    let mut out: Vec<String> = fs::read_dir(&dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    out.sort();
+    Ok(out)
+}
+
+/// Writes `data` to a temp file beside `path`, fsyncs it and renames it over
+/// `path`, so readers never observe a half-written file.
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    use std::io::Write;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+    let mut tmp = tempfile::NamedTempFile::new_in(dir).with_context(|| format!("create temp file in {}", dir.display()))?;
+    tmp.write_all(data)?;
+    tmp.as_file().sync_all()?;
+    tmp.persist(path).with_context(|| format!("replace {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        if let Ok(d) = fs::File::open(dir) { let _ = d.sync_all(); }
+    }
+    Ok(())
+}
+
+/// Exclusive advisory lock on `<path>.lock`, held until dropped. Used to
+/// serialize read-modify-write cycles between the CLI and the GUI.
+pub struct FileLock {
+    _file: fs::File,
+}
+
+pub fn lock_file(path: &Path) -> Result<FileLock> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".lock");
+    let lock_path = PathBuf::from(name);
+    if let Some(dir) = lock_path.parent() { fs::create_dir_all(dir)?; }
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("open lock file {}", lock_path.display()))?;
+    file.lock().with_context(|| format!("lock {}", lock_path.display()))?;
+    Ok(FileLock { _file: file })
 }
 
 pub fn open_in_editor(path: &PathBuf) -> Result<()> {
@@ -52,4 +148,62 @@ pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
         Command::new("xdg-open").arg(dir).status()?;
     }
     Ok(())
+}
+
+/// `YYYY-MM-DD` (UTC) for a Unix timestamp.
+pub fn format_date(timestamp: i64) -> String {
+    // Civil-from-days, after Howard Hinnant's date algorithms.
+    let z = timestamp.div_euclid(86_400) + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// Unix timestamp of midnight UTC on a `YYYY-MM-DD` date.
+pub fn parse_date(s: &str) -> Option<i64> {
+    let mut it = s.trim().splitn(3, '-');
+    let y: i64 = it.next()?.parse().ok()?;
+    let m: i64 = it.next()?.parse().ok()?;
+    let d: i64 = it.next()?.parse().ok()?;
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) { return None; }
+    // Days-from-civil, the inverse of `format_date`.
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let ts = (era * 146_097 + doe - 719_468) * 86_400;
+    (format_date(ts) == s.trim()).then_some(ts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_names() {
+        assert!(validate_profile_name("workshop-2026").is_ok());
+        assert!(validate_profile_name("team_a.v2").is_ok());
+        assert!(validate_profile_name("").is_err());
+        assert!(validate_profile_name("..").is_err());
+        assert!(validate_profile_name("a/b").is_err());
+        assert_eq!(profile_dir(Path::new("/data"), "x"), Path::new("/data/profiles/x"));
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_792_368_000), "2026-10-19");
+        assert_eq!(parse_date("2026-10-19"), Some(1_792_368_000));
+        assert_eq!(parse_date("2000-02-29"), Some(951_782_400));
+        assert_eq!(parse_date("2026-02-30"), None);
+        assert_eq!(parse_date("yesterday"), None);
+    }
 }
\ No newline at end of file